@@ -1,24 +1,30 @@
-fn find_coin(key: &[u8], start: &str) -> usize {
-    let mut n = 1;
-    loop {
-        let mut md5_input = key.to_vec();
-        md5_input.extend_from_slice(n.to_string().as_bytes());
-        let digest = md5::compute(md5_input);
-        let s = format!("{:x}", digest);
-        if s.starts_with(start) {
-            break;
-        }
-        n += 1;
-    }
-    n
+use std::io;
+
+use nom::character::complete::alphanumeric1;
+
+use util::hash_search::search_leading_zeros;
+use util::parse::parse_input;
+
+fn find_coin(key: &[u8], zeros: usize) -> u64 {
+    // The answer must be a positive number.
+    search_leading_zeros(key, zeros)
+        .map(|(n, _)| n)
+        .find(|&n| n > 0)
+        .expect("search is unbounded")
 }
 
-fn main() {
-    let key = b"yzbqklnj";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
-    let result_a = find_coin(key, "00000");
-    let result_b = find_coin(key, "000000");
+    // parse input
+    let key = parse_input(&input_data, alphanumeric1)?;
+    util::validate(&input_data, "", &key);
+
+    let result_a = find_coin(key.as_bytes(), 5);
+    let result_b = find_coin(key.as_bytes(), 6);
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -14,6 +14,10 @@ use nom::combinator::recognize;
 use nom::multi::many1;
 use nom::IResult;
 
+use util::tsp;
+use util::tsp::Objective;
+use util::tsp::Shape;
+
 #[derive(Clone, Debug)]
 struct Distance {
     location0: String,
@@ -59,42 +63,6 @@ fn input(i: &str) -> IResult<&str, Vec<Distance>> {
     many1(line)(i)
 }
 
-pub fn factorial(n: usize) -> usize {
-    let mut result = 1;
-    for i in 2..=n {
-        result *= i;
-    }
-    result
-}
-
-pub fn nth_permutation<T: Copy>(es: &[T], n: usize) -> Vec<T> {
-    let mut result = Vec::new();
-    let mut es = es.to_vec();
-    let mut n = n;
-    while !es.is_empty() {
-        let tail_len = es.len() - 1;
-        let tail_perms_len = factorial(tail_len);
-        let i = n / tail_perms_len;
-        n %= tail_perms_len;
-        let e = es.remove(i);
-        result.push(e);
-    }
-    result
-}
-
-fn path_cost<'map, 'path: 'map>(
-    path: &'path [&'map str],
-    distances: &'map HashMap<(&'map str, &'map str), i64>,
-) -> i64 {
-    let mut cost = 0;
-    for i in 0..(path.len() - 1) {
-        let loc0 = path[i];
-        let loc1 = path[i + 1];
-        cost += distances[&(loc0, loc1)];
-    }
-    cost
-}
-
 fn main() {
     let input_data = io::read_to_string(io::stdin()).expect("I/O error");
 
@@ -122,27 +90,21 @@ fn main() {
         distances.insert((&d.location1[..], &d.location0[..]), d.distance);
     }
 
-    let path0 = nth_permutation(&locations, 0);
-    let mut min_cost = path_cost(&path0[..], &distances);
-    for n in 1..factorial(locations.len()) {
-        let path = nth_permutation(&locations, n);
-        let cost = path_cost(&path[..], &distances);
-        if cost < min_cost {
-            min_cost = cost;
-        }
-    }
-    let result_a = min_cost;
-
-    let path0 = nth_permutation(&locations, 0);
-    let mut max_cost = path_cost(&path0[..], &distances);
-    for n in 1..factorial(locations.len()) {
-        let path = nth_permutation(&locations, n);
-        let cost = path_cost(&path[..], &distances);
-        if cost > max_cost {
-            max_cost = cost;
-        }
-    }
-    let result_b = max_cost;
+    let costs = locations
+        .iter()
+        .map(|&loc0| {
+            locations
+                .iter()
+                .map(|&loc1| distances.get(&(loc0, loc1)).copied().unwrap_or(0))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let route_a = tsp::solve(&costs, Objective::Min, Shape::Path, None).unwrap();
+    let result_a = route_a.cost;
+
+    let route_b = tsp::solve(&costs, Objective::Max, Shape::Path, None).unwrap();
+    let result_b = route_b.cost;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
@@ -1,3 +1,9 @@
+use std::io;
+
+use nom::character::complete::digit1;
+
+use util::parse::parse_input;
+
 fn look_and_say(s: &[i64]) -> Vec<i64> {
     let mut result = Vec::new();
     let mut iter = s.iter();
@@ -20,8 +26,12 @@ fn look_and_say(s: &[i64]) -> Vec<i64> {
     result
 }
 
-fn main() {
-    let input = "1113122113";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, digit1)?;
+    util::validate(&input_data, "", &input);
 
     let mut sequence = input
         .chars()
@@ -40,4 +50,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -1,5 +1,10 @@
 use std::collections::HashSet;
 use std::convert::TryFrom;
+use std::io;
+
+use nom::character::complete::alpha1;
+
+use util::parse::parse_input;
 
 fn next_char(c: char) -> Option<char> {
     let next = char::try_from(u32::from(c) + 1).unwrap();
@@ -77,8 +82,13 @@ fn next_valid_password(s: &[char]) -> Option<Vec<char>> {
     Some(s)
 }
 
-fn main() {
-    let input = "cqjxjnds";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, alpha1)?;
+    util::validate(&input_data, "", &input);
+
     let password = input.chars().collect::<Vec<char>>();
 
     let password_a = next_valid_password(&password).unwrap();
@@ -89,4 +99,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -15,6 +15,10 @@ use nom::combinator::recognize;
 use nom::multi::many1;
 use nom::IResult;
 
+use util::tsp;
+use util::tsp::Objective;
+use util::tsp::Shape;
+
 #[derive(Clone, Debug)]
 struct Record {
     name: String,
@@ -75,43 +79,6 @@ fn input(i: &str) -> IResult<&str, Vec<Record>> {
     many1(record)(i)
 }
 
-pub fn factorial(n: usize) -> usize {
-    let mut result = 1;
-    for i in 2..=n {
-        result *= i;
-    }
-    result
-}
-
-pub fn nth_permutation<T: Copy>(es: &[T], n: usize) -> Vec<T> {
-    let mut result = Vec::new();
-    let mut es = es.to_vec();
-    let mut n = n;
-    while !es.is_empty() {
-        let tail_len = es.len() - 1;
-        let tail_perms_len = factorial(tail_len);
-        let i = n / tail_perms_len;
-        n %= tail_perms_len;
-        let e = es.remove(i);
-        result.push(e);
-    }
-    result
-}
-
-fn seating_gain<'map, 'a: 'map>(
-    seating: &'a [&'map str],
-    gains: &'map HashMap<(&'map str, &'map str), i64>,
-) -> i64 {
-    let mut gain = 0;
-    for i in 0..(seating.len() - 1) {
-        let p0 = seating[i];
-        let p1 = seating[i + 1];
-        gain += gains[&(p0, p1)];
-    }
-    gain += gains[&(seating[0], seating[seating.len() - 1])];
-    gain
-}
-
 fn main() {
     let input_data = io::read_to_string(io::stdin()).expect("I/O error");
 
@@ -141,33 +108,23 @@ fn main() {
         *entry += r.gain;
     }
 
-    let seating0 = nth_permutation(&people, 0);
-    let mut max_gain = seating_gain(&seating0[..], &gains);
-    for n in 1..factorial(people.len()) {
-        let seating = nth_permutation(&people, n);
-        let gain = seating_gain(&seating[..], &gains);
-        if gain > max_gain {
-            max_gain = gain;
-        }
-    }
-    let result_a = max_gain;
-
-    people.push("self");
-    for p in &people {
-        gains.insert((&p[..], "self"), 0);
-        gains.insert(("self", &p[..]), 0);
-    }
-
-    let seating0 = nth_permutation(&people, 0);
-    let mut max_gain = seating_gain(&seating0[..], &gains);
-    for n in 1..factorial(people.len()) {
-        let seating = nth_permutation(&people, n);
-        let gain = seating_gain(&seating[..], &gains);
-        if gain > max_gain {
-            max_gain = gain;
-        }
-    }
-    let result_b = max_gain;
+    let costs = people
+        .iter()
+        .map(|&p0| {
+            people
+                .iter()
+                .map(|&p1| gains.get(&(p0, p1)).copied().unwrap_or(0))
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+
+    let seating_a = tsp::solve(&costs, Objective::Max, Shape::Tour, None).unwrap();
+    let result_a = seating_a.cost;
+
+    // Sitting between two people gains nothing for both sides,
+    // so it cuts the table into an open path.
+    let seating_b = tsp::solve(&costs, Objective::Max, Shape::Path, None).unwrap();
+    let result_b = seating_b.cost;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
@@ -1,8 +1,17 @@
+use std::io;
+
 use jmath_factor::Factorizer;
 use jmath_factor::TableFactorizer;
 
-fn main() {
-    let input = 29000000;
+use util::parse::parse_input;
+use util::parse::uint;
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, uint)?;
+    util::validate(&input_data, "", &input);
 
     let limit = input / 10;
 
@@ -30,4 +39,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
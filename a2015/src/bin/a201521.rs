@@ -1,127 +1,147 @@
+use std::env;
+use std::fs;
 use std::io;
-use std::iter::once;
-use std::str::FromStr;
 
+use nom::bytes::complete::is_not;
 use nom::bytes::complete::tag;
-use nom::character::complete::digit1;
+use nom::character::complete::alpha1;
+use nom::character::complete::char;
 use nom::character::complete::line_ending;
-use nom::combinator::map_res;
+use nom::character::complete::space1;
 use nom::combinator::recognize;
+use nom::multi::many1;
+use nom::multi::separated_list1;
 use nom::IResult;
 
+use util::parse::parse_input;
+use util::parse::uint;
+
+/// The shop as given in the puzzle, one section per item category.
+const SHOP: &str = "\
+Weapons:    Cost  Damage  Armor
+Dagger        8     4       0
+Shortsword   10     5       0
+Warhammer    25     6       0
+Longsword    40     7       0
+Greataxe     74     8       0
+
+Armor:      Cost  Damage  Armor
+Leather      13     0       1
+Chainmail    31     0       2
+Splintmail   53     0       3
+Bandedmail   75     0       4
+Platemail   102     0       5
+
+Rings:      Cost  Damage  Armor
+Damage +1    25     1       0
+Damage +2    50     2       0
+Damage +3   100     3       0
+Defense +1   20     0       1
+Defense +2   40     0       2
+Defense +3   80     0       3
+";
+
+/// The minimal and maximal number of items bought from each category.
+const LIMITS: [(&str, usize, usize); 3] = [("Weapons", 1, 1), ("Armor", 0, 1), ("Rings", 0, 2)];
+
+const PLAYER_HIT_POINTS: i64 = 100;
+
 #[derive(Clone, Debug)]
 struct Item {
     #[allow(unused)]
-    name: &'static str,
+    name: String,
     cost: i64,
     damage: i64,
     armor: i64,
 }
 
-const WEAPONS: [Item; 5] = [
-    Item {
-        name: "Dagger",
-        cost: 8,
-        damage: 4,
-        armor: 0,
-    },
-    Item {
-        name: "Shortsword",
-        cost: 10,
-        damage: 5,
-        armor: 0,
-    },
-    Item {
-        name: "Warhammer",
-        cost: 25,
-        damage: 6,
-        armor: 0,
-    },
-    Item {
-        name: "Longsword",
-        cost: 40,
-        damage: 7,
-        armor: 0,
-    },
-    Item {
-        name: "Greataxe",
-        cost: 74,
-        damage: 8,
-        armor: 0,
-    },
-];
+#[derive(Clone, Debug)]
+struct Category {
+    name: String,
+    items: Vec<Item>,
+}
+
+fn item(i: &str) -> IResult<&str, Item> {
+    // Item names may contain single spaces, columns are separated by several.
+    let (i, name) = recognize(separated_list1(char(' '), is_not(" \r\n")))(i)?;
+    let (i, _) = space1(i)?;
+    let (i, cost) = uint(i)?;
+    let (i, _) = space1(i)?;
+    let (i, damage) = uint(i)?;
+    let (i, _) = space1(i)?;
+    let (i, armor) = uint(i)?;
+    let (i, _) = line_ending(i)?;
+    let name = name.to_string();
+    Ok((
+        i,
+        Item {
+            name,
+            cost,
+            damage,
+            armor,
+        },
+    ))
+}
 
-const ARMOR: [Item; 5] = [
-    Item {
-        name: "Leather",
-        cost: 13,
-        damage: 0,
-        armor: 1,
-    },
-    Item {
-        name: "Chainmail",
-        cost: 31,
-        damage: 0,
-        armor: 2,
-    },
-    Item {
-        name: "Splintmail",
-        cost: 53,
-        damage: 0,
-        armor: 3,
-    },
-    Item {
-        name: "Bandedmail",
-        cost: 75,
-        damage: 0,
-        armor: 4,
-    },
-    Item {
-        name: "Platemail",
-        cost: 102,
-        damage: 0,
-        armor: 5,
-    },
-];
+fn category(i: &str) -> IResult<&str, Category> {
+    let (i, name) = alpha1(i)?;
+    let (i, _) = tag(":")(i)?;
+    let (i, _) = space1(i)?;
+    let (i, _) = tag("Cost")(i)?;
+    let (i, _) = space1(i)?;
+    let (i, _) = tag("Damage")(i)?;
+    let (i, _) = space1(i)?;
+    let (i, _) = tag("Armor")(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, items) = many1(item)(i)?;
+    let name = name.to_string();
+    Ok((i, Category { name, items }))
+}
 
-const RINGS: [Item; 6] = [
-    Item {
-        name: "Damage +1",
-        cost: 25,
-        damage: 1,
-        armor: 0,
-    },
-    Item {
-        name: "Damage +2",
-        cost: 50,
-        damage: 2,
-        armor: 0,
-    },
-    Item {
-        name: "Damage +3",
-        cost: 100,
-        damage: 3,
-        armor: 0,
-    },
-    Item {
-        name: "Defense +1",
-        cost: 20,
-        damage: 0,
-        armor: 1,
-    },
-    Item {
-        name: "Defense +2",
-        cost: 40,
-        damage: 0,
-        armor: 2,
-    },
-    Item {
-        name: "Defense +3",
-        cost: 80,
-        damage: 0,
-        armor: 3,
-    },
-];
+fn shop(i: &str) -> IResult<&str, Vec<Category>> {
+    separated_list1(line_ending, category)(i)
+}
+
+/// Returns the subsets of the items with a number of elements in the given range.
+fn subsets(items: &[Item], min_len: usize, max_len: usize) -> Vec<Vec<Item>> {
+    let mut result = Vec::new();
+    if min_len == 0 {
+        result.push(Vec::new());
+    }
+    if max_len > 0 {
+        for (i, item) in items.iter().enumerate() {
+            let rest = &items[(i + 1)..];
+            for mut subset in subsets(rest, min_len.saturating_sub(1), max_len - 1) {
+                subset.insert(0, item.clone());
+                result.push(subset);
+            }
+        }
+    }
+    result
+}
+
+/// Returns all combinations of items that may be bought in the shop.
+fn loadouts(shop: &[Category]) -> util::Result<Vec<Vec<Item>>> {
+    let mut result = vec![Vec::new()];
+    for (name, min_len, max_len) in LIMITS {
+        let category = shop
+            .iter()
+            .find(|c| c.name == name)
+            .ok_or_else(|| util::runtime_error!("category {} missing in shop", name))?;
+        let choices = subsets(&category.items, min_len, max_len);
+        result = result
+            .iter()
+            .flat_map(|loadout: &Vec<Item>| {
+                choices.iter().map(move |choice| {
+                    let mut loadout = loadout.clone();
+                    loadout.extend(choice.iter().cloned());
+                    loadout
+                })
+            })
+            .collect::<Vec<_>>();
+    }
+    Ok(result)
+}
 
 #[derive(Clone, Debug)]
 struct Character {
@@ -130,10 +150,6 @@ struct Character {
     armor: i64,
 }
 
-fn uint(i: &str) -> IResult<&str, i64> {
-    map_res(recognize(digit1), FromStr::from_str)(i)
-}
-
 fn input(i: &str) -> IResult<&str, Character> {
     let (i, _) = tag("Hit Points: ")(i)?;
     let (i, hit_points) = uint(i)?;
@@ -173,88 +189,39 @@ fn player_wins(player: &Character, boss: &Character) -> bool {
     }
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
-
-    // parse input
-    let result = input(&input_data);
-    //println!("{:?}", result);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
-    let input = result.unwrap().1;
-    //println!("{:?}", input);
+    // An optional argument names a file with a different shop.
+    let shop_data = match env::args().nth(1) {
+        Some(path) => fs::read_to_string(path)?,
+        None => SHOP.to_string(),
+    };
+    let shop = parse_input(&shop_data, shop)?;
 
-    let boss = input;
-
-    // try all allowed combinations of items
-    let mut min_cost = std::i64::MAX;
-    for w in WEAPONS {
-        for opt_a in once(None).chain(ARMOR.iter().map(Some)) {
-            for b in 0..(1 << RINGS.len()) {
-                let mut items = Vec::new();
-                items.push(w.clone());
-                if let Some(armor) = opt_a {
-                    items.push(armor.clone());
-                }
-                let mut ring_count = 0;
-                for i in 0..RINGS.len() {
-                    if b & (1 << i) != 0 {
-                        items.push(RINGS[i].clone());
-                        ring_count += 1;
-                    }
-                }
-                if ring_count <= 2 {
-                    let cost = items.iter().map(|item| item.cost).sum();
-                    let damage = items.iter().map(|item| item.damage).sum();
-                    let armor = items.iter().map(|item| item.armor).sum();
-                    let player = Character {
-                        hit_points: 100,
-                        damage,
-                        armor,
-                    };
-                    if player_wins(&player, &boss) {
-                        min_cost = min_cost.min(cost);
-                    }
-                }
-            }
-        }
-    }
-    let result_a = min_cost;
+    // parse input
+    let boss = parse_input(&input_data, input)?;
 
-    // try all allowed combinations of items
+    let mut min_cost = i64::MAX;
     let mut max_cost = 0;
-    for w in WEAPONS {
-        for opt_a in once(None).chain(ARMOR.iter().map(Some)) {
-            for b in 0..(1 << RINGS.len()) {
-                let mut items = Vec::new();
-                items.push(w.clone());
-                if let Some(armor) = opt_a {
-                    items.push(armor.clone());
-                }
-                let mut ring_count = 0;
-                for i in 0..RINGS.len() {
-                    if b & (1 << i) != 0 {
-                        items.push(RINGS[i].clone());
-                        ring_count += 1;
-                    }
-                }
-                if ring_count <= 2 {
-                    let cost = items.iter().map(|item| item.cost).sum();
-                    let damage = items.iter().map(|item| item.damage).sum();
-                    let armor = items.iter().map(|item| item.armor).sum();
-                    let player = Character {
-                        hit_points: 100,
-                        damage,
-                        armor,
-                    };
-                    if !player_wins(&player, &boss) {
-                        max_cost = max_cost.max(cost);
-                    }
-                }
-            }
+    for items in loadouts(&shop)? {
+        let cost = items.iter().map(|item| item.cost).sum();
+        let player = Character {
+            hit_points: PLAYER_HIT_POINTS,
+            damage: items.iter().map(|item| item.damage).sum(),
+            armor: items.iter().map(|item| item.armor).sum(),
+        };
+        if player_wins(&player, &boss) {
+            min_cost = min_cost.min(cost);
+        } else {
+            max_cost = max_cost.max(cost);
         }
     }
+    let result_a = min_cost;
     let result_b = max_cost;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -1,23 +1,28 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::collections::HashSet;
+use std::env;
+use std::fs;
 use std::io;
-use std::str::FromStr;
 
+use nom::branch::alt;
+use nom::bytes::complete::is_not;
 use nom::bytes::complete::tag;
-use nom::character::complete::digit1;
 use nom::character::complete::line_ending;
-use nom::combinator::map_res;
-use nom::combinator::recognize;
+use nom::combinator::value;
+use nom::multi::many1;
+use nom::multi::separated_list1;
 use nom::IResult;
 
+use util::parse::parse_input;
+use util::parse::uint;
+
 #[derive(Clone, Debug)]
 struct Input {
     hit_points: i64,
     damage: i64,
 }
 
-fn uint(i: &str) -> IResult<&str, i64> {
-    map_res(recognize(digit1), FromStr::from_str)(i)
-}
-
 fn input(i: &str) -> IResult<&str, Input> {
     let (i, _) = tag("Hit Points: ")(i)?;
     let (i, hit_points) = uint(i)?;
@@ -28,152 +33,431 @@ fn input(i: &str) -> IResult<&str, Input> {
     Ok((i, Input { hit_points, damage }))
 }
 
+/// The player and the spells as described in the puzzle.
+///
+/// A spell with a number of turns starts an effect which acts at the start
+/// of each turn while it lasts, otherwise it acts instantly.
+/// Armor is provided while the effect is active.
+const RULES: &str = "\
+Player: hit points 50, mana 500
+Magic Missile: cost 53, damage 4
+Drain: cost 73, damage 2, heal 2
+Shield: cost 113, turns 6, armor 7
+Poison: cost 173, turns 6, damage 3
+Recharge: cost 229, turns 5, mana 101
+";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Attribute {
+    Cost,
+    Turns,
+    Damage,
+    Heal,
+    Armor,
+    Mana,
+}
+
+#[derive(Clone, Debug, Default)]
+struct Spell {
+    name: String,
+    cost: i64,
+    /// The number of turns of the effect, or 0 for an instant spell.
+    turns: i64,
+    damage: i64,
+    heal: i64,
+    armor: i64,
+    mana: i64,
+}
+impl Spell {
+    fn is_effect(&self) -> bool {
+        self.turns > 0
+    }
+    /// Describes what the spell does, for the battle log.
+    fn description(&self) -> String {
+        let mut parts = Vec::new();
+        if self.damage > 0 {
+            parts.push(format!("deals {} damage", self.damage));
+        }
+        if self.heal > 0 {
+            parts.push(format!("heals {} hit points", self.heal));
+        }
+        if self.armor > 0 {
+            parts.push(format!("provides {} armor", self.armor));
+        }
+        if self.mana > 0 {
+            parts.push(format!("provides {} mana", self.mana));
+        }
+        parts.join(" and ")
+    }
+}
+
 #[derive(Clone, Debug)]
+struct Rules {
+    hit_points: i64,
+    mana: i64,
+    spells: Vec<Spell>,
+}
+
+fn attribute(i: &str) -> IResult<&str, (Attribute, i64)> {
+    let (i, attribute) = alt((
+        value(Attribute::Cost, tag("cost ")),
+        value(Attribute::Turns, tag("turns ")),
+        value(Attribute::Damage, tag("damage ")),
+        value(Attribute::Heal, tag("heal ")),
+        value(Attribute::Armor, tag("armor ")),
+        value(Attribute::Mana, tag("mana ")),
+    ))(i)?;
+    let (i, n) = uint(i)?;
+    Ok((i, (attribute, n)))
+}
+
+fn spell(i: &str) -> IResult<&str, Spell> {
+    let (i, name) = is_not(":\r\n")(i)?;
+    let (i, _) = tag(": ")(i)?;
+    let (i, attributes) = separated_list1(tag(", "), attribute)(i)?;
+    let (i, _) = line_ending(i)?;
+    let mut spell = Spell {
+        name: name.to_string(),
+        ..Spell::default()
+    };
+    for (attribute, n) in attributes {
+        match attribute {
+            Attribute::Cost => spell.cost = n,
+            Attribute::Turns => spell.turns = n,
+            Attribute::Damage => spell.damage = n,
+            Attribute::Heal => spell.heal = n,
+            Attribute::Armor => spell.armor = n,
+            Attribute::Mana => spell.mana = n,
+        }
+    }
+    Ok((i, spell))
+}
+
+fn rules(i: &str) -> IResult<&str, Rules> {
+    let (i, _) = tag("Player: hit points ")(i)?;
+    let (i, hit_points) = uint(i)?;
+    let (i, _) = tag(", mana ")(i)?;
+    let (i, mana) = uint(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, spells) = many1(spell)(i)?;
+    Ok((
+        i,
+        Rules {
+            hit_points,
+            mana,
+            spells,
+        },
+    ))
+}
+
+/// The state of a battle when the player is about to cast a spell.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct State {
-    hard: bool,
-    turn: i64,
-    player_turn: bool,
     player_hit_points: i64,
     player_mana: i64,
     boss_hit_points: i64,
-    boss_damage: i64,
-    shield_turns: i64,
-    poison_turns: i64,
-    recharge_turns: i64,
-    mana_spent: i64,
-}
-impl State {
-    fn start(input: &Input, hard: bool) -> State {
-        State {
-            hard,
-            turn: 1,
-            player_turn: true,
-            player_hit_points: 50,
-            player_mana: 500,
-            boss_hit_points: input.hit_points,
-            boss_damage: input.damage,
-            shield_turns: 0,
-            poison_turns: 0,
-            recharge_turns: 0,
-            mana_spent: 0,
+    /// The remaining turns of the effect of each spell.
+    timers: Vec<i64>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Outcome {
+    Won,
+    Lost,
+    Ongoing(State),
+}
+
+/// Collects the lines of a battle log, if enabled.
+struct Log {
+    lines: Option<Vec<String>>,
+}
+impl Log {
+    fn push<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if let Some(lines) = &mut self.lines {
+            lines.push(f());
         }
     }
+}
 
-    /// returns the minimum mana spent for a player win from this state,
-    /// or `None` if the boss wins.
-    fn min_win_mana(&self, min_mana: i64) -> i64 {
-        if self.boss_hit_points <= 0 {
-            self.mana_spent
-        } else if self.player_hit_points <= 0 {
-            std::i64::MAX
-        } else {
-            let mut after_effect_state = self.clone();
-
-            after_effect_state.turn += 1;
-            after_effect_state.player_turn = !self.player_turn;
+#[derive(Clone, Debug)]
+struct Battle<'a> {
+    rules: &'a Rules,
+    boss: Input,
+    /// In hard mode the player loses a hit point at the start of each of their turns.
+    hard: bool,
+}
+impl<'a> Battle<'a> {
+    /// Applies the effects at the start of a turn, and returns the armor of the player.
+    fn start_turn(&self, state: &mut State, player_turn: bool, log: &mut Log) -> i64 {
+        let who = if player_turn { "Player" } else { "Boss" };
+        log.push(|| format!("-- {} turn --", who));
+        log.push(|| {
+            format!(
+                "- Player has {} hit points, {} mana",
+                state.player_hit_points, state.player_mana
+            )
+        });
+        log.push(|| format!("- Boss has {} hit points", state.boss_hit_points));
 
-            if self.hard {
-                after_effect_state.player_hit_points -= 1;
-                if self.player_hit_points <= 0 {
-                    return std::i64::MAX;
+        if self.hard && player_turn {
+            state.player_hit_points -= 1;
+            log.push(|| "Player loses 1 hit point.".to_string());
+        }
+        let mut armor = 0;
+        for (spell, timer) in self.rules.spells.iter().zip(state.timers.iter_mut()) {
+            if *timer > 0 {
+                state.boss_hit_points -= spell.damage;
+                state.player_hit_points += spell.heal;
+                state.player_mana += spell.mana;
+                armor += spell.armor;
+                *timer -= 1;
+                log.push(|| {
+                    format!(
+                        "{} {}; its timer is now {}.",
+                        spell.name,
+                        spell.description(),
+                        timer
+                    )
+                });
+                if *timer == 0 {
+                    log.push(|| format!("{} wears off.", spell.name));
                 }
             }
+        }
+        armor
+    }
 
-            // apply effects
-            let mut player_armor = 0;
-            if self.shield_turns > 0 {
-                player_armor += 7;
-                after_effect_state.shield_turns -= 1;
-            }
-            if self.poison_turns > 0 {
-                after_effect_state.boss_hit_points -= 3;
-                after_effect_state.poison_turns -= 1;
-                if after_effect_state.boss_hit_points <= 0 {
-                    return after_effect_state.mana_spent;
-                }
-            }
-            if self.recharge_turns > 0 {
-                after_effect_state.player_mana += 101;
-                after_effect_state.recharge_turns -= 1;
-            }
+    /// Checks whether one side has died.
+    fn outcome(&self, state: State, log: &mut Log) -> Outcome {
+        if state.player_hit_points <= 0 {
+            log.push(|| "This kills the player, and the boss wins.".to_string());
+            Outcome::Lost
+        } else if state.boss_hit_points <= 0 {
+            log.push(|| "This kills the boss, and the player wins.".to_string());
+            Outcome::Won
+        } else {
+            Outcome::Ongoing(state)
+        }
+    }
 
-            if self.player_turn {
-                // try all possible moves
-
-                let mut min_mana = min_mana;
-                if after_effect_state.player_mana >= 53 {
-                    let mut next_state = after_effect_state.clone();
-                    next_state.player_mana -= 53;
-                    next_state.mana_spent += 53;
-                    if next_state.mana_spent < min_mana {
-                        next_state.boss_hit_points -= 4;
-                        min_mana = min_mana.min(next_state.min_win_mana(min_mana));
-                    }
-                }
-                if after_effect_state.player_mana >= 73 {
-                    let mut next_state = after_effect_state.clone();
-                    next_state.player_mana -= 73;
-                    next_state.mana_spent += 73;
-                    if next_state.mana_spent < min_mana {
-                        next_state.boss_hit_points -= 2;
-                        next_state.player_hit_points += 2;
-                        min_mana = min_mana.min(next_state.min_win_mana(min_mana));
-                    }
-                }
-                if after_effect_state.player_mana >= 113 && after_effect_state.shield_turns == 0 {
-                    let mut next_state = after_effect_state.clone();
-                    next_state.player_mana -= 113;
-                    next_state.mana_spent += 113;
-                    if next_state.mana_spent < min_mana {
-                        next_state.shield_turns = 6;
-                        min_mana = min_mana.min(next_state.min_win_mana(min_mana));
+    /// Returns the outcome at the first decision of the player.
+    fn start(&self, log: &mut Log) -> Outcome {
+        let mut state = State {
+            player_hit_points: self.rules.hit_points,
+            player_mana: self.rules.mana,
+            boss_hit_points: self.boss.hit_points,
+            timers: vec![0; self.rules.spells.len()],
+        };
+        self.start_turn(&mut state, true, log);
+        self.outcome(state, log)
+    }
+
+    /// Returns true if the player can cast the spell.
+    fn can_cast(&self, state: &State, spell_index: usize) -> bool {
+        let spell = &self.rules.spells[spell_index];
+        spell.cost <= state.player_mana && state.timers[spell_index] == 0
+    }
+
+    /// Plays a round of the player casting a spell and the boss attacking.
+    ///
+    /// The spell must be castable.
+    /// Returns the outcome at the next decision of the player.
+    fn round(&self, state: &State, spell_index: usize, log: &mut Log) -> Outcome {
+        let spell = &self.rules.spells[spell_index];
+        let mut state = state.clone();
+
+        // The player casts the spell.
+        state.player_mana -= spell.cost;
+        log.push(|| format!("Player casts {}.", spell.name));
+        if spell.is_effect() {
+            state.timers[spell_index] = spell.turns;
+        } else {
+            state.boss_hit_points -= spell.damage;
+            state.player_hit_points += spell.heal;
+            state.player_mana += spell.mana;
+            log.push(|| format!("{} {}.", spell.name, spell.description()));
+        }
+        let mut state = match self.outcome(state, log) {
+            Outcome::Ongoing(state) => state,
+            outcome => return outcome,
+        };
+
+        // The boss attacks.
+        log.push(String::new);
+        let armor = self.start_turn(&mut state, false, log);
+        let mut state = match self.outcome(state, log) {
+            Outcome::Ongoing(state) => state,
+            outcome => return outcome,
+        };
+        let damage = (self.boss.damage - armor).max(1);
+        state.player_hit_points -= damage;
+        log.push(|| format!("Boss attacks for {} damage.", damage));
+        let mut state = match self.outcome(state, log) {
+            Outcome::Ongoing(state) => state,
+            outcome => return outcome,
+        };
+
+        log.push(String::new);
+        self.start_turn(&mut state, true, log);
+        self.outcome(state, log)
+    }
+
+    /// Returns a spell sequence with the least mana spent for a player win,
+    /// together with the mana spent, or `None` if the player can't win.
+    fn best_spells(&self) -> Option<(i64, Vec<usize>)> {
+        // Nodes of the search tree with the outcome, their parent and the spell cast.
+        let mut nodes = vec![(self.start(&mut Log { lines: None }), None, None)];
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((0, 0)));
+        let mut visited = HashSet::new();
+        while let Some(Reverse((mana, index))) = queue.pop() {
+            let state = match &nodes[index].0 {
+                Outcome::Won => {
+                    // Reconstruct the spell sequence.
+                    let mut spells = Vec::new();
+                    let mut index = index;
+                    while let (_, Some(parent), Some(spell)) = nodes[index] {
+                        spells.push(spell);
+                        index = parent;
                     }
+                    spells.reverse();
+                    return Some((mana, spells));
                 }
-                if after_effect_state.player_mana >= 173 && after_effect_state.poison_turns == 0 {
-                    let mut next_state = after_effect_state.clone();
-                    next_state.player_mana -= 173;
-                    next_state.mana_spent += 173;
-                    if next_state.mana_spent < min_mana {
-                        next_state.poison_turns = 6;
-                        min_mana = min_mana.min(next_state.min_win_mana(min_mana));
-                    }
+                Outcome::Lost => continue,
+                Outcome::Ongoing(state) => state.clone(),
+            };
+            if !visited.insert(state.clone()) {
+                continue;
+            }
+            for spell_index in 0..self.rules.spells.len() {
+                if self.can_cast(&state, spell_index) {
+                    let outcome = self.round(&state, spell_index, &mut Log { lines: None });
+                    let new_mana = mana + self.rules.spells[spell_index].cost;
+                    queue.push(Reverse((new_mana, nodes.len())));
+                    nodes.push((outcome, Some(index), Some(spell_index)));
                 }
-                if after_effect_state.player_mana >= 229 && after_effect_state.recharge_turns == 0 {
-                    let mut next_state = after_effect_state;
-                    next_state.player_mana -= 229;
-                    next_state.mana_spent += 229;
-                    if next_state.mana_spent < min_mana {
-                        next_state.recharge_turns = 5;
-                        min_mana = min_mana.min(next_state.min_win_mana(min_mana));
+            }
+        }
+        None
+    }
+
+    /// Returns the log of a battle with the given spells cast by the player.
+    fn log(&self, spells: &[usize]) -> Vec<String> {
+        let mut log = Log {
+            lines: Some(Vec::new()),
+        };
+        let mut outcome = self.start(&mut log);
+        for &spell_index in spells {
+            outcome = match outcome {
+                Outcome::Ongoing(state) => {
+                    if self.can_cast(&state, spell_index) {
+                        self.round(&state, spell_index, &mut log)
+                    } else {
+                        let name = &self.rules.spells[spell_index].name;
+                        log.push(|| format!("Player can't cast {}.", name));
+                        break;
                     }
                 }
-                min_mana
-            } else {
-                // boss turn
-                let damage = (self.boss_damage - player_armor).max(1);
-                after_effect_state.player_hit_points -= damage;
-                after_effect_state.min_win_mana(min_mana)
-            }
+                _ => break,
+            };
         }
+        log.lines.unwrap_or_default()
     }
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
-
-    // parse input
-    let result = input(&input_data);
-    //println!("{:?}", result);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
-    let input = result.unwrap().1;
-    // println!("{:?}", input);
+    // Options are `--log` to print the battles,
+    // and the name of a file with different rules.
+    let mut print_log = false;
+    let mut rules_data = RULES.to_string();
+    for arg in env::args().skip(1) {
+        if arg == "--log" {
+            print_log = true;
+        } else {
+            rules_data = fs::read_to_string(arg)?;
+        }
+    }
+    let rules = parse_input(&rules_data, rules)?;
 
-    let state = State::start(&input, false);
-    let result_a = state.min_win_mana(std::i64::MAX);
+    // parse input
+    let boss = parse_input(&input_data, input)?;
 
-    let state = State::start(&input, true);
-    let result_b = state.min_win_mana(std::i64::MAX);
+    let mut results = Vec::new();
+    for hard in [false, true] {
+        let battle = Battle {
+            rules: &rules,
+            boss: boss.clone(),
+            hard,
+        };
+        let (mana, spells) = battle
+            .best_spells()
+            .ok_or_else(|| util::runtime_error!("the player can't win"))?;
+        if print_log {
+            for line in battle.log(&spells) {
+                println!("{}", line);
+            }
+            println!();
+        }
+        results.push(mana);
+    }
+    let result_a = results[0];
+    let result_b = results[1];
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_input;
+    use crate::rules;
+    use crate::Battle;
+    use crate::Input;
+    use crate::Rules;
+    use crate::RULES;
+
+    fn example_battle(rules: &Rules, boss_hit_points: i64) -> Battle<'_> {
+        Battle {
+            rules,
+            boss: Input {
+                hit_points: boss_hit_points,
+                damage: 8,
+            },
+            hard: false,
+        }
+    }
+
+    fn example_rules() -> Rules {
+        let mut rules = parse_input(RULES, rules).unwrap();
+        rules.hit_points = 10;
+        rules.mana = 250;
+        rules
+    }
+
+    #[test]
+    fn test_best_spells() {
+        let rules = example_rules();
+        let battle = example_battle(&rules, 13);
+        // Poison, Magic Missile
+        assert_eq!(battle.best_spells(), Some((226, vec![3, 0])));
+    }
+
+    #[test]
+    fn test_log() {
+        let rules = example_rules();
+        let battle = example_battle(&rules, 14);
+        // Recharge, Shield, Drain, Poison, Magic Missile
+        let log = battle.log(&[4, 2, 1, 3, 0]);
+        assert_eq!(
+            log.last().unwrap(),
+            "This kills the boss, and the player wins."
+        );
+    }
 }
@@ -1,19 +1,20 @@
-use std::convert::TryFrom;
+use std::io;
+
+use nom::character::complete::alphanumeric1;
+
+use util::hash_search::nibble;
+use util::hash_search::search_leading_zeros;
+use util::parse::parse_input;
+
+fn hex_char(n: u8) -> char {
+    char::from_digit(u32::from(n), 16).unwrap()
+}
 
 fn find_password_1(door_id: &[u8]) -> String {
-    let mut result = String::new();
-    let mut n = 0;
-    while result.len() < 8 {
-        let mut md5_input = door_id.to_vec();
-        md5_input.extend_from_slice(n.to_string().as_bytes());
-        let digest = md5::compute(md5_input);
-        let s = format!("{:x}", digest);
-        if s.starts_with("00000") {
-            result.push(s.chars().nth(5).unwrap());
-        }
-        n += 1;
-    }
-    result
+    search_leading_zeros(door_id, 5)
+        .take(8)
+        .map(|(_, digest)| hex_char(nibble(&digest, 5)))
+        .collect::<String>()
 }
 
 fn partial_password_to_string(cs: &[Option<char>]) -> String {
@@ -27,34 +28,34 @@ fn partial_password_to_string(cs: &[Option<char>]) -> String {
 
 fn find_password_2(door_id: &[u8]) -> String {
     let mut cs = (0..8).map(|_| None).collect::<Vec<_>>();
-    let mut n = 0;
     let mut count = 0;
-    while count < 8 {
-        let mut md5_input = door_id.to_vec();
-        md5_input.extend_from_slice(n.to_string().as_bytes());
-        let digest = md5::compute(md5_input);
-        let s = format!("{:x}", digest);
-        if s.starts_with("00000") {
-            let mut iter = s.chars().skip(5);
-            let pos = usize::try_from(iter.next().unwrap().to_digit(16).unwrap()).unwrap();
-            let c = iter.next().unwrap();
-            if (0..8).contains(&pos) && cs[pos] == None {
-                cs[pos] = Some(c);
-                count += 1;
-                println!("{}", partial_password_to_string(&cs));
+    for (_, digest) in search_leading_zeros(door_id, 5) {
+        let pos = usize::from(nibble(&digest, 5));
+        let c = hex_char(nibble(&digest, 6));
+        if (0..8).contains(&pos) && cs[pos].is_none() {
+            cs[pos] = Some(c);
+            count += 1;
+            println!("{}", partial_password_to_string(&cs));
+            if count >= 8 {
+                break;
             }
         }
-        n += 1;
     }
     partial_password_to_string(&cs)
 }
 
-fn main() {
-    let door_id = b"ojvtpuvg";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
-    let result_a = find_password_1(door_id);
-    let result_b = find_password_2(door_id);
+    // parse input
+    let door_id = parse_input(&input_data, alphanumeric1)?;
+    util::validate(&input_data, "", &door_id);
+
+    let result_a = find_password_1(door_id.as_bytes());
+    let result_b = find_password_2(door_id.as_bytes());
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
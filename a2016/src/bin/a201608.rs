@@ -11,6 +11,8 @@ use nom::combinator::recognize;
 use nom::multi::many1;
 use nom::IResult;
 
+use util::ocr::recognize_grid;
+
 #[derive(Clone, Copy, Debug)]
 enum Instruction {
     Rect(usize, usize),
@@ -126,8 +128,8 @@ fn input(i: &str) -> IResult<&str, Vec<Instruction>> {
     many1(instruction)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -146,9 +148,11 @@ fn main() {
     }
     let result_a = state.lit_pixel_count();
 
-    println!("{}", state);
-    let result_b = "EOARGPHYAO";
+    // println!("{}", state);
+    let result_b = recognize_grid(&state.screen)?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
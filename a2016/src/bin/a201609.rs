@@ -1,5 +1,7 @@
+use std::env;
 use std::fmt;
 use std::io;
+use std::io::Write;
 use std::str::FromStr;
 
 use nom::branch::alt;
@@ -136,8 +138,131 @@ fn input(i: &str) -> IResult<&str, CompressedData> {
     Ok((i, cd))
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Version {
+    /// Markers in repeated data are not expanded.
+    V1,
+    /// Markers in repeated data are expanded recursively.
+    V2,
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Parses a marker `(LxN)` at the start of the data.
+///
+/// Returns the length and the repeat count, and the data after the marker.
+fn marker(data: &[u8]) -> Option<(usize, usize, &[u8])> {
+    fn number(data: &[u8]) -> Option<(usize, &[u8])> {
+        let len = data.iter().take_while(|b| b.is_ascii_digit()).count();
+        let n = std::str::from_utf8(&data[..len]).ok()?.parse().ok()?;
+        Some((n, &data[len..]))
+    }
+    let data = data.strip_prefix(b"(")?;
+    let (len, data) = number(data)?;
+    let data = data.strip_prefix(b"x")?;
+    let (n, data) = number(data)?;
+    let data = data.strip_prefix(b")")?;
+    Some((len, n, data))
+}
+
+/// Writes the decompressed data without keeping the output in memory.
+///
+/// Whitespace is ignored, except in data repeated literally by version 1.
+fn decode<W>(data: &[u8], version: Version, w: &mut W) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut rest = data;
+    while !rest.is_empty() {
+        if rest[0] == b'(' {
+            let (len, n, tail) = marker(rest).ok_or_else(|| invalid_data("invalid marker"))?;
+            if len > tail.len() {
+                return Err(invalid_data("marker extends beyond the data"));
+            }
+            let (repeated, tail) = tail.split_at(len);
+            for _ in 0..n {
+                match version {
+                    Version::V1 => w.write_all(repeated)?,
+                    Version::V2 => decode(repeated, version, w)?,
+                }
+            }
+            rest = tail;
+        } else if rest[0].is_ascii_whitespace() {
+            rest = &rest[1..];
+        } else {
+            // Write plain data up to the next marker or whitespace at once.
+            let len = rest
+                .iter()
+                .take_while(|&&b| b != b'(' && !b.is_ascii_whitespace())
+                .count();
+            w.write_all(&rest[..len])?;
+            rest = &rest[len..];
+        }
+    }
+    Ok(())
+}
+
+/// The maximal length of data repeated by a marker when encoding.
+const MAX_ENCODE_LEN: usize = 64;
+
+/// Compresses text such that version 1 decompression restores it.
+///
+/// Repeated runs are replaced by markers where that saves space.
+/// Characters which would be taken as a marker or ignored as whitespace
+/// are wrapped into a marker of their own.
+fn encode(text: &[u8]) -> Vec<u8> {
+    let mut result = Vec::new();
+    let mut i = 0;
+    while i < text.len() {
+        // Find the repetition that saves the most space.
+        let mut best = None;
+        let mut best_saving = 0;
+        for len in 1..=MAX_ENCODE_LEN.min(text.len() - i) {
+            let pattern = &text[i..(i + len)];
+            let mut n = 1;
+            while text[(i + n * len)..].starts_with(pattern) {
+                n += 1;
+            }
+            let marker_len = 3 + uint_len(len) + uint_len(n);
+            let saving = (n * len) as i64 - (marker_len + len) as i64;
+            if saving > best_saving {
+                best = Some((len, n));
+                best_saving = saving;
+            }
+        }
+        let (len, n) = match best {
+            Some(best) => best,
+            None if text[i] == b'(' || text[i].is_ascii_whitespace() => (1, 1),
+            None => {
+                result.push(text[i]);
+                i += 1;
+                continue;
+            }
+        };
+        result.extend_from_slice(format!("({}x{})", len, n).as_bytes());
+        result.extend_from_slice(&text[i..(i + len)]);
+        i += n * len;
+    }
+    result
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // Options write the decompressed or compressed input instead of the lengths.
+    if let Some(arg) = env::args().nth(1) {
+        let mut stdout = io::BufWriter::new(io::stdout().lock());
+        match arg.as_str() {
+            "--decode-v1" => decode(input_data.as_bytes(), Version::V1, &mut stdout)?,
+            "--decode-v2" => decode(input_data.as_bytes(), Version::V2, &mut stdout)?,
+            "--encode" => stdout.write_all(&encode(input_data.as_bytes()))?,
+            _ => return Err(util::runtime_error!("unknown option {}", arg)),
+        }
+        writeln!(stdout)?;
+        return Ok(());
+    }
 
     // parse input
     let result = input(&input_data);
@@ -152,4 +277,62 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::decode;
+    use crate::encode;
+    use crate::input;
+    use crate::Version;
+
+    fn decoded(data: &str, version: Version) -> String {
+        let mut output = Vec::new();
+        decode(data.as_bytes(), version, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_decode_v1() {
+        assert_eq!(decoded("ADVENT", Version::V1), "ADVENT");
+        assert_eq!(decoded("A(1x5)BC", Version::V1), "ABBBBBC");
+        assert_eq!(decoded("A(2x2)BCD(2x2)EFG", Version::V1), "ABCBCDEFEFG");
+        assert_eq!(decoded("(6x1)(1x3)A", Version::V1), "(1x3)A");
+        assert_eq!(
+            decoded("X(8x2)(3x3)ABCY", Version::V1),
+            "X(3x3)ABC(3x3)ABCY"
+        );
+    }
+
+    #[test]
+    fn test_decode_v2() {
+        assert_eq!(
+            decoded("X(8x2)(3x3)ABCY", Version::V2),
+            "XABCABCABCABCABCABCY"
+        );
+        let data = "(25x3)(3x3)ABC(2x3)XY(5x2)PQRSTX(18x9)(3x2)TWO(5x7)SEVEN";
+        let (_, cd) = input(data).unwrap();
+        assert_eq!(decoded(data, Version::V2).len(), cd.len_uncompressed_v2());
+        assert_eq!(cd.len_uncompressed_v2(), 445);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for text in [
+            "",
+            "ADVENT",
+            "ABABABABABABABABAB",
+            "XXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXXX",
+            "(1x3)A and (((( some )))) spaces\n",
+            "abcabcabcabc (x)(x)(x)(x)(x)(x)(x)(x)",
+        ] {
+            let encoded = encode(text.as_bytes());
+            let mut decoded = Vec::new();
+            decode(&encoded, Version::V1, &mut decoded).unwrap();
+            assert_eq!(String::from_utf8(decoded).unwrap(), text);
+        }
+        assert!(encode("ABABABABABABABABAB".as_bytes()).len() < 18);
+    }
 }
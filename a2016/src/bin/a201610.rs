@@ -1,6 +1,8 @@
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::io;
+use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 use nom::branch::alt;
@@ -119,8 +121,214 @@ fn input(i: &str) -> IResult<&str, Vec<Instruction>> {
     many1(instruction)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// A comparison of two chips by a bot.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Comparison {
+    bot_id: usize,
+    low: usize,
+    high: usize,
+}
+
+/// The network of bots, through which the chips flow.
+#[derive(Clone, Debug)]
+struct Network {
+    bots: HashMap<usize, Bot>,
+    values: Vec<Value>,
+}
+impl Network {
+    fn new(instructions: &[Instruction]) -> util::Result<Network> {
+        let mut bots = HashMap::new();
+        let mut values = Vec::new();
+        for &i in instructions {
+            match i {
+                Instruction::Value(value) => values.push(value),
+                Instruction::Bot(bot) => {
+                    if bots.insert(bot.id, bot).is_some() {
+                        return Err(util::runtime_error!("bot {} has two instructions", bot.id));
+                    }
+                }
+            }
+        }
+        Ok(Network { bots, values })
+    }
+
+    /// Passes the chips until no bot holds two of them.
+    ///
+    /// A bot compares and passes on its chips as soon as it holds two.
+    fn run(&self) -> util::Result<Run> {
+        let mut held: HashMap<usize, Vec<usize>> = HashMap::new();
+        let mut run = Run {
+            comparisons: Vec::new(),
+            outputs: HashMap::new(),
+            received: HashMap::new(),
+        };
+        let mut pending = self
+            .values
+            .iter()
+            .rev()
+            .map(|v| (Destination::Bot(v.bot_id), v.id))
+            .collect::<Vec<_>>();
+        while let Some((destination, value)) = pending.pop() {
+            match destination {
+                Destination::Bot(id) => {
+                    *run.received.entry(id).or_insert(0) += 1;
+                    let chips = held.entry(id).or_default();
+                    chips.push(value);
+                    if chips.len() == 2 {
+                        let bot = self.bots.get(&id).ok_or_else(|| {
+                            util::runtime_error!(
+                                "bot {} holds two chips but has no instruction",
+                                id
+                            )
+                        })?;
+                        chips.sort();
+                        let low = chips[0];
+                        let high = chips[1];
+                        chips.clear();
+                        run.comparisons.push(Comparison {
+                            bot_id: id,
+                            low,
+                            high,
+                        });
+                        pending.push((bot.high_destination, high));
+                        pending.push((bot.low_destination, low));
+                    }
+                }
+                Destination::Output(id) => {
+                    run.outputs.entry(id).or_default().push(value);
+                }
+            }
+        }
+        Ok(run)
+    }
+
+    /// Returns the bots with an instruction which never compared chips.
+    fn idle_bots(&self, run: &Run) -> Vec<usize> {
+        let mut result = self
+            .bots
+            .keys()
+            .copied()
+            .filter(|id| run.comparisons.iter().all(|c| c.bot_id != *id))
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+
+    /// Returns the network in the Graphviz dot format.
+    ///
+    /// Bots are labelled with the chips they compared in the run.
+    fn dot(&self, run: &Run) -> String {
+        fn node(destination: Destination) -> String {
+            match destination {
+                Destination::Bot(id) => format!("bot{}", id),
+                Destination::Output(id) => format!("output{}", id),
+            }
+        }
+
+        let mut ids = self.bots.keys().copied().collect::<Vec<_>>();
+        ids.sort();
+        let mut outputs = self
+            .bots
+            .values()
+            .flat_map(|bot| [bot.low_destination, bot.high_destination])
+            .filter_map(|d| match d {
+                Destination::Output(id) => Some(id),
+                Destination::Bot(_) => None,
+            })
+            .collect::<Vec<_>>();
+        outputs.sort();
+        outputs.dedup();
+
+        let mut result = String::from("digraph bots {\n");
+        for id in &ids {
+            let compared = run
+                .comparisons
+                .iter()
+                .filter(|c| c.bot_id == *id)
+                .map(|c| format!("\\n{} < {}", c.low, c.high))
+                .collect::<String>();
+            result.push_str(&format!(
+                "    bot{} [shape=box, label=\"bot {}{}\"];\n",
+                id, id, compared
+            ));
+        }
+        for id in outputs {
+            result.push_str(&format!(
+                "    output{} [shape=ellipse, label=\"output {}\"];\n",
+                id, id
+            ));
+        }
+        for (i, value) in self.values.iter().enumerate() {
+            result.push_str(&format!(
+                "    value{} [shape=plaintext, label=\"{}\"];\n    value{} -> bot{};\n",
+                i, value.id, i, value.bot_id
+            ));
+        }
+        for id in &ids {
+            let bot = &self.bots[id];
+            result.push_str(&format!(
+                "    bot{} -> {} [label=\"low\"];\n    bot{} -> {} [label=\"high\"];\n",
+                id,
+                node(bot.low_destination),
+                id,
+                node(bot.high_destination)
+            ));
+        }
+        result.push_str("}\n");
+        result
+    }
+}
+
+/// The result of running a network.
+#[derive(Clone, Debug)]
+struct Run {
+    /// The comparisons in the order they happened.
+    comparisons: Vec<Comparison>,
+    /// The chips in the outputs, in the order they arrived.
+    outputs: HashMap<usize, Vec<usize>>,
+    /// The number of chips received by each bot.
+    received: HashMap<usize, usize>,
+}
+impl Run {
+    /// Returns the bot which compared the two values, in either order.
+    fn comparer(&self, value1: usize, value2: usize) -> Option<usize> {
+        let low = value1.min(value2);
+        let high = value1.max(value2);
+        self.comparisons
+            .iter()
+            .find(|c| c.low == low && c.high == high)
+            .map(|c| c.bot_id)
+    }
+
+    /// Returns the chips in a range of outputs, by output.
+    fn outputs(&self, range: RangeInclusive<usize>) -> Vec<(usize, &[usize])> {
+        range
+            .map(|id| (id, self.outputs.get(&id).map_or(&[][..], |v| &v[..])))
+            .collect()
+    }
+
+    /// Returns the bots which received more than two chips.
+    fn overloaded_bots(&self) -> Vec<usize> {
+        let mut result = self
+            .received
+            .iter()
+            .filter(|&(_, &n)| n > 2)
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        result.sort();
+        result
+    }
+}
+
+fn arg_uint(args: &mut impl Iterator<Item = String>) -> util::Result<usize> {
+    let arg = args
+        .next()
+        .ok_or_else(|| util::runtime_error!("missing argument"))?;
+    Ok(arg.parse()?)
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -131,58 +339,117 @@ fn main() {
     //     println!("{}", i);
     // }
 
-    let bots = input
-        .iter()
-        .filter_map(|i| {
-            if let Instruction::Bot(bot) = i {
-                Some((bot.id, bot))
-            } else {
-                None
-            }
-        })
-        .collect::<HashMap<_, _>>();
-    let mut bot_states = HashMap::new();
-    for &i in &input {
-        if let Instruction::Value(Value { id, bot_id }) = i {
-            let entry = bot_states.entry(bot_id).or_insert_with(Vec::new);
-            entry.push(id);
-        }
+    let network = Network::new(&input)?;
+    let run = network.run()?;
+    for id in network.idle_bots(&run) {
+        eprintln!("bot {} never compares chips", id);
     }
-    let mut output_states = HashMap::new();
-    let mut pending: Vec<(Destination, usize)> = Vec::new();
-    let mut bot_61_17_id = None;
-    loop {
-        while let Some((destination, value)) = pending.pop() {
-            match destination {
-                Destination::Bot(id) => {
-                    let entry = bot_states.entry(id).or_insert_with(Vec::new);
-                    entry.push(value);
-                }
-                Destination::Output(id) => {
-                    let entry = output_states.entry(id).or_insert_with(Vec::new);
-                    entry.push(value);
+    for id in run.overloaded_bots() {
+        eprintln!("bot {} receives more than two chips", id);
+    }
+
+    // Options are `--dot` to print the network,
+    // `--compare x y` for the bot comparing two values,
+    // and `--outputs i j` for the chips in a range of outputs.
+    let mut args = env::args().skip(1);
+    if let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dot" => print!("{}", network.dot(&run)),
+            "--compare" => {
+                let value1 = arg_uint(&mut args)?;
+                let value2 = arg_uint(&mut args)?;
+                match run.comparer(value1, value2) {
+                    Some(id) => println!("bot {}", id),
+                    None => println!("no bot compares {} and {}", value1, value2),
                 }
             }
-        }
-        if let Some((&id, v)) = bot_states.iter_mut().find(|(_id, v)| v.len() == 2) {
-            v.sort();
-            let low = v[0];
-            let high = v[1];
-            v.clear();
-            if low == 17 && high == 61 {
-                bot_61_17_id = Some(id);
+            "--outputs" => {
+                let start = arg_uint(&mut args)?;
+                let end = arg_uint(&mut args)?;
+                for (id, chips) in run.outputs(start..=end) {
+                    println!("output {}: {:?}", id, chips);
+                }
             }
-            let instruction = &bots[&id];
-            pending.push((instruction.low_destination, low));
-            pending.push((instruction.high_destination, high));
-        } else {
-            break;
+            _ => return Err(util::runtime_error!("unknown option {}", arg)),
         }
+        return Ok(());
     }
-    let result_a = bot_61_17_id.expect("no bot comparing 61 and 17 found");
 
-    let result_b = output_states[&0][0] * output_states[&1][0] * output_states[&2][0];
+    let result_a = run
+        .comparer(61, 17)
+        .ok_or_else(|| util::runtime_error!("no bot comparing 61 and 17 found"))?;
+
+    let mut result_b = 1;
+    for (id, chips) in run.outputs(0..=2) {
+        let chip = chips
+            .first()
+            .ok_or_else(|| util::runtime_error!("output {} is empty", id))?;
+        result_b *= chip;
+    }
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input;
+    use crate::Network;
+
+    const EXAMPLE: &str = "\
+value 5 goes to bot 2
+bot 2 gives low to bot 1 and high to bot 0
+value 3 goes to bot 1
+bot 1 gives low to output 1 and high to bot 0
+bot 0 gives low to output 2 and high to output 0
+value 2 goes to bot 2
+";
+
+    fn network(data: &str) -> Network {
+        let (_, instructions) = input(data).unwrap();
+        Network::new(&instructions).unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        let network = network(EXAMPLE);
+        let run = network.run().unwrap();
+        assert_eq!(run.comparer(5, 2), Some(2));
+        assert_eq!(run.comparer(2, 3), Some(1));
+        assert_eq!(run.comparer(3, 5), Some(0));
+        assert_eq!(run.comparer(2, 4), None);
+        assert_eq!(
+            run.outputs(0..=3),
+            vec![(0, &[5][..]), (1, &[2][..]), (2, &[3][..]), (3, &[][..])]
+        );
+        assert!(network.idle_bots(&run).is_empty());
+        assert!(run.overloaded_bots().is_empty());
+    }
+
+    #[test]
+    fn test_detection() {
+        let data = format!(
+            "{}{}",
+            EXAMPLE, "bot 3 gives low to output 3 and high to output 4\nvalue 7 goes to bot 2\n"
+        );
+        let network = network(&data);
+        let run = network.run().unwrap();
+        assert_eq!(network.idle_bots(&run), vec![3]);
+        assert_eq!(run.overloaded_bots(), vec![2]);
+
+        let (_, instructions) = input("value 1 goes to bot 0\nvalue 2 goes to bot 0\n").unwrap();
+        assert!(Network::new(&instructions).unwrap().run().is_err());
+    }
+
+    #[test]
+    fn test_dot() {
+        let network = network(EXAMPLE);
+        let dot = network.dot(&network.run().unwrap());
+        assert!(dot.starts_with("digraph bots {\n"));
+        assert!(dot.contains("bot2 [shape=box, label=\"bot 2\\n2 < 5\"];\n"));
+        assert!(dot.contains("bot1 -> output1 [label=\"low\"];\n"));
+        assert!(dot.contains("value0 -> bot2;\n"));
+    }
 }
@@ -1,5 +1,5 @@
-use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::env;
 use std::fmt;
 use std::hash::Hash;
 use std::io;
@@ -12,6 +12,7 @@ use nom::combinator::map;
 use nom::combinator::peek;
 use nom::combinator::recognize;
 use nom::combinator::value;
+use nom::error::ErrorKind;
 use nom::multi::many1;
 use nom::multi::separated_list1;
 use nom::IResult;
@@ -27,12 +28,6 @@ impl<Material> Device<Material>
 where
     Material: Clone,
 {
-    fn is_generator(&self) -> bool {
-        match self {
-            Device::Generator(_) => true,
-            Device::Microchip(_) => false,
-        }
-    }
     fn material(&self) -> Material {
         match self {
             Device::Generator(m) => m.clone(),
@@ -52,14 +47,17 @@ where
     }
 }
 
-const FLOORS: [&str; 5] = ["ground", "first", "second", "third", "fourth"];
+const FLOORS: [&str; 11] = [
+    "ground", "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+    "tenth",
+];
 
 #[derive(Clone, Debug)]
 struct Floor<Material> {
     number: usize,
     contents: Vec<Device<Material>>,
 }
-impl<'int, Material> fmt::Display for Floor<Material>
+impl<Material> fmt::Display for Floor<Material>
 where
     Material: fmt::Display,
 {
@@ -106,13 +104,13 @@ fn device(i: &str) -> IResult<&str, Device<String>> {
     alt((device_generator, device_microchip))(i)
 }
 
-fn floor_number<'i>(i: &'i str) -> IResult<&'i str, usize> {
-    alt((
-        value(1, tag("first")),
-        value(2, tag("second")),
-        value(3, tag("third")),
-        value(4, tag("fourth")),
-    ))(i)
+fn floor_number(i: &str) -> IResult<&str, usize> {
+    for (number, name) in FLOORS.iter().enumerate().skip(1) {
+        if let Ok((i, _)) = tag::<_, _, nom::error::Error<&str>>(*name)(i) {
+            return Ok((i, number));
+        }
+    }
+    Err(nom::Err::Error(nom::error::Error::new(i, ErrorKind::Tag)))
 }
 
 fn floor_contents_empty(i: &str) -> IResult<&str, Vec<Device<String>>> {
@@ -159,256 +157,247 @@ fn input(i: &str) -> IResult<&str, Vec<Floor<String>>> {
     many1(|i| floor(i))(i)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct Node {
-    elevator_floor: usize,
-    floors: Vec<Vec<Device<usize>>>,
+/// The number of devices the elevator can carry in the puzzle.
+const ELEVATOR_CAPACITY: usize = 2;
+
+/// The number of bits for a floor in a packed state.
+const FLOOR_BITS: u32 = 4;
+const FLOOR_MASK: u128 = (1 << FLOOR_BITS) - 1;
+
+/// The maximal number of floors of a packed state.
+const MAX_FLOORS: usize = 1 << FLOOR_BITS;
+/// The maximal number of materials of a packed state.
+const MAX_MATERIALS: usize = ((u128::BITS - FLOOR_BITS) / (2 * FLOOR_BITS)) as usize;
+
+/// The floors of the elevator and of the devices, numbered from 0.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Positions {
+    elevator: usize,
+    /// The floors of the generator and the microchip of each material.
+    pairs: Vec<(usize, usize)>,
 }
-impl Node {
-    fn start(input: &[Floor<usize>]) -> Node {
-        let elevator_floor = 1;
-        let mut floors = Vec::new();
-        for floor in input {
-            let n = floor.number;
-            if floors.len() < n + 1 {
-                floors.resize(n + 1, Vec::new());
-            }
-            let devices = &mut floors[n];
-            for device in &floor.contents {
-                devices.push(device.clone());
-            }
-            devices.sort();
+impl Positions {
+    /// Returns the floor of a device, where device `2 * m` is the generator
+    /// and device `2 * m + 1` the microchip of material `m`.
+    fn floor(&self, device: usize) -> usize {
+        let (g, c) = self.pairs[device / 2];
+        match device % 2 {
+            0 => g,
+            _ => c,
         }
-        Node {
-            elevator_floor,
-            floors,
+    }
+    fn set_floor(&mut self, device: usize, floor: usize) {
+        let (g, c) = &mut self.pairs[device / 2];
+        match device % 2 {
+            0 => *g = floor,
+            _ => *c = floor,
         }
     }
-    /// Nodes reached in a single step from this node
-    fn successors(&self) -> Vec<(Node, usize)> {
-        let mut result = Vec::new();
-        let floor = self.elevator_floor;
-
-        // try go up one floor with elevator
-        if floor < 4 {
-            let new_floor = floor + 1;
-
-            // pick one or two devices to move up
-            let devices = &self.floors[floor];
-
-            // pick one device
-            for d0 in devices {
-                let mut new_floors = self.floors.clone();
-                let i0 = new_floors[floor].binary_search(d0).unwrap();
-                new_floors[floor].remove(i0);
-                new_floors[new_floor].push(d0.clone());
-                new_floors[new_floor].sort();
-                let new_node = Node {
-                    elevator_floor: new_floor,
-                    floors: new_floors,
-                };
-                if new_node.is_allowed() {
-                    result.push((new_node, 1));
-                }
-            }
-
-            // pick two devices
-            for d0 in devices {
-                for d1 in devices {
-                    if d0 < d1 {
-                        let mut new_floors = self.floors.clone();
-                        let i0 = new_floors[floor].binary_search(d0).unwrap();
-                        new_floors[floor].remove(i0);
-                        let i1 = new_floors[floor].binary_search(d1).unwrap();
-                        new_floors[floor].remove(i1);
-                        new_floors[new_floor].push(d0.clone());
-                        new_floors[new_floor].push(d1.clone());
-                        new_floors[new_floor].sort();
-                        let new_node = Node {
-                            elevator_floor: new_floor,
-                            floors: new_floors,
-                        };
-                        if new_node.is_allowed() {
-                            result.push((new_node, 1));
-                        }
-                    }
-                }
-            }
+    fn devices(&self) -> impl Iterator<Item = usize> {
+        0..(2 * self.pairs.len())
+    }
+    /// Returns true if no microchip is fried.
+    ///
+    /// A microchip is fried if it is on a floor with a generator
+    /// but not with its own generator.
+    fn is_safe(&self) -> bool {
+        self.pairs
+            .iter()
+            .all(|&(g, c)| g == c || self.pairs.iter().all(|&(g1, _)| g1 != c))
+    }
+    /// Packs the positions into a state.
+    ///
+    /// The materials are interchangeable, so the pairs are sorted
+    /// to map positions which only differ by the material names to the same state.
+    fn pack(&self) -> State {
+        let mut pairs = self.pairs.clone();
+        pairs.sort();
+        let mut bits = 0;
+        for &(g, c) in pairs.iter().rev() {
+            bits = (bits << FLOOR_BITS) | c as u128;
+            bits = (bits << FLOOR_BITS) | g as u128;
         }
+        bits = (bits << FLOOR_BITS) | self.elevator as u128;
+        State(bits)
+    }
+}
 
-        // try go down one floor with elevator
-        if floor > 1 {
-            let new_floor = floor - 1;
-
-            // pick one or two devices to move up
-            let devices = &self.floors[floor];
-
-            // pick one device
-            for d0 in devices {
-                let mut new_floors = self.floors.clone();
-                let i0 = new_floors[floor].binary_search(d0).unwrap();
-                new_floors[floor].remove(i0);
-                new_floors[new_floor].push(d0.clone());
-                new_floors[new_floor].sort();
-                let new_node = Node {
-                    elevator_floor: new_floor,
-                    floors: new_floors,
-                };
-                if new_node.is_allowed() {
-                    result.push((new_node, 1));
-                }
-            }
+/// A canonical state of the facility packed into an integer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+struct State(u128);
+impl State {
+    fn unpack(self, material_count: usize) -> Positions {
+        let mut bits = self.0;
+        let mut next_floor = || {
+            let floor = (bits & FLOOR_MASK) as usize;
+            bits >>= FLOOR_BITS;
+            floor
+        };
+        let elevator = next_floor();
+        let pairs = (0..material_count)
+            .map(|_| {
+                let g = next_floor();
+                let c = next_floor();
+                (g, c)
+            })
+            .collect::<Vec<_>>();
+        Positions { elevator, pairs }
+    }
+}
 
-            // pick two devices
-            for d0 in devices {
-                for d1 in devices {
-                    if d0 < d1 {
-                        let mut new_floors = self.floors.clone();
-                        let i0 = new_floors[floor].binary_search(d0).unwrap();
-                        new_floors[floor].remove(i0);
-                        let i1 = new_floors[floor].binary_search(d1).unwrap();
-                        new_floors[floor].remove(i1);
-                        new_floors[new_floor].push(d0.clone());
-                        new_floors[new_floor].push(d1.clone());
-                        new_floors[new_floor].sort();
-                        let new_node = Node {
-                            elevator_floor: new_floor,
-                            floors: new_floors,
-                        };
-                        if new_node.is_allowed() {
-                            result.push((new_node, 1));
-                        }
-                    }
-                }
+/// Returns the non-empty subsets of the items with at most `max_len` elements.
+fn subsets(items: &[usize], max_len: usize) -> Vec<Vec<usize>> {
+    let mut result = Vec::new();
+    if max_len > 0 {
+        for (i, &item) in items.iter().enumerate() {
+            result.push(vec![item]);
+            for mut subset in subsets(&items[(i + 1)..], max_len - 1) {
+                subset.insert(0, item);
+                result.push(subset);
             }
         }
-        result
     }
-    /// Returns the minimum number of steps needed
-    /// to success from this node, or possibly less.
-    fn heuristic(&self) -> usize {
-        let mut steps_devices =
-            3 * self.floors[1].len() + 2 * self.floors[2].len() + 1 * self.floors[3].len();
-
-        //println!("{}: {}", self, steps_devices);
-
-        // The lowest device which is not alone travels for free,
-        // because the elevator can carry two devices.
-        if self.floors[1].len() >= 2 {
-            steps_devices -= 3;
+    result
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Facility {
+    floor_count: usize,
+    material_count: usize,
+    capacity: usize,
+}
+impl Facility {
+    /// States reached in a single step from this state.
+    fn successors(&self, state: &State) -> Vec<(State, usize)> {
+        let positions = state.unpack(self.material_count);
+        let floor = positions.elevator;
+        let devices = positions
+            .devices()
+            .filter(|&d| positions.floor(d) == floor)
+            .collect::<Vec<_>>();
+
+        // Never go down below all devices.
+        let lowest_floor = positions.devices().map(|d| positions.floor(d)).min();
+        let lowest_floor = lowest_floor.unwrap_or(floor);
+
+        let mut new_floors = Vec::new();
+        if floor + 1 < self.floor_count {
+            new_floors.push(floor + 1);
         }
-        if self.floors[2].len() >= 2 {
-            steps_devices -= 2;
+        if floor > lowest_floor {
+            new_floors.push(floor - 1);
         }
-        if self.floors[3].len() >= 2 {
-            steps_devices -= 1;
-        } else {
-            // do nothing
+
+        let mut result = Vec::new();
+        for new_floor in new_floors {
+            for subset in subsets(&devices, self.capacity) {
+                let mut new_positions = positions.clone();
+                new_positions.elevator = new_floor;
+                for &d in &subset {
+                    new_positions.set_floor(d, new_floor);
+                }
+                if new_positions.is_safe() {
+                    result.push((new_positions.pack(), 1));
+                }
+            }
         }
-        let steps_elevator = 4 - self.elevator_floor;
-        steps_devices.max(steps_elevator)
-    }
-    /// Return true if this a success node, false otherwise.
-    fn is_success(&self) -> bool {
-        self.elevator_floor == 4
-            && self.floors[1].is_empty()
-            && self.floors[2].is_empty()
-            && self.floors[3].is_empty()
+        result
     }
-    fn is_allowed(&self) -> bool {
-        // check that no chips are fried
-        self.floors.iter().all(|devices| {
-            devices.iter().all(|d0| {
-                if let Device::Microchip(m) = d0 {
-                    devices.iter().any(|d1| d1 == &Device::Generator(m.clone()))
-                        || devices.iter().all(|d1| !d1.is_generator())
+    /// Returns a lower bound for the number of steps to success from this state.
+    ///
+    /// Every step crosses the boundary between two adjacent floors once.
+    /// Each trip up across a boundary carries at most `capacity` devices,
+    /// and each trip down carries at least one device,
+    /// which bounds the number of trips needed to get all devices above it.
+    fn heuristic(&self, state: &State) -> usize {
+        let positions = state.unpack(self.material_count);
+        let net_per_round_trip = self.capacity.saturating_sub(1).max(1);
+        let mut steps = 0;
+        for boundary in 0..(self.floor_count - 1) {
+            let below = positions
+                .devices()
+                .filter(|&d| positions.floor(d) <= boundary)
+                .count();
+            if below > 0 {
+                if positions.elevator <= boundary {
+                    // The last trip up needs no trip back down.
+                    let ups = (below - 1).div_ceil(net_per_round_trip).max(1);
+                    steps += 2 * ups - 1;
                 } else {
-                    true
+                    let ups = below.div_ceil(net_per_round_trip);
+                    steps += 2 * ups;
                 }
-            })
-        })
-    }
-}
-impl fmt::Display for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
-        for floor in 1..=4 {
-            write!(f, "{}: ", floor)?;
-            let mut sep = "";
-            for device in &self.floors[floor] {
-                write!(f, "{}{}", sep, device)?;
-                sep = ", ";
             }
-            writeln!(f)?;
         }
-        Ok(())
+        steps
     }
-}
-
-fn map_device<T0, T1>(map: &BTreeMap<T0, T1>, device: &Device<T0>) -> Device<T1>
-where
-    T0: PartialOrd + Ord,
-    T1: Clone,
-{
-    match device {
-        Device::Generator(ms) => Device::Generator(map[ms].clone()),
-        Device::Microchip(ms) => Device::Microchip(map[ms].clone()),
+    /// Return true if all devices are on the top floor.
+    fn is_success(&self, state: &State) -> bool {
+        let positions = state.unpack(self.material_count);
+        positions
+            .devices()
+            .all(|d| positions.floor(d) == self.floor_count - 1)
     }
 }
 
-fn map_floor<T0, T1>(map: &BTreeMap<T0, T1>, floor: &Floor<T0>) -> Floor<T1>
-where
-    T0: PartialOrd + Ord,
-    T1: Clone,
-{
-    let Floor { number, contents } = floor;
-    let number = *number;
-    let contents = contents
+/// Returns the minimal number of steps to bring all devices to the top floor.
+fn min_steps(floors: &[Floor<String>], capacity: usize) -> util::Result<usize> {
+    let materials = floors
         .iter()
-        .map(|d| map_device(map, d))
-        .collect::<Vec<_>>();
-    Floor { number, contents }
+        .flat_map(|floor| floor.contents.iter().map(|d| d.material()))
+        .collect::<BTreeSet<_>>();
+    let floor_count = floors.iter().map(|floor| floor.number).max().unwrap_or(1);
+    if floor_count > MAX_FLOORS || materials.len() > MAX_MATERIALS {
+        return Err(util::runtime_error!(
+            "at most {} floors and {} materials are supported",
+            MAX_FLOORS,
+            MAX_MATERIALS
+        ));
+    }
+
+    let find_floor = |device: &Device<String>| {
+        floors
+            .iter()
+            .find(|floor| floor.contents.contains(device))
+            .map(|floor| floor.number - 1)
+            .ok_or_else(|| util::runtime_error!("{} is missing", device))
+    };
+    let mut pairs = Vec::new();
+    for m in materials {
+        let g = find_floor(&Device::Generator(m.clone()))?;
+        let c = find_floor(&Device::Microchip(m))?;
+        pairs.push((g, c));
+    }
+    let start = Positions { elevator: 0, pairs };
+
+    let facility = Facility {
+        floor_count,
+        material_count: start.pairs.len(),
+        capacity,
+    };
+    let search_result = astar(
+        &start.pack(),
+        |s| facility.successors(s),
+        |s| facility.heuristic(s),
+        |s| facility.is_success(s),
+    );
+    let (_path, cost) = search_result.ok_or_else(|| util::runtime_error!("no solution"))?;
+    Ok(cost)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // An optional argument overrides the capacity of the elevator.
+    let capacity = match env::args().nth(1) {
+        Some(arg) => arg.parse::<usize>()?,
+        None => ELEVATOR_CAPACITY,
+    };
 
     // parse input
-    let result = input(&input_data);
-    // println!("{:?}", result);
+    let (_, input) = input(&input_data).map_err(|e| util::parse::to_error(&input_data, e))?;
 
-    let input = result.unwrap().1;
-    // for i in &input {
-    //     println!("{}", i);
-    // }
-
-    // Construct a map from material names (strings) to ids (unsigned integers).
-    let materials_a = input
-        .iter()
-        .flat_map(|floor| floor.contents.iter().map(|d| d.material()))
-        .collect::<BTreeSet<_>>();
-    let material_map_a = materials_a
-        .into_iter()
-        .enumerate()
-        .map(|(i, v)| (v, i))
-        .collect::<BTreeMap<_, _>>();
-    // println!("{:?}", material_map);
-
-    // Map material names to ids throughout the input structure.
-    // This speeds up the search by a factor of 2.5 .
-    let floors_a = input
-        .iter()
-        .map(|floor| map_floor(&material_map_a, floor))
-        .collect::<Vec<_>>();
-
-    let start_node_a = Node::start(&floors_a);
-    let search_result_a = astar(
-        &start_node_a,
-        Node::successors,
-        Node::heuristic,
-        Node::is_success,
-    );
-    let (_path, cost_a) = search_result_a.unwrap();
-    let result_a = cost_a;
+    let result_a = min_steps(&input, capacity)?;
 
     // Add additional devices as specified in the problem part 2.
     let mut input_b = input;
@@ -424,35 +413,45 @@ fn main() {
             }
         }
     }
-
-    // Construct a map from material names (strings) to ids (unsigned integers).
-    let materials_b = input_b
-        .iter()
-        .flat_map(|floor| floor.contents.iter().map(|d| d.material()))
-        .collect::<BTreeSet<_>>();
-    let material_map_b = materials_b
-        .into_iter()
-        .enumerate()
-        .map(|(i, v)| (v, i))
-        .collect::<BTreeMap<_, _>>();
-    // println!("{:?}", materials_b);
-
-    // map material names to ids throughout the input structure
-    let floors_b = input_b
-        .iter()
-        .map(|floor| map_floor(&material_map_b, floor))
-        .collect::<Vec<_>>();
-
-    let start_node_b = Node::start(&floors_b);
-    let search_result_b = astar(
-        &start_node_b,
-        Node::successors,
-        Node::heuristic,
-        Node::is_success,
-    );
-    let (_path, cost_b) = search_result_b.unwrap();
-    let result_b = cost_b;
+    let result_b = min_steps(&input_b, capacity)?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input;
+    use crate::min_steps;
+    use crate::Positions;
+
+    const EXAMPLE: &str = concat!(
+        "The first floor contains a hydrogen-compatible microchip",
+        " and a lithium-compatible microchip.\n",
+        "The second floor contains a hydrogen generator.\n",
+        "The third floor contains a lithium generator.\n",
+        "The fourth floor contains nothing relevant.\n",
+    );
+
+    #[test]
+    fn test_example() {
+        let (_, floors) = input(EXAMPLE).unwrap();
+        assert_eq!(min_steps(&floors, 2).unwrap(), 11);
+    }
+
+    #[test]
+    fn test_pack() {
+        let p0 = Positions {
+            elevator: 1,
+            pairs: vec![(2, 0), (1, 3)],
+        };
+        let p1 = Positions {
+            elevator: 1,
+            pairs: vec![(1, 3), (2, 0)],
+        };
+        assert_eq!(p0.pack(), p1.pack());
+        assert_eq!(p0.pack().unpack(2), p1);
+    }
 }
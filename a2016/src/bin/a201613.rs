@@ -1,10 +1,14 @@
 use std::collections::HashSet;
+use std::io;
 
 use pathfinding::prelude::astar;
 
 use lowdim::p2d;
 use lowdim::Point2d;
 
+use util::parse::parse_input;
+use util::parse::uint;
+
 fn is_open(p: Point2d, n: i64) -> bool {
     let x = p.x();
     let y = p.y();
@@ -12,8 +16,12 @@ fn is_open(p: Point2d, n: i64) -> bool {
     x >= 0 && y >= 0 && sum.count_ones() % 2 == 0
 }
 
-fn main() {
-    let input = 1350;
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, uint::<i64>)?;
+    util::validate(&input_data, "", &input);
 
     let start_node = p2d(1, 1);
     let target_node = p2d(31, 39);
@@ -54,4 +62,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -1,3 +1,9 @@
+use std::io;
+
+use nom::character::complete::digit1;
+
+use util::parse::parse_input;
+
 fn step(input: &[u8]) -> Vec<u8> {
     let mut result = input.to_vec();
     result.push(b'0');
@@ -38,19 +44,25 @@ fn checksum(data: &[u8]) -> String {
     data.into_iter().map(|b| b as char).collect::<String>()
 }
 
-fn main() {
-    let input = b"10001001100000001";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, digit1)?;
+    util::validate(&input_data, "", &input);
 
     let disk_len1 = 272;
-    let data = disk_data(disk_len1, input);
+    let data = disk_data(disk_len1, input.as_bytes());
     let result_a = checksum(&data);
 
     let disk_len2 = 35651584;
-    let data = disk_data(disk_len2, input);
+    let data = disk_data(disk_len2, input.as_bytes());
     let result_b = checksum(&data);
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -1,28 +1,40 @@
-use lowdim::bb2d;
+use std::io;
+
+use nom::character::complete::alphanumeric1;
+
 use pathfinding::prelude::astar;
 
+use lowdim::bb2d;
 use lowdim::p2d;
 use lowdim::v2d;
 use lowdim::Point2d;
 use lowdim::Vec2d;
 
+use util::hash_search::nibble;
+use util::hash_search::PrefixHasher;
+use util::parse::parse_input;
+
 #[derive(Clone, Debug, PartialEq, Eq, Hash)]
 struct Node {
     pos: Point2d,
     path: String,
 }
 impl Node {
-    fn hash(&self, passcode: &str) -> Vec<bool> {
-        let md5_input = passcode.to_string() + &self.path;
-        let md5_input = md5_input.as_bytes();
-        let digest = md5::compute(md5_input);
-        let s = format!("{:x}", digest);
-        s.chars().take(4).map(|c| c >= 'b').collect::<Vec<bool>>()
+    /// Returns which doors are open, given the hasher for the passcode.
+    fn hash(&self, hasher: &PrefixHasher) -> Vec<bool> {
+        let digest = hasher.hash(self.path.as_bytes());
+        (0..4).map(|i| nibble(&digest, i) >= 0xb).collect::<Vec<bool>>()
     }
 }
 
-fn main() {
-    let input = "qtetzkpl";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, alphanumeric1)?;
+    util::validate(&input_data, "", &input);
+
+    let hasher = PrefixHasher::new(input.as_bytes());
 
     let bbox = bb2d(0..4, 0..4);
     let moves: [(&str, Vec2d<i64>); 4] = [
@@ -40,7 +52,7 @@ fn main() {
     let successors = |n: &Node| {
         moves
             .iter()
-            .zip(n.hash(input))
+            .zip(n.hash(&hasher))
             .filter_map(|((c, v), b)| {
                 let pos = n.pos + v;
                 if bbox.contains(&pos) && b {
@@ -76,4 +88,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -1,30 +1,189 @@
-use std::collections::VecDeque;
-
-fn main() {
-    let input = 3012210;
-
-    let mut have_presents = (1..=input).collect::<VecDeque<_>>();
-    while have_presents.len() >= 2 {
-        let taker = have_presents.pop_front().unwrap();
-        let _giver = have_presents.pop_front().unwrap();
-        have_presents.push_back(taker);
-    }
-    let result_a = have_presents.pop_front().unwrap();
-
-    let mut have_presents_h0 = VecDeque::new();
-    let mut have_presents_h1 = (1..=input).collect::<VecDeque<_>>();
-    while have_presents_h0.len() + have_presents_h1.len() >= 2 {
-        // Balance the halves so that they are either equal
-        // or the second half is one larger.
-        while have_presents_h1.len() - have_presents_h0.len() >= 2 {
-            have_presents_h0.push_back(have_presents_h1.pop_front().unwrap());
-        }
-        let taker = have_presents_h0.pop_front().unwrap();
-        let _giver = have_presents_h1.pop_front().unwrap();
-        have_presents_h1.push_back(taker);
+use std::env;
+use std::io;
+
+use util::parse::parse_input;
+use util::parse::uint;
+
+/// Returns the winning elf when each elf steals from the elf to its left.
+///
+/// This is the Josephus problem where every second elf is eliminated,
+/// computed with the recurrence `J(2n) = 2 J(n) - 1` and `J(2n + 1) = 2 J(n) + 1`.
+/// Elves are numbered from 1.
+fn steal_from_left(n: u64) -> u64 {
+    if n <= 1 {
+        n
+    } else if n & 1 == 0 {
+        2 * steal_from_left(n / 2) - 1
+    } else {
+        2 * steal_from_left(n / 2) + 1
+    }
+}
+
+/// Returns the winning elf when each elf steals from the elf across the circle.
+///
+/// With `p` the largest power of 3 not greater than `n`, the winner is `n` if `n == p`,
+/// otherwise `n - p` up to `n = 2p` and `2n - 3p` beyond.
+/// Elves are numbered from 1.
+fn steal_from_across(n: u64) -> u64 {
+    if n <= 1 {
+        return n;
+    }
+    let mut p = 1;
+    while p * 3 <= n {
+        p *= 3;
+    }
+    if n == p {
+        n
+    } else if n <= 2 * p {
+        n - p
+    } else {
+        2 * n - 3 * p
+    }
+}
+
+/// Returns the survivor when every `k`-th of `n` elves in a circle is eliminated.
+///
+/// Counting starts at elf 1 and elves are numbered from 1.
+/// Eliminating a full round at once takes `O(k log n)` steps,
+/// and the rounds are kept in a list instead of recursing over them.
+fn josephus(n: u64, k: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    // Eliminate the elves k, 2k, ... of each round while there is a full round.
+    let mut sizes = Vec::new();
+    let mut m = n;
+    while k > 1 && m > 1 && k <= m {
+        sizes.push(m);
+        m -= m / k;
+    }
+    // The zero-based survivor of the remaining elves.
+    let mut s = if k == 1 {
+        m - 1
+    } else {
+        // No full round can be eliminated at once, so add one elf at a time.
+        (2..=m).fold(0, |s, j| (s + k) % j)
+    };
+    // Renumber the survivor back through the rounds.
+    for &size in sizes.iter().rev() {
+        let offset = size % k;
+        s = if s < offset {
+            s + size - offset
+        } else {
+            let s = s - offset;
+            s + s / (k - 1)
+        };
     }
-    let result_b = have_presents_h1.pop_front().unwrap();
+    s + 1
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, uint::<u64>)?;
+    util::validate(&input_data, "", &input);
+
+    let result_a = steal_from_left(input);
+    let result_b = steal_from_across(input);
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    // An optional argument `k` gives the survivor when every k-th elf is eliminated.
+    if let Some(arg) = env::args().nth(1) {
+        let k = arg.parse::<u64>()?;
+        if k == 0 {
+            return Err(util::runtime_error!("k must be at least 1"));
+        }
+        println!("k = {}: {}", k, josephus(input, k));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::VecDeque;
+
+    use crate::josephus;
+    use crate::steal_from_across;
+    use crate::steal_from_left;
+
+    fn simulate_left(n: u64) -> u64 {
+        let mut have_presents = (1..=n).collect::<VecDeque<_>>();
+        while have_presents.len() >= 2 {
+            let taker = have_presents.pop_front().unwrap();
+            let _giver = have_presents.pop_front().unwrap();
+            have_presents.push_back(taker);
+        }
+        have_presents.pop_front().unwrap()
+    }
+
+    fn simulate_across(n: u64) -> u64 {
+        let mut have_presents_h0 = VecDeque::new();
+        let mut have_presents_h1 = (1..=n).collect::<VecDeque<_>>();
+        while have_presents_h0.len() + have_presents_h1.len() >= 2 {
+            // Balance the halves so that they are either equal
+            // or the second half is one larger.
+            while have_presents_h1.len() - have_presents_h0.len() >= 2 {
+                have_presents_h0.push_back(have_presents_h1.pop_front().unwrap());
+            }
+            let taker = have_presents_h0.pop_front().unwrap();
+            let _giver = have_presents_h1.pop_front().unwrap();
+            have_presents_h1.push_back(taker);
+        }
+        have_presents_h1.pop_front().unwrap()
+    }
+
+    fn simulate_josephus(n: u64, k: u64) -> u64 {
+        let mut circle = (1..=n).collect::<VecDeque<_>>();
+        while circle.len() >= 2 {
+            for _ in 1..k {
+                let e = circle.pop_front().unwrap();
+                circle.push_back(e);
+            }
+            circle.pop_front();
+        }
+        circle.pop_front().unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(steal_from_left(5), 3);
+        assert_eq!(steal_from_across(5), 2);
+    }
+
+    #[test]
+    fn test_against_simulation() {
+        for n in 1..=300 {
+            assert_eq!(steal_from_left(n), simulate_left(n), "n = {}", n);
+            assert_eq!(steal_from_across(n), simulate_across(n), "n = {}", n);
+            for k in 1..=7 {
+                assert_eq!(
+                    josephus(n, k),
+                    simulate_josephus(n, k),
+                    "n = {}, k = {}",
+                    n,
+                    k
+                );
+            }
+            for k in [n + 1, 2 * n + 3] {
+                assert_eq!(
+                    josephus(n, k),
+                    simulate_josephus(n, k),
+                    "n = {}, k = {}",
+                    n,
+                    k
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_josephus_large_k() {
+        let (n, k) = (3_014_387, 3_000_000);
+        let expected = (2..=n).fold(0, |s, m| (s + k) % m) + 1;
+        assert_eq!(josephus(n, k), expected);
+    }
 }
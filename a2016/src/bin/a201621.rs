@@ -1,4 +1,5 @@
 use std::cmp::Ordering;
+use std::env;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
@@ -24,7 +25,17 @@ enum Operation {
     Reverse(usize, usize),
     Move(usize, usize),
 }
-impl Operation {}
+impl Operation {
+    /// Returns the largest position used by the operation, if any.
+    fn max_position(&self) -> Option<usize> {
+        match self {
+            Operation::SwapPosition(x, y) | Operation::Reverse(x, y) | Operation::Move(x, y) => {
+                Some(*x.max(y))
+            }
+            _ => None,
+        }
+    }
+}
 impl fmt::Display for Operation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -77,18 +88,53 @@ impl State {
             Operation::Move(x, y) => self.move_(*x, *y),
         }
     }
-    fn reverse_execute(&mut self, operation: &Operation) {
-        match operation {
-            Operation::SwapPosition(x, y) => self.swap_position(*x, *y),
-            Operation::SwapLetter(x, y) => self.swap_letter(*x, *y),
-            Operation::RotateLeft(x) => self.rotate_right(*x),
-            Operation::RotateRight(x) => self.rotate_left(*x),
-            Operation::RotateBasedOnPositionOfLetter(x) => {
-                self.reverse_rotate_based_on_position_of_letter(*x)
+    /// Returns the states which the operation turns into this state.
+    ///
+    /// Most operations are invertible, but rotating based on the position
+    /// of a letter may map several states to the same one, depending on
+    /// the length of the password, and swapping letters may do so
+    /// if the password contains repeated letters.
+    fn preimages(&self, operation: &Operation) -> Vec<State> {
+        let candidates = match operation {
+            Operation::RotateLeft(x) => vec![self.with(|s| s.rotate_right(*x))],
+            Operation::RotateRight(x) => vec![self.with(|s| s.rotate_left(*x))],
+            Operation::Move(x, y) => vec![self.with(|s| s.move_(*y, *x))],
+            Operation::SwapPosition(_, _) | Operation::Reverse(_, _) => {
+                vec![self.with(|s| s.execute(operation))]
+            }
+            Operation::SwapLetter(_, _) | Operation::RotateBasedOnPositionOfLetter(_) => {
+                // Try all rotations and letter swaps, which includes the identity.
+                let len = self.password.len();
+                let mut candidates = (0..len)
+                    .map(|r| self.with(|s| s.rotate_left(r)))
+                    .collect::<Vec<_>>();
+                for i in 0..len {
+                    for j in (i + 1)..len {
+                        candidates.push(self.with(|s| s.swap_position(i, j)));
+                    }
+                }
+                candidates
+            }
+        };
+        let mut result = Vec::new();
+        for candidate in candidates {
+            if candidate.with(|s| s.execute(operation)).password == self.password
+                && !result
+                    .iter()
+                    .any(|s: &State| s.password == candidate.password)
+            {
+                result.push(candidate);
             }
-            Operation::Reverse(x, y) => self.reverse(*x, *y),
-            Operation::Move(x, y) => self.move_(*y, *x),
         }
+        result
+    }
+    fn with<F>(&self, f: F) -> State
+    where
+        F: FnOnce(&mut State),
+    {
+        let mut state = self.clone();
+        f(&mut state);
+        state
     }
     fn swap_position(&mut self, x: usize, y: usize) {
         self.password.swap(x, y);
@@ -102,6 +148,9 @@ impl State {
         }
     }
     fn rotate_left(&mut self, x: usize) {
+        if self.password.is_empty() {
+            return;
+        }
         let x = x % self.password.len();
         let mut new_password = self.password.split_off(x);
         new_password.append(&mut self.password);
@@ -109,6 +158,9 @@ impl State {
     }
     fn rotate_right(&mut self, x: usize) {
         let len = self.password.len();
+        if len == 0 {
+            return;
+        }
         self.rotate_left(len - x % len);
     }
     fn rotate_based_on_position_of_letter(&mut self, x: char) {
@@ -117,27 +169,6 @@ impl State {
             self.rotate_right(j);
         }
     }
-    fn reverse_rotate_based_on_position_of_letter(&mut self, x: char) {
-        if let Some(i) = self.password.iter().position(|&c| c == x) {
-            // Derive the rotation from the position of x after the operation.
-            // This is only valid for passwords of length 8!
-            assert_eq!(8, self.password.len());
-
-            // pos x before op  0 1 2 3 4 5 6 7
-            // right rotation   1 2 3 4 6 7 8 9
-            // pos x after op   1 3 5 7 2 4 6 0
-            let j = if i % 2 != 0 {
-                (i + 1) / 2
-            } else if i == 0 {
-                1
-            } else {
-                (i / 2) + 5
-            };
-
-            // Reverse the rotation.
-            self.rotate_left(j);
-        }
-    }
     fn reverse(&mut self, x: usize, y: usize) {
         self.permute(|i| if x <= i && i <= y { x + y - i } else { i });
     }
@@ -272,8 +303,50 @@ fn input(i: &str) -> IResult<&str, Vec<Operation>> {
     many1(operation)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// Checks that the operations only use positions within the password.
+fn check_length(operations: &[Operation], password: &str) -> util::Result<()> {
+    let len = password.chars().count();
+    match operations.iter().filter_map(|op| op.max_position()).max() {
+        Some(max_position) if max_position >= len => Err(util::runtime_error!(
+            "password {} is too short for position {}",
+            password,
+            max_position
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn scramble(operations: &[Operation], password: &str) -> util::Result<String> {
+    check_length(operations, password)?;
+    let mut state = State::new(password);
+    for operation in operations {
+        state.execute(operation);
+    }
+    Ok(state.to_string())
+}
+
+/// Returns all passwords which scramble to the given one.
+fn unscramble(operations: &[Operation], password: &str) -> util::Result<Vec<String>> {
+    check_length(operations, password)?;
+    let mut states = vec![State::new(password)];
+    for operation in operations.iter().rev() {
+        let mut new_states: Vec<State> = Vec::new();
+        for state in &states {
+            for s in state.preimages(operation) {
+                if !new_states.iter().any(|n| n.password == s.password) {
+                    new_states.push(s);
+                }
+            }
+        }
+        states = new_states;
+    }
+    let mut result = states.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+    result.sort();
+    Ok(result)
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -284,18 +357,145 @@ fn main() {
     //     println!("{}", operation);
     // }
 
-    let mut state = State::new("abcdefgh");
-    for operation in &input {
-        state.execute(operation);
+    // Options are pairs of `--scramble` or `--unscramble` and a password.
+    let args = env::args().skip(1).collect::<Vec<_>>();
+    if !args.is_empty() {
+        for pair in args.chunks(2) {
+            match pair {
+                [direction, password] if direction == "--scramble" => {
+                    println!("{} -> {}", password, scramble(&input, password)?);
+                }
+                [direction, password] if direction == "--unscramble" => {
+                    let passwords = unscramble(&input, password)?;
+                    if passwords.is_empty() {
+                        println!("{} <- no password", password);
+                    } else {
+                        println!("{} <- {}", password, passwords.join(", "));
+                    }
+                }
+                _ => return Err(util::runtime_error!("invalid arguments {:?}", pair)),
+            }
+        }
+        return Ok(());
     }
-    let result_a = state;
 
-    let mut state = State::new("fbgdceah");
-    for operation in input.iter().rev() {
-        state.reverse_execute(operation);
-    }
-    let result_b = state;
+    let result_a = scramble(&input, "abcdefgh")?;
+
+    let passwords = unscramble(&input, "fbgdceah")?;
+    let result_b = match &passwords[..] {
+        [password] => password,
+        _ => return Err(util::runtime_error!("no unique password: {:?}", passwords)),
+    };
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use crate::input;
+    use crate::scramble;
+    use crate::unscramble;
+    use crate::Operation;
+
+    const EXAMPLE: &str = "\
+swap position 4 with position 0
+swap letter d with letter b
+reverse positions 0 through 4
+rotate left 1 step
+move position 1 to position 4
+move position 3 to position 0
+rotate based on position of letter b
+rotate based on position of letter d
+";
+
+    #[test]
+    fn test_example() {
+        let (_, operations) = input(EXAMPLE).unwrap();
+        assert_eq!(scramble(&operations, "abcde").unwrap(), "decab");
+        let passwords = unscramble(&operations, "decab").unwrap();
+        assert!(passwords.contains(&"abcde".to_string()));
+        assert!(scramble(&operations, "abcd").is_err());
+    }
+
+    fn random_operation(rng: &mut StdRng, password: &[char]) -> Operation {
+        let len = password.len();
+        // Letters which don't occur in the password are allowed too.
+        let mut letter = || {
+            *password
+                .choose(rng)
+                .filter(|_| rng.gen_bool(0.9))
+                .unwrap_or(&'z')
+        };
+        let (x, y) = (letter(), letter());
+        let (i, j) = (rng.gen_range(0..len), rng.gen_range(0..len));
+        let steps = rng.gen_range(0..(2 * len));
+        match rng.gen_range(0..7) {
+            0 => Operation::SwapPosition(i, j),
+            1 => Operation::SwapLetter(x, y),
+            2 => Operation::RotateLeft(steps),
+            3 => Operation::RotateRight(steps),
+            4 => Operation::RotateBasedOnPositionOfLetter(x),
+            5 => Operation::Reverse(i.min(j), i.max(j)),
+            _ => Operation::Move(i, j),
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = StdRng::seed_from_u64(2016);
+        for _ in 0..500 {
+            let len = rng.gen_range(1..=10);
+            // Repeated letters are allowed as well.
+            let alphabet = if rng.gen_bool(0.8) {
+                "abcdefghij"
+            } else {
+                "aab"
+            };
+            let alphabet = alphabet.chars().collect::<Vec<_>>();
+            let password = if alphabet.len() >= len && rng.gen_bool(0.8) {
+                alphabet
+                    .choose_multiple(&mut rng, len)
+                    .copied()
+                    .collect::<Vec<_>>()
+            } else {
+                (0..len)
+                    .map(|_| *alphabet.choose(&mut rng).unwrap())
+                    .collect()
+            };
+            let operations = (0..rng.gen_range(1..20))
+                .map(|_| random_operation(&mut rng, &password))
+                .collect::<Vec<_>>();
+            let password = password.into_iter().collect::<String>();
+
+            let scrambled = scramble(&operations, &password).unwrap();
+            let passwords = unscramble(&operations, &scrambled).unwrap();
+            assert!(passwords.contains(&password));
+            for p in passwords {
+                assert_eq!(scramble(&operations, &p).unwrap(), scrambled);
+            }
+        }
+    }
+
+    #[test]
+    fn test_unique_for_length_8() {
+        let mut rng = StdRng::seed_from_u64(8);
+        for _ in 0..100 {
+            let mut password = "abcdefgh".chars().collect::<Vec<_>>();
+            password.shuffle(&mut rng);
+            let operations = (0..rng.gen_range(1..20))
+                .map(|_| random_operation(&mut rng, &password))
+                .collect::<Vec<_>>();
+            let password = password.into_iter().collect::<String>();
+            let scrambled = scramble(&operations, &password).unwrap();
+            assert_eq!(unscramble(&operations, &scrambled).unwrap(), vec![password]);
+        }
+    }
 }
@@ -1,4 +1,7 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::collections::HashMap;
+use std::env;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
@@ -117,67 +120,106 @@ fn input(i: &str) -> IResult<&str, Vec<Line>> {
     many1(line)(i)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct Node {
+/// The number of states the general search expands before giving up.
+const MAX_GENERAL_STATES: usize = 10_000;
+
+/// The storage nodes of the grid cluster, indexed by position.
+#[derive(Clone, Debug)]
+struct Cluster {
+    nodes: HashMap<Point2d, StorageNode>,
     /// The position of the data we want to access.
     data_pos: Point2d,
-    /// The position of the empty server.
-    hole_pos: Point2d,
+    /// The position where we want to access the data.
+    target_pos: Point2d,
 }
-
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
-
-    // parse input
-    let result = input(&input_data);
-    // println!("{:?}", result);
-
-    let input = result.unwrap().1;
-    // for line in &input {
-    //     println!("{}", line);
-    // }
-
-    let storage_nodes = input
-        .iter()
-        .filter_map(|line| match line {
-            Line::StorageNode(node) => Some(node),
-            _ => None,
+impl Cluster {
+    fn new(storage_nodes: &[StorageNode]) -> util::Result<Cluster> {
+        let nodes = storage_nodes
+            .iter()
+            .map(|sn| (sn.pos, *sn))
+            .collect::<HashMap<_, _>>();
+        let &data_pos = nodes
+            .keys()
+            .filter(|pos| pos.y() == 0)
+            .max_by_key(|pos| pos.x())
+            .ok_or_else(|| util::runtime_error!("no data server found"))?;
+        let target_pos = p2d(0, 0);
+        if !nodes.contains_key(&target_pos) {
+            return Err(util::runtime_error!("no target server found"));
+        }
+        Ok(Cluster {
+            nodes,
+            data_pos,
+            target_pos,
         })
-        .collect::<Vec<_>>();
+    }
 
-    let mut count = 0;
-    for node_a in &storage_nodes {
-        for node_b in &storage_nodes {
-            if !node_a.is_empty() && node_a.pos != node_b.pos && node_a.used() <= node_b.avail() {
-                count += 1;
+    fn neighbors(&self, pos: Point2d) -> impl Iterator<Item = Point2d> + '_ {
+        pos.neighbors_l1()
+            .filter(move |new_pos| self.nodes.contains_key(new_pos))
+    }
+
+    /// Checks that the puzzle reduces to moving a single hole around walls.
+    ///
+    /// This holds if the only possible moves are into a single empty node,
+    /// and the data of every other node is either small enough to fit
+    /// into any node that may become empty, or too large for all of them.
+    /// Returns the position of the empty node, or the reason the model doesn't apply.
+    fn hole_pos(&self) -> Result<Point2d, String> {
+        let empty = self
+            .nodes
+            .values()
+            .filter(|sn| sn.is_empty())
+            .collect::<Vec<_>>();
+        let hole = match empty[..] {
+            [hole] => hole,
+            _ => return Err(format!("{} empty nodes instead of one", empty.len())),
+        };
+        for node_a in self.nodes.values() {
+            for node_b in self.nodes.values() {
+                if !node_a.is_empty()
+                    && !node_b.is_empty()
+                    && node_a.pos != node_b.pos
+                    && node_a.used() <= node_b.avail()
+                {
+                    return Err(format!(
+                        "data of {} fits into non-empty {}",
+                        node_a.name(),
+                        node_b.name()
+                    ));
+                }
             }
         }
+        let (movable, walls): (Vec<&StorageNode>, Vec<&StorageNode>) =
+            self.nodes.values().partition(|sn| sn.used() <= hole.size());
+        let min_size = movable.iter().map(|sn| sn.size()).min().unwrap_or(0);
+        let max_size = movable.iter().map(|sn| sn.size()).max().unwrap_or(0);
+        if let Some(sn) = movable.iter().find(|sn| sn.used() > min_size) {
+            return Err(format!("data of {} doesn't fit everywhere", sn.name()));
+        }
+        if let Some(sn) = walls.iter().find(|sn| sn.used() <= max_size) {
+            return Err(format!("data of {} fits somewhere", sn.name()));
+        }
+        if walls.iter().any(|sn| sn.pos == self.data_pos) {
+            return Err("the data can't be moved".to_string());
+        }
+        Ok(hole.pos)
     }
-    let result_a = count;
-
-    let map = storage_nodes
-        .into_iter()
-        .map(|sn| (sn.pos, sn))
-        .collect::<HashMap<_, _>>();
 
-    let (&hole_pos, _) = map
-        .iter()
-        .find(|(_pos, sn)| sn.is_empty())
-        .expect("no empty server found");
-    let &data_pos = map
-        .keys()
-        .filter(|pos| pos.y() == 0)
-        .max_by_key(|pos| pos.x())
-        .expect("no data server found");
-
-    let start_node = Node { data_pos, hole_pos };
-    let target_data_pos = p2d(0, 0);
-    let successors = |n: &Node| {
-        let dest_sn = map[&n.hole_pos];
-        n.hole_pos
-            .neighbors_l1()
-            .filter_map(|new_hole_pos| {
-                if let Some(src_sn) = map.get(&new_hole_pos) {
+    /// Returns the moves of a shortest solution as pairs of source and destination.
+    ///
+    /// Searches for the positions of the data and the hole,
+    /// which requires the assumptions checked by `hole_pos`.
+    fn solve_hole(&self, hole_pos: Point2d) -> Option<Vec<(Point2d, Point2d)>> {
+        let start_node = Node {
+            data_pos: self.data_pos,
+            hole_pos,
+        };
+        let successors = |n: &Node| {
+            let dest_sn = self.nodes[&n.hole_pos];
+            self.neighbors(n.hole_pos)
+                .filter_map(|new_hole_pos| {
+                    let src_sn = self.nodes[&new_hole_pos];
                     // Can we move the data from the source storage node
                     // to the destination storage node?
                     if src_sn.used() <= dest_sn.size() {
@@ -200,19 +242,330 @@ fn main() {
                     } else {
                         None
                     }
-                } else {
-                    None
+                })
+                .collect::<Vec<_>>()
+        };
+        let heuristic = |n: &Node| n.data_pos.distance_l1(self.target_pos);
+        let success = |n: &Node| n.data_pos == self.target_pos;
+
+        let (path, _cost) = astar(&start_node, successors, heuristic, success)?;
+        Some(
+            path.windows(2)
+                .map(|w| (w[1].hole_pos, w[0].hole_pos))
+                .collect(),
+        )
+    }
+
+    /// Returns the moves of a shortest solution as pairs of source and destination.
+    ///
+    /// Searches over the used space of all nodes, so it allows several empty nodes
+    /// and moves into nodes which are not empty, but is only feasible for small grids.
+    /// Returns `None` if there is no solution, and an error if the search
+    /// would expand more than `max_states` states.
+    fn solve_general(&self, max_states: usize) -> util::Result<Option<Vec<(Point2d, Point2d)>>> {
+        let mut positions = self.nodes.keys().copied().collect::<Vec<_>>();
+        positions.sort_by_key(|pos| (pos.y(), pos.x()));
+        let indices = positions
+            .iter()
+            .enumerate()
+            .map(|(i, &pos)| (pos, i))
+            .collect::<HashMap<_, _>>();
+        let sizes = positions
+            .iter()
+            .map(|pos| self.nodes[pos].size())
+            .collect::<Vec<_>>();
+        let neighbors = positions
+            .iter()
+            .map(|&pos| self.neighbors(pos).map(|n| indices[&n]).collect())
+            .collect::<Vec<Vec<_>>>();
+
+        let start_state = GeneralState {
+            used: positions.iter().map(|pos| self.nodes[pos].used()).collect(),
+            data: indices[&self.data_pos],
+        };
+        let target = indices[&self.target_pos];
+        let successors = |s: &GeneralState| {
+            let mut result = Vec::new();
+            for src in 0..s.used.len() {
+                if s.used[src] == 0 {
+                    continue;
+                }
+                for &dest in &neighbors[src] {
+                    if s.used[src] <= sizes[dest] - s.used[dest] {
+                        let mut used = s.used.clone();
+                        used[dest] += used[src];
+                        used[src] = 0;
+                        let data = if s.data == src { dest } else { s.data };
+                        result.push(GeneralState { used, data });
+                    }
                 }
+            }
+            result
+        };
+        let heuristic = |s: &GeneralState| positions[s.data].distance_l1(self.target_pos);
+
+        // An A* search which fails explicitly when it exceeds the state limit.
+        let mut parents = HashMap::<GeneralState, (i64, Option<GeneralState>)>::new();
+        parents.insert(start_state.clone(), (0, None));
+        let mut queue = BinaryHeap::new();
+        queue.push(Reverse((heuristic(&start_state), 0, start_state)));
+        let mut expanded = 0;
+        let path = loop {
+            let Some(Reverse((_, cost, state))) = queue.pop() else {
+                return Ok(None);
+            };
+            if parents[&state].0 < cost {
+                // We already found a shorter path to this state.
+                continue;
+            }
+            if state.data == target {
+                let mut path = vec![state];
+                while let Some(parent) = &parents[&path[path.len() - 1]].1 {
+                    path.push(parent.clone());
+                }
+                path.reverse();
+                break path;
+            }
+            expanded += 1;
+            if expanded > max_states {
+                return Err(util::runtime_error!(
+                    "state limit of {} exceeded",
+                    max_states
+                ));
+            }
+            for next in successors(&state) {
+                let next_cost = cost + 1;
+                let improved = match parents.get(&next) {
+                    Some(&(c, _)) => next_cost < c,
+                    None => true,
+                };
+                if improved {
+                    parents.insert(next.clone(), (next_cost, Some(state.clone())));
+                    queue.push(Reverse((next_cost + heuristic(&next), next_cost, next)));
+                }
+            }
+        };
+        Ok(Some(
+            path.windows(2)
+                .map(|w| {
+                    // The source is the node that was emptied by the move.
+                    let src = (0..w[0].used.len())
+                        .find(|&i| w[0].used[i] != 0 && w[1].used[i] == 0)
+                        .unwrap();
+                    let dest = (0..w[0].used.len())
+                        .find(|&i| w[1].used[i] > w[0].used[i])
+                        .unwrap();
+                    (positions[src], positions[dest])
+                })
+                .collect(),
+        ))
+    }
+
+    /// Returns a shortest sequence of moves which brings the data to the target.
+    fn solve(&self) -> util::Result<Vec<Move>> {
+        let steps = match self.hole_pos() {
+            Ok(hole_pos) => self.solve_hole(hole_pos),
+            Err(_) => self.solve_general(MAX_GENERAL_STATES)?,
+        };
+        let steps = steps.ok_or_else(|| util::runtime_error!("no solution found"))?;
+
+        // Replay the moves to find the amounts of data moved.
+        let mut used = self
+            .nodes
+            .iter()
+            .map(|(&pos, sn)| (pos, sn.used()))
+            .collect::<HashMap<_, _>>();
+        Ok(steps
+            .into_iter()
+            .map(|(from, to)| {
+                let amount = used.insert(from, 0).unwrap();
+                *used.get_mut(&to).unwrap() += amount;
+                Move { from, to, amount }
             })
-            .collect::<Vec<_>>()
-    };
-    let heuristic = |n: &Node| n.data_pos.distance_l1(target_data_pos);
-    let success = |n: &Node| n.data_pos == target_data_pos;
+            .collect())
+    }
+
+    /// Returns a map of the grid as in the puzzle statement.
+    ///
+    /// The data is `G`, the target is in parentheses,
+    /// empty nodes are `_` and nodes whose data is too large for any empty node are `#`.
+    fn map(&self) -> String {
+        let max_x = self.nodes.keys().map(|pos| pos.x()).max().unwrap_or(0);
+        let max_y = self.nodes.keys().map(|pos| pos.y()).max().unwrap_or(0);
+        let max_empty_size = self
+            .nodes
+            .values()
+            .filter(|sn| sn.is_empty())
+            .map(|sn| sn.size())
+            .max()
+            .unwrap_or(0);
+        let mut result = String::new();
+        for y in 0..=max_y {
+            for x in 0..=max_x {
+                let pos = p2d(x, y);
+                let c = match self.nodes.get(&pos) {
+                    None => ' ',
+                    Some(_) if pos == self.data_pos => 'G',
+                    Some(sn) if sn.used() > max_empty_size => '#',
+                    Some(sn) if sn.is_empty() => '_',
+                    Some(_) => '.',
+                };
+                if pos == self.target_pos {
+                    result.push_str(&format!("({})", c));
+                } else {
+                    result.push_str(&format!(" {} ", c));
+                }
+            }
+            result.push('\n');
+        }
+        result
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+struct Node {
+    /// The position of the data we want to access.
+    data_pos: Point2d,
+    /// The position of the empty server.
+    hole_pos: Point2d,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+struct GeneralState {
+    /// The used space of the storage nodes.
+    used: Vec<usize>,
+    /// The index of the storage node with the data we want to access.
+    data: usize,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Move {
+    from: Point2d,
+    to: Point2d,
+    amount: usize,
+}
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(
+            f,
+            "move {}T from x{}-y{} to x{}-y{}",
+            self.amount,
+            self.from.x(),
+            self.from.y(),
+            self.to.x(),
+            self.to.y()
+        )
+    }
+}
+
+fn storage_nodes(input: &[Line]) -> Vec<StorageNode> {
+    input
+        .iter()
+        .filter_map(|line| match line {
+            Line::StorageNode(node) => Some(*node),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // The option `--moves` prints the map, the moves,
+    // and why the general search is used if it is.
+    let print_moves = env::args().nth(1).is_some_and(|arg| arg == "--moves");
+
+    // parse input
+    let result = input(&input_data);
+    // println!("{:?}", result);
+
+    let input = result.unwrap().1;
+    // for line in &input {
+    //     println!("{}", line);
+    // }
 
-    let search_result = astar(&start_node, successors, heuristic, success);
-    let (_path, cost) = search_result.unwrap();
-    let result_b = cost;
+    let storage_nodes = storage_nodes(&input);
+
+    let mut count = 0;
+    for node_a in &storage_nodes {
+        for node_b in &storage_nodes {
+            if !node_a.is_empty() && node_a.pos != node_b.pos && node_a.used() <= node_b.avail() {
+                count += 1;
+            }
+        }
+    }
+    let result_a = count;
+
+    let cluster = Cluster::new(&storage_nodes)?;
+    let moves = cluster.solve()?;
+    if print_moves {
+        if let Err(reason) = cluster.hole_pos() {
+            println!("using general search: {}", reason);
+        }
+        print!("{}", cluster.map());
+        for m in &moves {
+            println!("{}", m);
+        }
+    }
+    let result_b = moves.len();
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lowdim::p2d;
+
+    use crate::input;
+    use crate::storage_nodes;
+    use crate::Cluster;
+    use crate::MAX_GENERAL_STATES;
+
+    const EXAMPLE: &str = "\
+Filesystem            Size  Used  Avail  Use%
+/dev/grid/node-x0-y0   10T    8T     2T   80%
+/dev/grid/node-x0-y1   11T    6T     5T   54%
+/dev/grid/node-x0-y2   32T   28T     4T   87%
+/dev/grid/node-x1-y0    9T    7T     2T   77%
+/dev/grid/node-x1-y1    8T    0T     8T    0%
+/dev/grid/node-x1-y2   11T    7T     4T   63%
+/dev/grid/node-x2-y0   10T    6T     4T   60%
+/dev/grid/node-x2-y1    9T    8T     1T   88%
+/dev/grid/node-x2-y2    9T    6T     3T   66%
+";
+
+    fn cluster(data: &str) -> Cluster {
+        let (_, input) = input(data).unwrap();
+        Cluster::new(&storage_nodes(&input)).unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        let cluster = cluster(EXAMPLE);
+        assert_eq!(cluster.hole_pos(), Ok(p2d(1, 1)));
+        assert_eq!(cluster.solve().unwrap().len(), 7);
+        let steps = cluster.solve_general(MAX_GENERAL_STATES).unwrap();
+        assert_eq!(steps.unwrap().len(), 7);
+        let error = cluster.solve_general(3).unwrap_err();
+        assert_eq!(error.to_string(), "state limit of 3 exceeded");
+        assert_eq!(cluster.map(), "(.) .  G \n .  _  . \n #  .  . \n");
+    }
+
+    #[test]
+    fn test_general() {
+        // A second empty node shortens the way.
+        let data = EXAMPLE.replace(
+            "node-x0-y1   11T    6T     5T",
+            "node-x0-y1   11T    0T    11T",
+        );
+        let cluster = cluster(&data);
+        assert!(cluster.hole_pos().is_err());
+        let moves = cluster.solve().unwrap();
+        assert_eq!(moves.len(), 4);
+        assert_eq!(moves[3].amount, 6);
+        assert_eq!(moves[3].to, p2d(0, 0));
+    }
 }
@@ -1,4 +1,5 @@
 use std::collections::HashMap;
+use std::collections::VecDeque;
 use std::fmt;
 use std::io;
 use std::ops;
@@ -12,11 +13,13 @@ use nom::combinator::value;
 use nom::multi::many1;
 use nom::IResult;
 
-use pathfinding::prelude::astar;
-
 use lowdim::Array2d;
 use lowdim::Point2d;
 
+use util::tsp;
+use util::tsp::Objective;
+use util::tsp::Shape;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Square {
     // Point of interest
@@ -99,14 +102,26 @@ fn input(i: &str) -> IResult<&str, Vec<Vec<Square>>> {
     many1(line)(i)
 }
 
-#[derive(Clone, Debug, PartialEq, Eq, Hash)]
-struct Node {
-    pos: Point2d,
-    pois: Vec<Point2d>,
+/// Returns the distances from a start position to all reachable positions.
+fn distances(map: &Array2d<i64, Square>, start: Point2d) -> HashMap<Point2d, i64> {
+    let mut result = HashMap::new();
+    result.insert(start, 0);
+    let mut queue = VecDeque::new();
+    queue.push_back(start);
+    while let Some(pos) = queue.pop_front() {
+        let d = result[&pos];
+        for new_pos in pos.neighbors_l1() {
+            if map.get(new_pos).is_some_and(|sq| sq.is_open()) && !result.contains_key(&new_pos) {
+                result.insert(new_pos, d + 1);
+                queue.push_back(new_pos);
+            }
+        }
+    }
+    result
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -121,7 +136,7 @@ fn main() {
     // }
 
     let map = Array2d::from_vec(input);
-    let pois = map
+    let mut pois = map
         .bbox()
         .iter()
         .flat_map(|p| {
@@ -131,76 +146,37 @@ fn main() {
                 None
             }
         })
-        .collect::<HashMap<_, _>>();
-
-    let start_pos = pois[&0];
-
-    let start_node = Node {
-        pos: start_pos,
-        pois: pois
-            .values()
-            .filter(|&&poi| poi != start_pos)
-            .cloned()
-            .collect::<Vec<_>>(),
-    };
-    let successors = |n: &Node| {
-        n.pos
-            .neighbors_l1()
-            .filter_map(|new_pos| {
-                if let Some(sq) = map.get(new_pos) {
-                    if sq.is_open() {
-                        let new_pois = n
-                            .pois
-                            .iter()
-                            .filter(|&&poi| poi != new_pos)
-                            .cloned()
-                            .collect::<Vec<_>>();
-                        Some((
-                            Node {
-                                pos: new_pos,
-                                pois: new_pois,
-                            },
-                            1,
-                        ))
-                    } else {
-                        None
-                    }
-                } else {
-                    None
-                }
-            })
-            .collect::<Vec<_>>()
-    };
-
-    let heuristic_a = |n: &Node| {
-        if let Some(d) = n.pois.iter().map(|&poi| n.pos.distance_l1(poi)).max() {
-            d
-        } else {
-            0
-        }
-    };
-    let success_a = |n: &Node| n.pois.is_empty();
-    let search_result = astar(&start_node, successors, heuristic_a, success_a);
-    let (_path, cost) = search_result.unwrap();
-    let result_a = cost;
-
-    let heuristic_b = |n: &Node| {
-        if let Some(d) = n
-            .pois
-            .iter()
-            .map(|&poi| n.pos.distance_l1(poi) + poi.distance_l1(start_pos))
-            .max()
-        {
-            d
-        } else {
-            n.pos.distance_l1(start_pos)
-        }
-    };
-    let success_b = |n: &Node| n.pos == start_pos && n.pois.is_empty();
-    let search_result = astar(&start_node, successors, heuristic_b, success_b);
-    let (_path, cost) = search_result.unwrap();
-    let result_b = cost;
+        .collect::<Vec<_>>();
+    pois.sort();
+
+    // Compute the distances between the points of interest.
+    let costs = pois
+        .iter()
+        .map(|&(d0, p0)| {
+            let ds = distances(&map, p0);
+            pois.iter()
+                .map(|&(d1, p1)| {
+                    ds.get(&p1).copied().ok_or_else(|| {
+                        util::runtime_error!("point {} is unreachable from point {}", d1, d0)
+                    })
+                })
+                .collect::<util::Result<Vec<_>>>()
+        })
+        .collect::<util::Result<Vec<_>>>()?;
+
+    // The robot starts at the point of interest 0.
+    let start = pois.iter().position(|&(d, _)| d == 0).unwrap();
+
+    let route_a = tsp::solve(&costs, Objective::Min, Shape::Path, Some(start))
+        .ok_or_else(|| util::runtime_error!("too many points of interest"))?;
+    let result_a = route_a.cost;
+
+    let route_b = tsp::solve(&costs, Objective::Min, Shape::Tour, Some(start))
+        .ok_or_else(|| util::runtime_error!("too many points of interest"))?;
+    let result_b = route_b.cost;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -1,5 +1,6 @@
 use std::collections::HashMap;
-use std::collections::HashSet;
+use std::env;
+use std::error;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
@@ -75,68 +76,240 @@ fn input(i: &str) -> IResult<&str, Vec<Program>> {
     many1(program)(i)
 }
 
+/// The ways in which parent/child lists can fail to describe a rooted tree.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum TreeError {
+    DuplicateNode(String),
+    MissingChild {
+        parent: String,
+        child: String,
+    },
+    MultipleParents {
+        child: String,
+        parents: [String; 2],
+    },
+    MultipleRoots(Vec<String>),
+    /// The nodes which are not reachable from a root, since they lie on or below a cycle.
+    Cycle(Vec<String>),
+}
+impl fmt::Display for TreeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TreeError::DuplicateNode(name) => write!(f, "node {} is defined twice", name),
+            TreeError::MissingChild { parent, child } => {
+                write!(f, "child {} of {} is not defined", child, parent)
+            }
+            TreeError::MultipleParents { child, parents } => write!(
+                f,
+                "node {} has parents {} and {}",
+                child, parents[0], parents[1]
+            ),
+            TreeError::MultipleRoots(names) => write!(f, "multiple roots {}", names.join(", ")),
+            TreeError::Cycle(names) => write!(f, "cycle through {}", names.join(", ")),
+        }
+    }
+}
+impl error::Error for TreeError {}
+
+/// A rooted tree of named nodes.
+///
+/// The nodes are identified by their index in the order in which they were given.
 #[derive(Clone, Debug)]
-struct SubtreeInfo {
-    /// The total weight of the subtree.
-    total_weight: i64,
-    /// The corrected weight of the program with the wrong weight
-    /// if it is in the subtree, otherwise `None`.
-    corrected_weight: Option<i64>,
-}
-
-fn subtree_info(
-    programs: &HashMap<String, Program>,
-    subtree_infos: &mut HashMap<String, SubtreeInfo>,
-    name: &str,
-) -> SubtreeInfo {
-    if let Some(info) = subtree_infos.get(name) {
-        info.clone()
-    } else {
-        let p = programs[name].clone();
-        let mut weight_names = HashMap::new();
-        let mut total_weight = p.weight;
-        for n in &p.subprogram_names {
-            let info = subtree_info(programs, subtree_infos, n);
-            total_weight += info.total_weight;
-
-            let entry = weight_names
-                .entry(info.total_weight)
-                .or_insert_with(Vec::new);
-            entry.push(n.to_string());
+struct Tree {
+    names: Vec<String>,
+    children: Vec<Vec<usize>>,
+    root: usize,
+}
+impl Tree {
+    /// Builds the tree from the nodes given as names with the names of their children.
+    fn new<'a, I>(nodes: I) -> Result<Tree, TreeError>
+    where
+        I: IntoIterator<Item = (&'a str, &'a [String])>,
+    {
+        let nodes = nodes.into_iter().collect::<Vec<_>>();
+
+        let mut names = Vec::new();
+        let mut indices = HashMap::new();
+        for &(name, _) in &nodes {
+            if indices.insert(name.to_string(), names.len()).is_some() {
+                return Err(TreeError::DuplicateNode(name.to_string()));
+            }
+            names.push(name.to_string());
         }
-        let corrected_weight = {
-            if weight_names.len() > 1 {
-                // p is unbalanced, find subprogram with wrong weight
-                let (wrong_weight, wrong_weight_names) =
-                    weight_names.iter().find(|(_w, ns)| ns.len() == 1).unwrap();
-                let wrong_weight_name = &wrong_weight_names[0];
-                let wrong_weight_info = subtree_info(programs, subtree_infos, wrong_weight_name);
-                if let Some(cw) = wrong_weight_info.corrected_weight {
-                    // The wrong weight is deeper inside the subtree, just copy it.
-                    Some(cw)
-                } else {
-                    // The wrong weight is at an immediate subprogram.
-                    let (right_weight, _) =
-                        weight_names.iter().find(|(_w, ns)| ns.len() > 1).unwrap();
-                    let wrong_weight_program = &programs[wrong_weight_name];
-                    let error = wrong_weight - right_weight;
-                    Some(wrong_weight_program.weight - error)
+
+        let mut children = Vec::new();
+        let mut parents: Vec<Option<usize>> = vec![None; names.len()];
+        for (i, &(name, child_names)) in nodes.iter().enumerate() {
+            let mut cs = Vec::new();
+            for child in child_names {
+                let c = *indices.get(child).ok_or_else(|| TreeError::MissingChild {
+                    parent: name.to_string(),
+                    child: child.clone(),
+                })?;
+                if let Some(p) = parents[c] {
+                    return Err(TreeError::MultipleParents {
+                        child: child.clone(),
+                        parents: [names[p].clone(), name.to_string()],
+                    });
                 }
-            } else {
-                None
+                parents[c] = Some(i);
+                cs.push(c);
             }
+            children.push(cs);
+        }
+
+        let roots = (0..names.len())
+            .filter(|&i| parents[i].is_none())
+            .collect::<Vec<_>>();
+        if roots.len() > 1 {
+            return Err(TreeError::MultipleRoots(
+                roots.iter().map(|&i| names[i].clone()).collect(),
+            ));
+        }
+        let unreachable = match roots.first() {
+            Some(&root) => {
+                let mut reachable = vec![false; names.len()];
+                let mut stack = vec![root];
+                while let Some(i) = stack.pop() {
+                    reachable[i] = true;
+                    stack.extend(&children[i]);
+                }
+                (0..names.len()).filter(|&i| !reachable[i]).collect()
+            }
+            // Every node has a parent, so following them leads into a cycle.
+            None => (0..names.len()).collect::<Vec<_>>(),
         };
-        let info = SubtreeInfo {
-            total_weight,
-            corrected_weight,
-        };
-        subtree_infos.insert(name.to_string(), info.clone());
-        info
+        if !unreachable.is_empty() {
+            return Err(TreeError::Cycle(
+                unreachable.iter().map(|&i| names[i].clone()).collect(),
+            ));
+        }
+        let root = roots[0];
+
+        Ok(Tree {
+            names,
+            children,
+            root,
+        })
+    }
+    fn len(&self) -> usize {
+        self.names.len()
+    }
+    fn root(&self) -> usize {
+        self.root
+    }
+    fn name(&self, i: usize) -> &str {
+        &self.names[i]
+    }
+    /// Returns the nodes in an order where children come before their parents.
+    fn post_order(&self) -> Vec<usize> {
+        let mut result = Vec::new();
+        let mut stack = vec![(self.root, false)];
+        while let Some((i, expanded)) = stack.pop() {
+            if expanded {
+                result.push(i);
+            } else {
+                stack.push((i, true));
+                stack.extend(self.children[i].iter().rev().map(|&c| (c, false)));
+            }
+        }
+        result
+    }
+    /// Computes a value for each subtree from the node and the values of its children.
+    fn aggregate<T, F>(&self, mut f: F) -> Vec<T>
+    where
+        T: Clone,
+        F: FnMut(usize, &[T]) -> T,
+    {
+        let mut values: Vec<Option<T>> = vec![None; self.len()];
+        for i in self.post_order() {
+            let child_values = self.children[i]
+                .iter()
+                .map(|&c| values[c].clone().unwrap())
+                .collect::<Vec<_>>();
+            values[i] = Some(f(i, &child_values));
+        }
+        values.into_iter().map(Option::unwrap).collect()
+    }
+    /// Returns the total weight of each subtree.
+    fn subtree_weights(&self, weights: &[i64]) -> Vec<i64> {
+        self.aggregate(|i, child_weights| weights[i] + child_weights.iter().sum::<i64>())
+    }
+    /// Finds the single node whose weight must change so that at every node
+    /// all subtrees of the children have the same total weight.
+    ///
+    /// Returns `Ok(None)` if the tree is already balanced.
+    fn find_imbalance(&self, weights: &[i64]) -> util::Result<Option<Correction>> {
+        let totals = self.subtree_weights(weights);
+        let mut node = self.root;
+        // The total weight the subtree of the current node should have, if known.
+        let mut target = None;
+        loop {
+            let mut counts = HashMap::new();
+            for &c in &self.children[node] {
+                *counts.entry(totals[c]).or_insert(0) += 1;
+            }
+            if counts.len() <= 1 {
+                // The children are balanced, so the node itself must be corrected.
+                return Ok(target.map(|t: i64| Correction {
+                    node,
+                    weight: weights[node] + t - totals[node],
+                }));
+            }
+            let majority = counts
+                .iter()
+                .filter(|&(_, &n)| n > 1)
+                .map(|(&w, _)| w)
+                .collect::<Vec<_>>();
+            if majority.len() != 1 || counts.len() > 2 {
+                return Err(util::runtime_error!(
+                    "cannot balance {} by changing a single weight",
+                    self.names[node]
+                ));
+            }
+            let right_total = majority[0];
+            node = self.children[node]
+                .iter()
+                .copied()
+                .find(|&c| totals[c] != right_total)
+                .unwrap();
+            target = Some(right_total);
+        }
+    }
+    /// Renders the tree with one node per line, indented by its depth.
+    fn render<F>(&self, label: F) -> String
+    where
+        F: Fn(usize) -> String,
+    {
+        let mut result = String::new();
+        let mut stack = vec![(self.root, 0)];
+        while let Some((i, depth)) = stack.pop() {
+            result.push_str(&format!("{}{}\n", "  ".repeat(depth), label(i)));
+            stack.extend(self.children[i].iter().rev().map(|&c| (c, depth + 1)));
+        }
+        result
     }
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// A node with a wrong weight, and the weight it should have.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Correction {
+    node: usize,
+    weight: i64,
+}
+
+fn tree(programs: &[Program]) -> Result<Tree, TreeError> {
+    Tree::new(
+        programs
+            .iter()
+            .map(|p| (p.name.as_str(), p.subprogram_names.as_slice())),
+    )
+}
+
+fn main() -> util::Result<()> {
+    let print_tree = env::args().nth(1).is_some_and(|arg| arg == "--tree");
+
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -147,26 +320,144 @@ fn main() {
     //     println!("{}", program);
     // }
 
-    let mut parents = HashSet::new();
-    let mut children = HashSet::new();
-    for program in &input {
-        parents.insert(program.name.clone());
-        for n in &program.subprogram_names {
-            children.insert(n.clone());
-        }
-    }
-    let diff = parents.difference(&children).cloned().collect::<Vec<_>>();
-    let root_program = &diff[0];
-    let result_a = root_program;
+    let tree = tree(&input)?;
+    let weights = input.iter().map(|p| p.weight).collect::<Vec<_>>();
 
-    let mut programs = HashMap::new();
-    for p in &input {
-        programs.insert(p.name.clone(), p.clone());
+    if print_tree {
+        let totals = tree.subtree_weights(&weights);
+        let text = tree.render(|i| format!("{} ({}) [{}]", tree.name(i), weights[i], totals[i]));
+        print!("{}", text);
     }
-    let mut subtree_infos = HashMap::new();
-    let info = subtree_info(&programs, &mut subtree_infos, root_program);
-    let result_b = info.corrected_weight.unwrap();
+
+    let result_a = tree.name(tree.root());
+
+    let correction = tree
+        .find_imbalance(&weights)?
+        .ok_or_else(|| util::runtime_error!("the tower is balanced"))?;
+    let result_b = correction.weight;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::input;
+    use crate::tree;
+    use crate::Correction;
+    use crate::Tree;
+    use crate::TreeError;
+
+    const EXAMPLE: &str = "\
+pbga (66)
+xhth (57)
+ebii (61)
+havc (66)
+ktlj (57)
+fwft (72) -> ktlj, cntj, xhth
+qoyq (66)
+padx (45) -> pbga, havc, qoyq
+tknk (41) -> ugml, padx, fwft
+jptl (61)
+ugml (68) -> gyxo, ebii, jptl
+gyxo (61)
+cntj (57)
+";
+
+    fn index(tree: &Tree, name: &str) -> usize {
+        (0..tree.len()).find(|&i| tree.name(i) == name).unwrap()
+    }
+
+    fn tree_error(data: &str) -> TreeError {
+        let (_, programs) = input(data).unwrap();
+        tree(&programs).unwrap_err()
+    }
+
+    #[test]
+    fn test_example() {
+        let (_, programs) = input(EXAMPLE).unwrap();
+        let tree = tree(&programs).unwrap();
+        assert_eq!(tree.name(tree.root()), "tknk");
+
+        let mut weights = programs.iter().map(|p| p.weight).collect::<Vec<_>>();
+        let totals = tree.subtree_weights(&weights);
+        assert_eq!(totals[index(&tree, "ugml")], 251);
+        assert_eq!(totals[index(&tree, "padx")], 243);
+        assert_eq!(totals[index(&tree, "fwft")], 243);
+
+        let ugml = index(&tree, "ugml");
+        let correction = tree.find_imbalance(&weights).unwrap();
+        assert_eq!(
+            correction,
+            Some(Correction {
+                node: ugml,
+                weight: 60
+            })
+        );
+        weights[ugml] = 60;
+        assert_eq!(tree.find_imbalance(&weights).unwrap(), None);
+    }
+
+    #[test]
+    fn test_deep_imbalance() {
+        let (_, programs) = input(EXAMPLE).unwrap();
+        let tree = tree(&programs).unwrap();
+        let mut weights = programs.iter().map(|p| p.weight).collect::<Vec<_>>();
+        weights[index(&tree, "ugml")] = 60;
+        let jptl = index(&tree, "jptl");
+        weights[jptl] = 70;
+        let correction = tree.find_imbalance(&weights).unwrap();
+        assert_eq!(
+            correction,
+            Some(Correction {
+                node: jptl,
+                weight: 61
+            })
+        );
+    }
+
+    #[test]
+    fn test_render() {
+        let (_, programs) = input("a (1) -> b, c\nb (2) -> d\nc (3)\nd (4)\n").unwrap();
+        let tree = tree(&programs).unwrap();
+        let text = tree.render(|i| tree.name(i).to_string());
+        assert_eq!(text, "a\n  b\n    d\n  c\n");
+        assert_eq!(tree.post_order(), vec![3, 1, 2, 0]);
+    }
+
+    #[test]
+    fn test_errors() {
+        assert_eq!(
+            tree_error("a (1) -> b\na (2)\n"),
+            TreeError::DuplicateNode("a".to_string())
+        );
+        assert_eq!(
+            tree_error("a (1) -> b\n"),
+            TreeError::MissingChild {
+                parent: "a".to_string(),
+                child: "b".to_string()
+            }
+        );
+        assert_eq!(
+            tree_error("a (1) -> c\nb (1) -> c\nc (1)\n"),
+            TreeError::MultipleParents {
+                child: "c".to_string(),
+                parents: ["a".to_string(), "b".to_string()]
+            }
+        );
+        assert_eq!(
+            tree_error("a (1)\nb (1)\n"),
+            TreeError::MultipleRoots(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            tree_error("a (1) -> b\nb (1) -> a\n"),
+            TreeError::Cycle(vec!["a".to_string(), "b".to_string()])
+        );
+        assert_eq!(
+            tree_error("r (1)\na (1) -> b\nb (1) -> a\n"),
+            TreeError::Cycle(vec!["a".to_string(), "b".to_string()])
+        );
+    }
 }
@@ -1,11 +1,20 @@
+use std::io;
+
+use nom::character::complete::alpha1;
+
 use lowdim::p2d;
 use lowdim::v2d;
 
 use util::knot_hash;
+use util::parse::parse_input;
 use util::DisjointSets;
 
-fn main() {
-    let input = "wenycdww";
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, alpha1)?;
+    util::validate(&input_data, "", &input);
 
     let mut count = 0;
     for i in 0..128 {
@@ -46,4 +55,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
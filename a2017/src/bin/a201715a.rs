@@ -1,7 +1,45 @@
-fn main() {
+use core::fmt;
+
+use std::io;
+
+use nom::bytes::complete::tag;
+use nom::character::complete::line_ending;
+use nom::IResult;
+
+use util::parse::parse_input;
+use util::parse::uint;
+
+#[derive(Clone, Copy, Debug)]
+struct Input {
+    start_a: u64,
+    start_b: u64,
+}
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Generator A starts with {}", self.start_a)?;
+        writeln!(f, "Generator B starts with {}", self.start_b)
+    }
+}
+
+fn input(i: &str) -> IResult<&str, Input> {
+    let (i, _) = tag("Generator A starts with ")(i)?;
+    let (i, start_a) = uint(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, _) = tag("Generator B starts with ")(i)?;
+    let (i, start_b) = uint(i)?;
+    Ok((i, Input { start_a, start_b }))
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
+
     let m: u64 = 2147483647;
-    let mut a = 516;
-    let mut b = 190;
+    let mut a = input.start_a;
+    let mut b = input.start_b;
 
     let mut count = 0;
     for _ in 0..40_000_000 {
@@ -12,4 +50,6 @@ fn main() {
         }
     }
     println!("{}", count);
+
+    Ok(())
 }
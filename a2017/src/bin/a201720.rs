@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::cmp::Ordering;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
@@ -17,7 +17,6 @@ use nom::IResult;
 use lowdim::v3d;
 use lowdim::Point3d;
 use lowdim::Vec3d;
-use lowdim::Vector;
 
 fn fmt_v3d(f: &mut fmt::Formatter<'_>, v: Vec3d) -> fmt::Result {
     write!(f, "<{},{},{}>", v.x(), v.y(), v.z())
@@ -30,37 +29,117 @@ struct Particle {
     v: Vec3d,
     a: Vec3d,
 }
+fn coords(v: Vec3d) -> [i64; 3] {
+    [v.x(), v.y(), v.z()]
+}
+
+/// Returns the square root if the number is a perfect square.
+fn exact_sqrt(n: i64) -> Option<i64> {
+    if n < 0 {
+        return None;
+    }
+    let mut r = (n as f64).sqrt() as i64;
+    // Correct rounding errors of the floating point square root.
+    while r * r > n {
+        r -= 1;
+    }
+    while (r + 1) * (r + 1) <= n {
+        r += 1;
+    }
+    if r * r == n {
+        Some(r)
+    } else {
+        None
+    }
+}
+
+/// The non-negative integer solutions of a quadratic equation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Roots {
+    /// All coefficients are zero, so every value is a solution.
+    All,
+    /// The solutions in increasing order.
+    Some(Vec<i64>),
+}
+
+/// Solves `a t^2 + b t + c = 0` for integers `t >= 0`.
+fn quadratic_roots(a: i64, b: i64, c: i64) -> Roots {
+    let candidates = if a == 0 {
+        if b == 0 {
+            return if c == 0 {
+                Roots::All
+            } else {
+                Roots::Some(vec![])
+            };
+        }
+        vec![(-c, b)]
+    } else {
+        match exact_sqrt(b * b - 4 * a * c) {
+            Some(s) => vec![(-b - s, 2 * a), (-b + s, 2 * a)],
+            None => vec![],
+        }
+    };
+    let mut roots = candidates
+        .into_iter()
+        .filter(|&(n, d)| n % d == 0 && n / d >= 0)
+        .map(|(n, d)| n / d)
+        .collect::<Vec<_>>();
+    roots.sort();
+    roots.dedup();
+    Roots::Some(roots)
+}
+
 impl Particle {
-    fn step(&self) -> Particle {
-        let id = self.id;
-        let a = self.a;
-        let v = self.v + a;
-        let p = self.p + v;
-        Particle { id, p, v, a }
-    }
-    /// Returns `true` if it can be proven that two particles can't collide,
-    /// and `false` if they may or may not collide.
-    fn cant_collide(&self, other: &Particle) -> bool {
-        let sa = (self.a - other.a).signum();
-        let sv = (self.v - other.v).signum();
-        let sp = (self.p - other.p).signum();
-        // Check for each coordinate that particles are in different positions
-        // and cannot come closer together, hence cannot collide.
-        let cant_collide_x =
-            // The particles are not in the same position.
-            sp.x() != 0
-            // The relative velocity cannot change the signum of the relative position,
-            // so the relative position cannot become zero.
-            && ((sa.x() == 0 && sv.x() == 0) || sv.x() == sp.x())
-            // The relative acceleration cannot change the signum of the relative velocity.
-            && (sa.x() == 0 || sa.x() == sv.x());
-        let cant_collide_y = sp.y() != 0
-            && ((sa.y() == 0 && sv.y() == 0) || sv.y() == sp.y())
-            && (sa.y() == 0 || sa.y() == sv.y());
-        let cant_collide_z = sp.z() != 0
-            && ((sa.z() == 0 && sv.z() == 0) || sv.z() == sp.z())
-            && (sa.z() == 0 || sa.z() == sv.z());
-        cant_collide_x || cant_collide_y || cant_collide_z
+    /// Returns the position after `t` ticks.
+    ///
+    /// In each tick the velocity increases by the acceleration first,
+    /// and then the position increases by the velocity.
+    fn position_at(&self, t: i64) -> Point3d {
+        self.p + t * self.v + (t * (t + 1) / 2) * self.a
+    }
+    /// Returns the first tick at which the particles are in the same position.
+    ///
+    /// The difference of the positions after `t` ticks is
+    /// `da t (t + 1) / 2 + dv t + dp` in each coordinate,
+    /// so the ticks are the common integer roots of `da t^2 + (2 dv + da) t + 2 dp`.
+    fn collision_time(&self, other: &Particle) -> Option<i64> {
+        let da = coords(self.a - other.a);
+        let dv = coords(self.v - other.v);
+        let dp = coords(self.p - other.p);
+        let roots = (0..3)
+            .map(|k| quadratic_roots(da[k], 2 * dv[k] + da[k], 2 * dp[k]))
+            .find(|roots| *roots != Roots::All);
+        match roots {
+            // The particles have the same trajectory.
+            None => Some(0),
+            Some(Roots::All) => unreachable!(),
+            Some(Roots::Some(ts)) => ts
+                .into_iter()
+                .find(|&t| self.position_at(t) == other.position_at(t)),
+        }
+    }
+    /// Returns the coefficients of twice the Manhattan distance from the origin,
+    /// as a polynomial in the ticks which is valid after a large enough number of them.
+    ///
+    /// In the long run the sign of each coordinate is the sign of its first
+    /// nonzero coefficient among acceleration, velocity and position.
+    fn long_run_distance(&self) -> (i64, i64, i64) {
+        let a = coords(self.a);
+        let v = coords(self.v);
+        let p = coords(self.p.to_vec());
+        let mut result = (0, 0, 0);
+        for k in 0..3 {
+            let coefficients = (a[k], 2 * v[k] + a[k], 2 * p[k]);
+            let sign = [coefficients.0, coefficients.1, coefficients.2]
+                .into_iter()
+                .map(i64::signum)
+                .find(|&s| s != 0)
+                .unwrap_or(0);
+            result.0 += sign * coefficients.0;
+            result.1 += sign * coefficients.1;
+            result.2 += sign * coefficients.2;
+        }
+        result
     }
 }
 impl fmt::Display for Particle {
@@ -115,8 +194,60 @@ fn input(i: &str) -> IResult<&str, Vec<Particle>> {
     many1(particle)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// Returns the particle which stays closest to the origin in the long run.
+///
+/// Compares the coefficients of the long run distances lexicographically,
+/// which is exact for large times.  Returns `None` if this doesn't single out one particle.
+fn closest_in_long_run(particles: &[Particle]) -> Option<usize> {
+    let mut best: Option<&Particle> = None;
+    let mut unique = false;
+    for particle in particles {
+        let ordering = best.map(|b| particle.long_run_distance().cmp(&b.long_run_distance()));
+        match ordering {
+            None | Some(Ordering::Less) => {
+                best = Some(particle);
+                unique = true;
+            }
+            Some(Ordering::Equal) => unique = false,
+            Some(Ordering::Greater) => (),
+        }
+    }
+    best.filter(|_| unique).map(|b| b.id)
+}
+
+/// Returns the particles which are never destroyed in collisions.
+///
+/// Processes the first collision of each pair of particles in order of time,
+/// where collisions only count if both particles still exist at that time.
+fn survivors(particles: &[Particle]) -> Vec<Particle> {
+    let mut events = Vec::new();
+    for i in 0..particles.len() {
+        for j in (i + 1)..particles.len() {
+            if let Some(t) = particles[i].collision_time(&particles[j]) {
+                events.push((t, i, j));
+            }
+        }
+    }
+    events.sort();
+
+    let mut destroyed_at = vec![None; particles.len()];
+    for (t, i, j) in events {
+        let exists = |k: usize| !matches!(destroyed_at[k], Some(td) if td != t);
+        if exists(i) && exists(j) {
+            destroyed_at[i] = Some(t);
+            destroyed_at[j] = Some(t);
+        }
+    }
+    particles
+        .iter()
+        .zip(destroyed_at)
+        .filter(|(_, d)| d.is_none())
+        .map(|(p, _)| p.clone())
+        .collect()
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -131,46 +262,121 @@ fn main() {
         particle.id = id;
     }
 
-    // The particle with the smallest acceleration stays closest to the origin.
-    // We use the square of the Euclidean norm for comparison.
-    let result_a = input
-        .iter()
-        .enumerate()
-        .min_by_key(|(_id, particle)| particle.a.norm_l2_squared())
-        .unwrap()
-        .0;
-
-    let mut particles = input;
-    loop {
-        let mut some_can_collide = false;
-        'outer: for i in 0..(particles.len() - 1) {
-            for j in (i + 1)..particles.len() {
-                let pi = &particles[i];
-                let pj = &particles[j];
-                if !pi.cant_collide(pj) {
-                    some_can_collide = true;
-                    break 'outer;
-                }
-            }
-        }
-        if !some_can_collide {
-            break;
-        }
+    let result_a = closest_in_long_run(&input)
+        .ok_or_else(|| util::runtime_error!("no unique closest particle"))?;
+
+    let result_b = survivors(&input).len();
+
+    println!("a: {}", result_a);
+    println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
 
-        let mut positions = HashMap::new();
-        for particle in &particles {
-            let entry = positions.entry(particle.p).or_insert_with(Vec::new);
-            entry.push(particle);
+    use lowdim::p3d;
+    use lowdim::v3d;
+
+    use crate::closest_in_long_run;
+    use crate::input;
+    use crate::quadratic_roots;
+    use crate::survivors;
+    use crate::Particle;
+    use crate::Roots;
+
+    fn particles(data: &str) -> Vec<Particle> {
+        let (_, mut particles) = input(data).unwrap();
+        for (id, particle) in particles.iter_mut().enumerate() {
+            particle.id = id;
         }
+        particles
+    }
 
-        particles = particles
-            .iter()
-            .filter(|particle| positions[&particle.p].len() == 1)
-            .map(Particle::step)
-            .collect::<Vec<_>>();
+    #[test]
+    fn test_quadratic_roots() {
+        assert_eq!(quadratic_roots(0, 0, 0), Roots::All);
+        assert_eq!(quadratic_roots(0, 0, 1), Roots::Some(vec![]));
+        assert_eq!(quadratic_roots(0, 2, -6), Roots::Some(vec![3]));
+        assert_eq!(quadratic_roots(0, 2, 6), Roots::Some(vec![]));
+        assert_eq!(quadratic_roots(1, -5, 6), Roots::Some(vec![2, 3]));
+        assert_eq!(quadratic_roots(1, 1, -6), Roots::Some(vec![2]));
+        assert_eq!(quadratic_roots(2, -5, 3), Roots::Some(vec![1]));
+        assert_eq!(quadratic_roots(1, -4, 4), Roots::Some(vec![2]));
+        assert_eq!(quadratic_roots(1, 0, 1), Roots::Some(vec![]));
     }
-    let result_b = particles.len();
 
-    println!("a: {}", result_a);
-    println!("b: {}", result_b);
+    #[test]
+    fn test_closest_in_long_run() {
+        let ps = particles("p=<3,0,0>, v=<2,0,0>, a=<-1,0,0>\np=<4,0,0>, v=<0,0,0>, a=<-2,0,0>\n");
+        assert_eq!(closest_in_long_run(&ps), Some(0));
+        // The same acceleration, but moving away from the origin.
+        let ps = particles("p=<0,0,0>, v=<-1,0,0>, a=<1,0,0>\np=<0,0,0>, v=<1,0,0>, a=<-1,0,0>\n");
+        assert_eq!(closest_in_long_run(&ps), None);
+        let ps = particles("p=<5,0,0>, v=<-1,0,0>, a=<1,0,0>\np=<0,0,0>, v=<0,1,0>, a=<0,-1,0>\n");
+        assert_eq!(closest_in_long_run(&ps), Some(1));
+    }
+
+    #[test]
+    fn test_survivors() {
+        let ps = particles(
+            "\
+p=<-6,0,0>, v=<3,0,0>, a=<0,0,0>
+p=<-4,0,0>, v=<2,0,0>, a=<0,0,0>
+p=<-2,0,0>, v=<1,0,0>, a=<0,0,0>
+p=<3,0,0>, v=<-1,0,0>, a=<0,0,0>
+",
+        );
+        let ids = survivors(&ps).iter().map(|p| p.id).collect::<Vec<_>>();
+        assert_eq!(ids, vec![3]);
+    }
+
+    #[test]
+    fn test_survivors_by_simulation() {
+        let mut rng = StdRng::seed_from_u64(2017);
+        for _ in 0..50 {
+            let mut ps = (0..30)
+                .map(|id| Particle {
+                    id,
+                    p: p3d(
+                        rng.gen_range(-5..=5),
+                        rng.gen_range(-5..=5),
+                        rng.gen_range(-1..=1),
+                    ),
+                    v: v3d(
+                        rng.gen_range(-2..=2),
+                        rng.gen_range(-2..=2),
+                        rng.gen_range(-1..=1),
+                    ),
+                    a: v3d(
+                        rng.gen_range(-1..=1),
+                        rng.gen_range(-1..=1),
+                        rng.gen_range(-1..=1),
+                    ),
+                })
+                .collect::<Vec<_>>();
+            let expected = survivors(&ps).iter().map(|p| p.id).collect::<Vec<_>>();
+
+            // All collisions happen within a few ticks for these small values.
+            for _ in 0..100 {
+                let mut counts = HashMap::new();
+                for p in &ps {
+                    *counts.entry(p.p).or_insert(0) += 1;
+                }
+                ps.retain(|p| counts[&p.p] == 1);
+                for p in &mut ps {
+                    p.v = p.v + p.a;
+                    p.p = p.p + p.v;
+                }
+            }
+            let ids = ps.iter().map(|p| p.id).collect::<Vec<_>>();
+            assert_eq!(ids, expected);
+        }
+    }
 }
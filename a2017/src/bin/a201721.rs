@@ -1,6 +1,6 @@
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
-use std::io::Read;
 
 use nom::branch::alt;
 use nom::bytes::complete::tag;
@@ -12,6 +12,8 @@ use nom::IResult;
 
 use lowdim::p2d;
 use lowdim::Array2d;
+use lowdim::BBox2d;
+use lowdim::Point2d;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Pixel {
@@ -36,6 +38,74 @@ impl fmt::Display for Pixel {
 struct Pattern {
     array: Array2d<i64, Pixel>,
 }
+impl Pattern {
+    fn with<F>(size: i64, f: F) -> Pattern
+    where
+        F: Fn(Point2d) -> Pixel,
+    {
+        let bbox = BBox2d::from_corners(p2d(0, 0), p2d(size - 1, size - 1));
+        Pattern {
+            array: Array2d::with(bbox, f),
+        }
+    }
+    fn size(&self) -> i64 {
+        self.array.bbox().x_range().count() as i64
+    }
+    /// Returns a string identifying the pattern, as in the rules.
+    fn key(&self) -> String {
+        self.to_string()
+    }
+    fn count_on(&self) -> usize {
+        self.array
+            .bbox()
+            .iter()
+            .filter(|&p| self.array[p] == Pixel::On)
+            .count()
+    }
+    /// Returns the pattern rotated by a quarter turn.
+    fn rotate(&self) -> Pattern {
+        let size = self.size();
+        Pattern::with(size, |p| self.array[p2d(p.y(), size - 1 - p.x())])
+    }
+    /// Returns the pattern flipped along the vertical axis.
+    fn flip(&self) -> Pattern {
+        let size = self.size();
+        Pattern::with(size, |p| self.array[p2d(size - 1 - p.x(), p.y())])
+    }
+    /// Returns the 8 rotations and reflections of the pattern.
+    fn symmetries(&self) -> Vec<Pattern> {
+        let mut result = Vec::new();
+        let mut pattern = self.clone();
+        for _ in 0..4 {
+            result.push(pattern.flip());
+            pattern = pattern.rotate();
+            result.push(pattern.clone());
+        }
+        result
+    }
+    /// Splits the pattern into blocks of the given size, row by row.
+    fn split(&self, block_size: i64) -> Vec<Pattern> {
+        let blocks = self.size() / block_size;
+        let mut result = Vec::new();
+        for by in 0..blocks {
+            for bx in 0..blocks {
+                let offset = p2d(bx * block_size, by * block_size).to_vec();
+                result.push(Pattern::with(block_size, |p| self.array[p + offset]));
+            }
+        }
+        result
+    }
+    /// Joins square blocks given row by row into one pattern.
+    fn join(blocks: &[Pattern]) -> Pattern {
+        let blocks_per_row = (blocks.len() as f64).sqrt().round() as i64;
+        let block_size = blocks[0].size();
+        Pattern::with(blocks_per_row * block_size, |p| {
+            let block =
+                &blocks[(p.y() / block_size * blocks_per_row + p.x() / block_size) as usize];
+            block.array[p2d(p.x() % block_size, p.y() % block_size)]
+        })
+    }
+}
 impl fmt::Display for Pattern {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let bounds = self.array.bbox();
@@ -93,22 +163,184 @@ fn input(i: &str) -> IResult<&str, Vec<Rule>> {
     many1(rule)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// The start pattern of the art program.
+const START: &str = ".#./..#/###";
+
+/// The number of iterations after which a 3x3 block turns into 3x3 blocks again.
+///
+/// It grows to 4x4, 6x6 and 9x9, which splits into 3x3 blocks
+/// that are enhanced independently of each other from then on.
+const BLOCK_PERIOD: usize = 3;
+
+/// The enhancement rules, applying to all rotations and reflections of their patterns.
+#[derive(Clone, Debug)]
+struct Enhancer {
+    rules: HashMap<String, Pattern>,
+}
+impl Enhancer {
+    fn new(rules: &[Rule]) -> Enhancer {
+        let mut map = HashMap::new();
+        for rule in rules {
+            for left in rule.left.symmetries() {
+                map.insert(left.key(), rule.right.clone());
+            }
+        }
+        Enhancer { rules: map }
+    }
+
+    fn enhance(&self, pattern: &Pattern) -> util::Result<Pattern> {
+        let block_size = if pattern.size() % 2 == 0 { 2 } else { 3 };
+        let blocks = pattern
+            .split(block_size)
+            .iter()
+            .map(|block| {
+                self.rules
+                    .get(&block.key())
+                    .cloned()
+                    .ok_or_else(|| util::runtime_error!("no rule for {}", block))
+            })
+            .collect::<util::Result<Vec<_>>>()?;
+        Ok(Pattern::join(&blocks))
+    }
+
+    fn enhance_n(&self, pattern: &Pattern, iterations: usize) -> util::Result<Pattern> {
+        let mut pattern = pattern.clone();
+        for _ in 0..iterations {
+            pattern = self.enhance(&pattern)?;
+        }
+        Ok(pattern)
+    }
+
+    /// Returns the number of pixels which are on after the iterations.
+    ///
+    /// Starting from a 3x3 pattern, only the multiplicities of the distinct
+    /// 3x3 blocks are tracked, so the grid is never materialized as a whole.
+    fn count_on(&self, start: &Pattern, iterations: usize) -> util::Result<usize> {
+        if start.size() != 3 {
+            return Ok(self.enhance_n(start, iterations)?.count_on());
+        }
+
+        let mut blocks = HashMap::new();
+        blocks.insert(start.key(), (start.clone(), 1));
+        let mut memo: HashMap<String, Vec<Pattern>> = HashMap::new();
+        let mut remaining = iterations;
+        while remaining >= BLOCK_PERIOD {
+            let mut new_blocks = HashMap::new();
+            for (key, (block, count)) in blocks {
+                if !memo.contains_key(&key) {
+                    let next = self.enhance_n(&block, BLOCK_PERIOD)?.split(3);
+                    memo.insert(key.clone(), next);
+                }
+                for next in &memo[&key] {
+                    let entry = new_blocks
+                        .entry(next.key())
+                        .or_insert_with(|| (next.clone(), 0));
+                    entry.1 += count;
+                }
+            }
+            blocks = new_blocks;
+            remaining -= BLOCK_PERIOD;
+        }
+
+        let mut result = 0;
+        for (block, count) in blocks.values() {
+            result += count * self.enhance_n(block, remaining)?.count_on();
+        }
+        Ok(result)
+    }
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
     // println!("{:?}", result);
 
-    let mut input = result.unwrap().1;
+    let input = result.unwrap().1;
     // for rule in &input {
     //     println!("{}", rule);
     // }
 
-    let result_a = 0;
+    let enhancer = Enhancer::new(&input);
+    let (_, start) = pattern(START).unwrap();
+
+    let result_a = enhancer.count_on(&start, 5)?;
 
-    let result_b = 0;
+    let result_b = enhancer.count_on(&start, 18)?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::Rng;
+    use rand::SeedableRng;
+
+    use crate::input;
+    use crate::pattern;
+    use crate::Enhancer;
+    use crate::Pattern;
+    use crate::Pixel;
+    use crate::Rule;
+    use crate::START;
+
+    #[test]
+    fn test_example() {
+        let (_, rules) =
+            input("../.# => ##./#../...\n.#./..#/### => #..#/..../..../#..#\n").unwrap();
+        let enhancer = Enhancer::new(&rules);
+        let (_, start) = pattern(START).unwrap();
+        assert_eq!(
+            enhancer.enhance_n(&start, 2).unwrap().key(),
+            "##.##./#..#../....../##.##./#..#../......"
+        );
+        assert_eq!(enhancer.count_on(&start, 2).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_symmetries() {
+        let (_, p) = pattern(".#./..#/###").unwrap();
+        let mut keys = p.symmetries().iter().map(|s| s.key()).collect::<Vec<_>>();
+        keys.sort();
+        keys.dedup();
+        assert_eq!(keys.len(), 8);
+        assert!(keys.contains(&"#../#.#/##.".to_string()));
+        assert!(keys.contains(&"###/..#/.#.".to_string()));
+    }
+
+    #[test]
+    fn test_blocks() {
+        // Random rules for all patterns.
+        let mut rng = StdRng::seed_from_u64(2017);
+        let mut rules = Vec::new();
+        for (size, patterns) in [(2, 1 << 4), (3, 1 << 9)] {
+            for bits in 0..patterns {
+                let pixel = |bits: u32, bit: i64| {
+                    if bits & (1 << bit) != 0 {
+                        Pixel::On
+                    } else {
+                        Pixel::Off
+                    }
+                };
+                let left = Pattern::with(size, |p| pixel(bits, p.y() * size + p.x()));
+                let right_bits = rng.gen::<u32>();
+                let right =
+                    Pattern::with(size + 1, |p| pixel(right_bits, p.y() * (size + 1) + p.x()));
+                rules.push(Rule { left, right });
+            }
+        }
+        let enhancer = Enhancer::new(&rules);
+        let (_, start) = pattern(START).unwrap();
+        for iterations in 0..=8 {
+            assert_eq!(
+                enhancer.count_on(&start, iterations).unwrap(),
+                enhancer.enhance_n(&start, iterations).unwrap().count_on()
+            );
+        }
+    }
 }
@@ -1,4 +1,7 @@
 use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::env;
 use std::fmt;
 use std::io;
 use std::str::FromStr;
@@ -10,28 +13,29 @@ use nom::character::complete::line_ending;
 use nom::character::complete::one_of;
 use nom::combinator::map_res;
 use nom::combinator::value;
+use nom::combinator::verify;
+use nom::multi::many1;
 use nom::multi::separated_list1;
 use nom::IResult;
 
+use util::parse::parse_input;
+
+/// A symbol on the tape, where the tape is initially all 0.
+type Symbol = u8;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Val {
-    Zero,
-    One,
+enum Move {
+    Left,
+    Right,
 }
-impl fmt::Display for Val {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+impl Move {
+    fn to_char(self) -> char {
         match self {
-            Val::Zero => write!(f, "0"),
-            Val::One => write!(f, "1"),
+            Move::Left => 'L',
+            Move::Right => 'R',
         }
     }
 }
-
-#[derive(Clone, Copy, Debug)]
-enum Move {
-    Left,
-    Right,
-}
 impl fmt::Display for Move {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         match self {
@@ -43,17 +47,10 @@ impl fmt::Display for Move {
 
 #[derive(Clone, Copy, Debug)]
 struct Instruction {
-    write: Val,
+    write: Symbol,
     move_: Move,
     next_state: char,
 }
-impl Instruction {
-    fn execute(&self, tape_state: &mut TapeState) {
-        tape_state.write(self.write);
-        tape_state.move_(self.move_);
-        tape_state.set_state(self.next_state);
-    }
-}
 impl fmt::Display for Instruction {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         writeln!(f, "    - Write the value {}.", self.write)?;
@@ -62,19 +59,20 @@ impl fmt::Display for Instruction {
     }
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Debug)]
 struct State {
     name: char,
-    instruction0: Instruction,
-    instruction1: Instruction,
+    /// The instructions indexed by the current value.
+    instructions: Vec<Instruction>,
 }
 impl fmt::Display for State {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         writeln!(f, "In state {}:", self.name)?;
-        writeln!(f, "  If the current value is 0:")?;
-        write!(f, "{}", self.instruction0)?;
-        writeln!(f, "  If the current value is 1:")?;
-        write!(f, "{}", self.instruction1)
+        for (value, instruction) in self.instructions.iter().enumerate() {
+            writeln!(f, "  If the current value is {}:", value)?;
+            write!(f, "{}", instruction)?;
+        }
+        Ok(())
     }
 }
 
@@ -84,6 +82,44 @@ struct Input {
     steps: i64,
     states: Vec<State>,
 }
+impl Input {
+    /// Returns the transitions of the states as a table.
+    ///
+    /// Each entry gives the symbol written, the move and the next state.
+    fn table(&self) -> String {
+        let symbols = self
+            .states
+            .iter()
+            .map(|s| s.instructions.len())
+            .max()
+            .unwrap_or(0);
+        let mut lines = Vec::new();
+        let mut line = "state".to_string();
+        for symbol in 0..symbols {
+            line.push_str(&format!(" | {:<5}", symbol));
+        }
+        lines.push(line);
+        for state in &self.states {
+            let mut line = format!("{:<5}", state.name);
+            for symbol in 0..symbols {
+                match state.instructions.get(symbol) {
+                    Some(i) => line.push_str(&format!(
+                        " | {} {} {}",
+                        i.write,
+                        i.move_.to_char(),
+                        i.next_state
+                    )),
+                    None => line.push_str(" | -    "),
+                }
+            }
+            lines.push(line);
+        }
+        lines
+            .iter()
+            .map(|line| format!("{}\n", line.trim_end()))
+            .collect()
+    }
+}
 impl fmt::Display for Input {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
         writeln!(f, "Begin in state {}.", self.initial_state)?;
@@ -100,49 +136,200 @@ impl fmt::Display for Input {
     }
 }
 
+/// The number of cells in a block of the tape.
+const BLOCK_SIZE: usize = 8;
+
+/// The cells of a block of the tape, one symbol per byte.
+type Block = u64;
+
+fn get_cell(block: Block, pos: usize) -> Symbol {
+    (block >> (8 * pos)) as Symbol
+}
+fn set_cell(block: Block, pos: usize, symbol: Symbol) -> Block {
+    block & !(0xff << (8 * pos)) | Block::from(symbol) << (8 * pos)
+}
+
+/// The result of running the machine within a block until the head leaves it.
+#[derive(Clone, Copy, Debug)]
+enum BlockTransition {
+    Exit {
+        block: Block,
+        state: usize,
+        move_: Move,
+        steps: u64,
+    },
+    /// The machine never leaves the block.
+    Loop,
+}
+
+/// A Turing machine with the states numbered, running on a tape of blocks.
+///
+/// The head jumps over whole blocks using memoized block transitions,
+/// and only steps single cells when the remaining steps end within a block.
 #[derive(Clone, Debug)]
-struct TapeState {
-    head_pos: i64,
-    state: char,
-    tape: HashMap<i64, Val>,
-}
-impl TapeState {
-    fn init(initial_state: char) -> TapeState {
-        TapeState {
-            head_pos: 0,
-            state: initial_state,
-            tape: HashMap::new(),
-        }
-    }
-    fn step(&mut self, states: &HashMap<char, State>) {
-        let state = states[&self.state];
-        match self.read() {
-            Val::Zero => state.instruction0.execute(self),
-            Val::One => state.instruction1.execute(self),
+struct Machine {
+    /// The transitions by state and symbol: symbol written, move and next state.
+    transitions: Vec<Vec<(Symbol, Move, usize)>>,
+    state: usize,
+    blocks: VecDeque<Block>,
+    /// The index of the block with the head.
+    block_index: usize,
+    /// The position of the head in its block.
+    pos: usize,
+    memo: HashMap<(Block, usize, usize), BlockTransition>,
+}
+impl Machine {
+    fn new(input: &Input) -> util::Result<Machine> {
+        let indices = input
+            .states
+            .iter()
+            .enumerate()
+            .map(|(i, s)| (s.name, i))
+            .collect::<HashMap<_, _>>();
+        let index = |name| {
+            indices
+                .get(&name)
+                .copied()
+                .ok_or_else(|| util::runtime_error!("unknown state {}", name))
+        };
+        let symbols = input.states.iter().map(|s| s.instructions.len()).min();
+        let mut transitions = Vec::new();
+        for state in &input.states {
+            let mut ts = Vec::new();
+            for i in &state.instructions {
+                if Some(usize::from(i.write)) >= symbols {
+                    return Err(util::runtime_error!(
+                        "state {} writes {}, which not all states can read",
+                        state.name,
+                        i.write
+                    ));
+                }
+                ts.push((i.write, i.move_, index(i.next_state)?));
+            }
+            transitions.push(ts);
         }
+        Ok(Machine {
+            transitions,
+            state: index(input.initial_state)?,
+            blocks: VecDeque::from([0]),
+            block_index: 0,
+            pos: 0,
+            memo: HashMap::new(),
+        })
     }
-    fn diagnostic_checksum(&self) -> usize {
-        self.tape.values().filter(|&&val| val == Val::One).count()
+
+    /// Does one step within a block.
+    ///
+    /// Returns the new block, state and position, which may be outside the block.
+    fn step_in_block(&self, block: Block, state: usize, pos: usize) -> (Block, usize, isize) {
+        let (write, move_, next_state) = self.transitions[state][usize::from(get_cell(block, pos))];
+        let new_pos = match move_ {
+            Move::Left => pos as isize - 1,
+            Move::Right => pos as isize + 1,
+        };
+        (set_cell(block, pos, write), next_state, new_pos)
     }
 
-    fn read(&mut self) -> Val {
-        let entry = self.tape.entry(self.head_pos).or_insert(Val::Zero);
-        *entry
+    fn block_transition(&mut self, block: Block, state: usize, pos: usize) -> BlockTransition {
+        if let Some(&transition) = self.memo.get(&(block, state, pos)) {
+            return transition;
+        }
+        let start = (block, state, pos);
+        let mut seen = HashSet::new();
+        let (mut block, mut state, mut pos) = start;
+        let mut steps = 0;
+        let transition = loop {
+            if !seen.insert((block, state, pos)) {
+                break BlockTransition::Loop;
+            }
+            let (new_block, new_state, new_pos) = self.step_in_block(block, state, pos);
+            steps += 1;
+            block = new_block;
+            state = new_state;
+            if new_pos < 0 || new_pos >= BLOCK_SIZE as isize {
+                let move_ = if new_pos < 0 { Move::Left } else { Move::Right };
+                break BlockTransition::Exit {
+                    block,
+                    state,
+                    move_,
+                    steps,
+                };
+            }
+            pos = new_pos as usize;
+        };
+        self.memo.insert(start, transition);
+        transition
     }
-    fn write(&mut self, val: Val) {
-        let entry = self.tape.entry(self.head_pos).or_insert(Val::Zero);
-        *entry = val;
+
+    /// Moves the head to the neighboring block, extending the tape if needed.
+    fn move_block(&mut self, move_: Move) {
+        match move_ {
+            Move::Left => {
+                if self.block_index == 0 {
+                    self.blocks.push_front(0);
+                } else {
+                    self.block_index -= 1;
+                }
+                self.pos = BLOCK_SIZE - 1;
+            }
+            Move::Right => {
+                self.block_index += 1;
+                if self.block_index == self.blocks.len() {
+                    self.blocks.push_back(0);
+                }
+                self.pos = 0;
+            }
+        }
     }
-    fn move_(&mut self, move_: Move) {
-        self.head_pos += {
-            match move_ {
-                Move::Left => -1,
-                Move::Right => 1,
+
+    fn run(&mut self, steps: u64) {
+        let mut remaining = steps;
+        while remaining > 0 {
+            let block = self.blocks[self.block_index];
+            match self.block_transition(block, self.state, self.pos) {
+                BlockTransition::Exit {
+                    block,
+                    state,
+                    move_,
+                    steps,
+                } if steps <= remaining => {
+                    self.blocks[self.block_index] = block;
+                    self.state = state;
+                    self.move_block(move_);
+                    remaining -= steps;
+                }
+                _ => {
+                    // The remaining steps end within the block.
+                    let (mut block, mut state, mut pos) = (block, self.state, self.pos);
+                    for _ in 0..remaining {
+                        let (new_block, new_state, new_pos) = self.step_in_block(block, state, pos);
+                        block = new_block;
+                        state = new_state;
+                        pos = new_pos as usize;
+                    }
+                    self.blocks[self.block_index] = block;
+                    self.state = state;
+                    self.pos = pos;
+                    remaining = 0;
+                }
             }
-        };
+        }
     }
-    fn set_state(&mut self, state: char) {
-        self.state = state;
+
+    /// Returns the number of cells with the symbol.
+    fn count(&self, symbol: Symbol) -> usize {
+        self.blocks
+            .iter()
+            .map(|&block| {
+                (0..BLOCK_SIZE)
+                    .filter(|&pos| get_cell(block, pos) == symbol)
+                    .count()
+            })
+            .sum()
+    }
+
+    fn diagnostic_checksum(&self) -> usize {
+        self.count(1)
     }
 }
 
@@ -150,8 +337,8 @@ fn uint(i: &str) -> IResult<&str, i64> {
     map_res(digit1, FromStr::from_str)(i)
 }
 
-fn val(i: &str) -> IResult<&str, Val> {
-    alt((value(Val::Zero, tag("0")), value(Val::One, tag("1"))))(i)
+fn symbol(i: &str) -> IResult<&str, Symbol> {
+    map_res(digit1, FromStr::from_str)(i)
 }
 
 fn move_(i: &str) -> IResult<&str, Move> {
@@ -167,7 +354,7 @@ fn name(i: &str) -> IResult<&str, char> {
 
 fn instruction(i: &str) -> IResult<&str, Instruction> {
     let (i, _) = tag("    - Write the value ")(i)?;
-    let (i, v) = val(i)?;
+    let (i, v) = symbol(i)?;
     let (i, _) = tag(".")(i)?;
     let (i, _) = line_ending(i)?;
     let (i, _) = tag("    - Move one slot to the ")(i)?;
@@ -188,23 +375,35 @@ fn instruction(i: &str) -> IResult<&str, Instruction> {
     ))
 }
 
+fn case(i: &str) -> IResult<&str, (Symbol, Instruction)> {
+    let (i, _) = tag("  If the current value is ")(i)?;
+    let (i, v) = symbol(i)?;
+    let (i, _) = tag(":")(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, instruction) = instruction(i)?;
+    Ok((i, (v, instruction)))
+}
+
 fn state(i: &str) -> IResult<&str, State> {
     let (i, _) = tag("In state ")(i)?;
     let (i, n) = name(i)?;
     let (i, _) = tag(":")(i)?;
     let (i, _) = line_ending(i)?;
-    let (i, _) = tag("  If the current value is 0:")(i)?;
-    let (i, _) = line_ending(i)?;
-    let (i, i0) = instruction(i)?;
-    let (i, _) = tag("  If the current value is 1:")(i)?;
-    let (i, _) = line_ending(i)?;
-    let (i, i1) = instruction(i)?;
+    // The cases must be for the values 0, 1, ... in order.
+    let (i, cases) = verify(many1(case), |cases: &[(Symbol, Instruction)]| {
+        cases
+            .iter()
+            .enumerate()
+            .all(|(k, &(v, _))| usize::from(v) == k)
+    })(i)?;
     Ok((
         i,
         State {
             name: n,
-            instruction0: i0,
-            instruction1: i1,
+            instructions: cases
+                .into_iter()
+                .map(|(_, instruction)| instruction)
+                .collect(),
         },
     ))
 }
@@ -230,26 +429,167 @@ fn input(i: &str) -> IResult<&str, Input> {
     ))
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
-    let result = input(&input_data);
-    // println!("{:?}", result);
-
-    let input = result.unwrap().1;
-    // print!("{}", input);
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
 
-    let states = input
-        .states
-        .iter()
-        .map(|&s| (s.name, s))
-        .collect::<HashMap<_, _>>();
-    let mut tape_state = TapeState::init(input.initial_state);
-    for _ in 0..input.steps {
-        tape_state.step(&states);
+    // The option `--table` prints the transitions as a table.
+    if env::args().nth(1).is_some_and(|arg| arg == "--table") {
+        print!("{}", input.table());
     }
-    let result_a = tape_state.diagnostic_checksum();
+
+    let mut machine = Machine::new(&input)?;
+    machine.run(u64::try_from(input.steps)?);
+    let result_a = machine.diagnostic_checksum();
 
     println!("a: {}", result_a);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::input;
+    use crate::Input;
+    use crate::Machine;
+    use crate::Move;
+    use crate::Symbol;
+
+    const EXAMPLE: &str = "\
+Begin in state A.
+Perform a diagnostic checksum after 6 steps.
+
+In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 0.
+    - Move one slot to the left.
+    - Continue with state B.
+
+In state B:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+";
+
+    /// A busy beaver like machine with three symbols.
+    const THREE_SYMBOLS: &str = "\
+Begin in state A.
+Perform a diagnostic checksum after 1000 steps.
+
+In state A:
+  If the current value is 0:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state B.
+  If the current value is 1:
+    - Write the value 2.
+    - Move one slot to the left.
+    - Continue with state B.
+  If the current value is 2:
+    - Write the value 1.
+    - Move one slot to the left.
+    - Continue with state A.
+
+In state B:
+  If the current value is 0:
+    - Write the value 2.
+    - Move one slot to the left.
+    - Continue with state A.
+  If the current value is 1:
+    - Write the value 2.
+    - Move one slot to the right.
+    - Continue with state B.
+  If the current value is 2:
+    - Write the value 1.
+    - Move one slot to the right.
+    - Continue with state A.
+";
+
+    /// Runs the machine one step at a time and counts the symbols.
+    fn run_simple(input: &Input, steps: usize) -> Vec<usize> {
+        let states = input
+            .states
+            .iter()
+            .map(|s| (s.name, s))
+            .collect::<HashMap<_, _>>();
+        let mut tape = HashMap::new();
+        let mut pos = 0;
+        let mut state = input.initial_state;
+        for _ in 0..steps {
+            let value = tape.entry(pos).or_insert(0);
+            let instruction = states[&state].instructions[usize::from(*value)];
+            *value = instruction.write;
+            pos += match instruction.move_ {
+                Move::Left => -1,
+                Move::Right => 1,
+            };
+            state = instruction.next_state;
+        }
+        (1..3)
+            .map(|s: Symbol| tape.values().filter(|&&v| v == s).count())
+            .collect()
+    }
+
+    #[test]
+    fn test_example() {
+        let (_, input) = input(EXAMPLE).unwrap();
+        let mut machine = Machine::new(&input).unwrap();
+        machine.run(6);
+        assert_eq!(machine.diagnostic_checksum(), 3);
+    }
+
+    #[test]
+    fn test_macro_steps() {
+        for data in [EXAMPLE, THREE_SYMBOLS] {
+            let (_, input) = input(data).unwrap();
+            for steps in [0, 1, 7, 8, 9, 100, 1000, 12345] {
+                let mut machine = Machine::new(&input).unwrap();
+                machine.run(steps as u64);
+                let counts = (1..3).map(|s| machine.count(s)).collect::<Vec<_>>();
+                assert_eq!(counts, run_simple(&input, steps));
+            }
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for data in [EXAMPLE, THREE_SYMBOLS] {
+            let (_, input) = input(data).unwrap();
+            assert_eq!(input.to_string(), data);
+        }
+    }
+
+    #[test]
+    fn test_table() {
+        let (_, input) = input(THREE_SYMBOLS).unwrap();
+        assert_eq!(
+            input.table(),
+            "\
+state | 0     | 1     | 2
+A     | 1 R B | 2 L B | 1 L A
+B     | 2 L A | 2 R B | 1 R A
+"
+        );
+    }
+
+    #[test]
+    fn test_unknown_symbol() {
+        let data = EXAMPLE.replace("Write the value 0", "Write the value 2");
+        let (_, input) = input(&data).unwrap();
+        assert!(Machine::new(&input).is_err());
+    }
 }
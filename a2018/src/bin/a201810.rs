@@ -1,4 +1,3 @@
-use std::collections::HashSet;
 use std::i64;
 use std::io;
 use std::str::FromStr;
@@ -18,6 +17,8 @@ use lowdim::BBox;
 use lowdim::Point2d;
 use lowdim::Vec2d;
 
+use util::ocr::recognize_points;
+
 #[derive(Clone, Debug)]
 struct Record {
     pos: Point2d,
@@ -68,13 +69,13 @@ fn pos_at_t(r: &Record, t: i64) -> Point2d {
     r.pos + t * r.v
 }
 
-fn main() {
+fn main() -> util::Result<()> {
     let mut records = Vec::new();
 
     let mut line = String::new();
     loop {
         line.clear();
-        io::stdin().read_line(&mut line).expect("I/O error");
+        io::stdin().read_line(&mut line)?;
 
         if line.trim().is_empty() {
             break;
@@ -98,29 +99,20 @@ fn main() {
         }
         let area = bbox.area();
         if area > last_area {
-            let ps = records
-                .iter()
-                .map(|r| pos_at_t(r, t - 1))
-                .collect::<HashSet<_>>();
-            for y in bbox.y_range() {
-                for x in bbox.x_range() {
-                    print!(
-                        "{}",
-                        if ps.contains(&Point2d::new(x, y)) {
-                            "#"
-                        } else {
-                            "."
-                        }
-                    );
-                }
-                println!();
-            }
             break;
         }
         last_area = bbox.area();
 
         t += 1;
     }
+    // The message appears when the points are closest together.
+    let t = t - 1;
+    let ps = records.iter().map(|r| pos_at_t(r, t));
+    let result_a = recognize_points(ps.map(|p| (p.x(), p.y())))?;
+    let result_b = t;
+
+    println!("a: {}", result_a);
+    println!("b: {}", result_b);
 
-    println!("{}", t - 1);
+    Ok(())
 }
@@ -1,14 +1,23 @@
-const SERIAL: i64 = 2568;
+use std::io;
 
-fn power_level(x: i64, y: i64) -> i64 {
+use util::parse::parse_input;
+use util::parse::uint;
+
+fn power_level(serial: i64, x: i64, y: i64) -> i64 {
     let rack_id = x + 10;
-    let pl1 = rack_id * y + SERIAL;
+    let pl1 = rack_id * y + serial;
     let pl2 = pl1 * rack_id;
     let digit = (pl2 / 100) % 10;
     digit - 5
 }
 
-fn main() {
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let serial = parse_input(&input_data, uint::<i64>)?;
+    util::validate(&input_data, "", &serial);
+
     let size = 300;
     let mut max_sum = std::i64::MIN;
     let mut max_xy = None;
@@ -17,7 +26,7 @@ fn main() {
             let mut sum = 0;
             for dx in 0..3 {
                 for dy in 0..3 {
-                    sum += power_level(x + dx, y + dy);
+                    sum += power_level(serial, x + dx, y + dy);
                 }
             }
             if sum > max_sum {
@@ -28,4 +37,6 @@ fn main() {
     }
     let (x, y) = max_xy.unwrap();
     println!("{},{}", x, y);
+
+    Ok(())
 }
@@ -1,6 +1,8 @@
+use std::io;
 use std::iter::repeat;
 
-const SERIAL: i64 = 2568;
+use util::parse::parse_input;
+use util::parse::uint;
 
 fn power_level(serial: i64, x: i64, y: i64) -> i64 {
     let rack_id = x + 10;
@@ -56,11 +58,19 @@ fn max_square(serial: i64) -> Data {
     max_data.unwrap()
 }
 
-fn main() {
-    let result = max_square(SERIAL);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let serial = parse_input(&input_data, uint::<i64>)?;
+    util::validate(&input_data, "", &serial);
+
+    let result = max_square(serial);
     println!("{}", result.sum);
 
     println!("{},{},{}", result.x, result.y, result.size);
+
+    Ok(())
 }
 
 #[cfg(test)]
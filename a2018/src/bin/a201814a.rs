@@ -1,4 +1,9 @@
 use std::fmt;
+use std::io;
+
+use nom::character::complete::digit1;
+
+use util::parse::parse_input;
 
 struct State {
     data: Vec<usize>,
@@ -53,14 +58,19 @@ impl fmt::Display for State {
     }
 }
 
-fn main() {
-    let n = 260321;
-    let input = vec![3, 7];
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, digit1)?;
+    util::validate(&input_data, "", &input);
+
+    let n = input.parse::<usize>()?;
     let output_len = 10;
 
     let skip = n;
 
-    let data = input;
+    let data = vec![3, 7];
     let i1 = 0;
     let i2 = 1;
 
@@ -75,4 +85,6 @@ fn main() {
         print!("{}", d);
     }
     println!();
+
+    Ok(())
 }
@@ -1,4 +1,9 @@
 use std::fmt;
+use std::io;
+
+use nom::character::complete::digit1;
+
+use util::parse::parse_input;
 
 struct State {
     data: Vec<usize>,
@@ -61,11 +66,20 @@ impl fmt::Display for State {
     }
 }
 
-fn main() {
-    let needle = vec![2, 6, 0, 3, 2, 1];
-    let input = vec![3, 7];
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, digit1)?;
+    util::validate(&input_data, "", &input);
 
-    let data = input;
+    let needle = input
+        .chars()
+        .map(|c| c.to_digit(10).map(|d| d as usize))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("invalid digit")?;
+
+    let data = vec![3, 7];
     let i1 = 0;
     let i2 = 1;
 
@@ -83,4 +97,6 @@ fn main() {
     }
 
     println!("{}", state.found_pos.unwrap());
+
+    Ok(())
 }
@@ -1,10 +1,9 @@
-use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::collections::HashSet;
+use std::env;
 use std::fmt;
 use std::io;
-use std::rc::Rc;
 
 use nom::branch::alt;
 use nom::character::complete::char;
@@ -23,7 +22,7 @@ enum UnitType {
     Elf,
 }
 impl UnitType {
-    fn to_char(&self) -> char {
+    fn to_char(self) -> char {
         match self {
             UnitType::Goblin => 'G',
             UnitType::Elf => 'E',
@@ -38,7 +37,7 @@ enum Square {
     Unit(UnitType),
 }
 impl Square {
-    fn to_char(&self) -> char {
+    fn to_char(self) -> char {
         match self {
             Square::Open => '.',
             Square::Wall => '#',
@@ -56,13 +55,11 @@ fn reading_order_cmp(p0: &Point2d, p1: &Point2d) -> Ordering {
     p0.y().cmp(&p1.y()).then(p0.x().cmp(&p1.x()))
 }
 
-fn attack_order_cmp(u0: &Rc<RefCell<Unit>>, u1: &Rc<RefCell<Unit>>) -> Ordering {
-    let u0 = u0.borrow();
-    let u1 = u1.borrow();
-    u0.hit_points
-        .cmp(&u1.hit_points)
-        .then(reading_order_cmp(&u0.position, &u1.position))
-}
+/// The hit points of a unit at the start of the combat.
+const HIT_POINTS: i64 = 200;
+
+/// The attack power of goblins, and of elves by default.
+const ATTACK_POWER: i64 = 3;
 
 #[derive(Clone, Copy, Debug)]
 struct Unit {
@@ -70,6 +67,11 @@ struct Unit {
     position: Point2d,
     hit_points: i64,
 }
+impl Unit {
+    fn is_alive(&self) -> bool {
+        self.hit_points > 0
+    }
+}
 
 #[derive(Clone, Copy, Debug)]
 struct Config {
@@ -79,7 +81,7 @@ struct Config {
 impl Default for Config {
     fn default() -> Self {
         Config {
-            elf_attack_power: 3,
+            elf_attack_power: ATTACK_POWER,
             elves_may_die: true,
         }
     }
@@ -90,83 +92,115 @@ enum Err {
     ElfDied,
 }
 
+/// The result of a combat.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Outcome {
+    full_rounds: i64,
+    hit_points: i64,
+    winner: UnitType,
+}
+impl Outcome {
+    fn score(&self) -> i64 {
+        self.full_rounds * self.hit_points
+    }
+}
+impl fmt::Display for Outcome {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let winners = match self.winner {
+            UnitType::Goblin => "Goblins",
+            UnitType::Elf => "Elves",
+        };
+        writeln!(f, "Combat ends after {} full rounds", self.full_rounds)?;
+        writeln!(
+            f,
+            "{} win with {} total hit points left",
+            winners, self.hit_points
+        )?;
+        writeln!(
+            f,
+            "Outcome: {} * {} = {}",
+            self.full_rounds,
+            self.hit_points,
+            self.score()
+        )
+    }
+}
+
 #[derive(Clone, Debug)]
 struct State {
     config: Config,
     map: Array2d<i64, Square>,
-    units: HashMap<Point2d, Rc<RefCell<Unit>>>,
+    /// All units including the dead ones, so indices stay valid.
+    units: Vec<Unit>,
+    /// The indices of the living units by position.
+    unit_indices: HashMap<Point2d, usize>,
 }
 impl State {
     fn from_map(map: Array2d<i64, Square>, config: Config) -> State {
-        let mut units = HashMap::new();
+        let mut units = Vec::new();
+        let mut unit_indices = HashMap::new();
         for position in map.bbox().iter() {
             let square = map[position];
             if let Square::Unit(unit_type) = square {
-                let unit = Unit {
+                unit_indices.insert(position, units.len());
+                units.push(Unit {
                     unit_type,
                     position,
-                    hit_points: 200,
-                };
-                units.insert(position, Rc::new(RefCell::new(unit)));
+                    hit_points: HIT_POINTS,
+                });
             }
         }
-        State { map, units, config }
-    }
-    pub fn get_unit(&self, p: Point2d) -> Option<Rc<RefCell<Unit>>> {
-        self.units.get(&p).cloned()
+        State {
+            config,
+            map,
+            units,
+            unit_indices,
+        }
     }
-    pub fn turns(&self) -> Vec<Rc<RefCell<Unit>>> {
-        let mut units = self.units.values().cloned().collect::<Vec<_>>();
-        units.sort_by(|u0, u1| {
-            let u0 = u0.borrow();
-            let u1 = u1.borrow();
-            reading_order_cmp(&u0.position, &u1.position)
+    /// Returns the indices of the living units in turn order.
+    pub fn turns(&self) -> Vec<usize> {
+        let mut indices = self.unit_indices.values().copied().collect::<Vec<_>>();
+        indices.sort_by(|&i0, &i1| {
+            reading_order_cmp(&self.units[i0].position, &self.units[i1].position)
         });
-        units
+        indices
     }
-    pub fn target_positions(&self, unit: &Rc<RefCell<Unit>>) -> HashSet<Point2d> {
-        let mut result = HashSet::new();
-        let unit = unit.borrow();
-        for u in self.units.values() {
-            let u = u.borrow();
-            if u.unit_type != unit.unit_type {
-                result.insert(u.position);
-            }
-        }
-        result
+    pub fn target_positions(&self, unit: usize) -> HashSet<Point2d> {
+        let unit_type = self.units[unit].unit_type;
+        self.unit_indices
+            .iter()
+            .filter(|&(_, &i)| self.units[i].unit_type != unit_type)
+            .map(|(&p, _)| p)
+            .collect()
     }
     pub fn is_open_position(&self, p: Point2d) -> bool {
         self.map.get(p) == Some(&Square::Open)
     }
-    pub fn is_target_position(&self, u: &Rc<RefCell<Unit>>, p: Point2d) -> bool {
+    pub fn is_target_position(&self, unit: usize, p: Point2d) -> bool {
         if let Some(&Square::Unit(unit_type)) = self.map.get(p) {
-            unit_type != u.borrow().unit_type
+            unit_type != self.units[unit].unit_type
         } else {
             false
         }
     }
-    pub fn move_unit(&mut self, unit: &Rc<RefCell<Unit>>, p: Point2d) {
-        self.units.remove(&unit.borrow().position);
-
-        let u = &mut unit.borrow_mut();
-        let unit_position = &mut u.position;
-        assert_eq!(1, unit_position.distance_l1(p));
-        self.map[*unit_position] = Square::Open;
-        *unit_position = p;
-        let unit_type = u.unit_type;
-        self.map[p] = Square::Unit(unit_type);
-
-        self.units.insert(p, unit.clone());
+    pub fn move_unit(&mut self, unit: usize, p: Point2d) {
+        let u = &mut self.units[unit];
+        assert_eq!(1, u.position.distance_l1(p));
+        self.unit_indices.remove(&u.position);
+        self.map[u.position] = Square::Open;
+        u.position = p;
+        self.map[p] = Square::Unit(u.unit_type);
+        self.unit_indices.insert(p, unit);
     }
-    pub fn attack(&mut self, unit: &Rc<RefCell<Unit>>, p: Point2d) -> Result<(), Err> {
-        assert_eq!(1, unit.borrow().position.distance_l1(p));
+    pub fn attack(&mut self, unit: usize, p: Point2d) -> Result<(), Err> {
+        assert_eq!(1, self.units[unit].position.distance_l1(p));
         assert!(self.is_target_position(unit, p));
 
-        let target = self.units[&p].clone();
-        let mut target = target.borrow_mut();
-        target.hit_points -= self.attack_power(unit);
-        if target.hit_points <= 0 {
-            self.units.remove(&p);
+        let attack_power = self.attack_power(unit);
+        let target = &mut self.units[self.unit_indices[&p]];
+        target.hit_points -= attack_power;
+        if !target.is_alive() {
+            self.unit_indices.remove(&p);
             self.map[p] = Square::Open;
             if target.unit_type == UnitType::Elf && !self.config.elves_may_die {
                 return Err(Err::ElfDied);
@@ -174,125 +208,139 @@ impl State {
         }
         Ok(())
     }
-    pub fn attack_power(&self, unit: &Rc<RefCell<Unit>>) -> i64 {
-        if unit.borrow().unit_type == UnitType::Elf {
+    pub fn attack_power(&self, unit: usize) -> i64 {
+        if self.units[unit].unit_type == UnitType::Elf {
             self.config.elf_attack_power
         } else {
-            3
+            ATTACK_POWER
         }
     }
     pub fn hit_points(&self) -> i64 {
-        self.units
+        self.unit_indices
             .values()
-            .map(|u| u.borrow().hit_points)
+            .map(|&i| self.units[i].hit_points)
             .sum::<i64>()
     }
-    pub fn run(&mut self) -> Result<i64, Err> {
-        let mut round = 0;
-        let full_rounds;
-        'outer: loop {
-            round += 1;
+    /// Plays one turn of a unit.
+    ///
+    /// Returns false if the unit finds no targets, which ends the combat.
+    fn turn(&mut self, u: usize) -> Result<bool, Err> {
+        let target_positions = self.target_positions(u);
+        if target_positions.is_empty() {
+            return Ok(false);
+        }
 
-            // play the units in turn order
-            for u in self.turns() {
-                if u.borrow().hit_points > 0 {
-                    let target_positions = self.target_positions(&u);
-                    if target_positions.is_empty() {
-                        full_rounds = round - 1;
-                        break 'outer;
-                    }
+        let pu = self.units[u].position;
 
-                    let pu = u.borrow().position;
+        let mut target_neighbor_positions = HashSet::new();
+        for tp in target_positions {
+            for p1 in tp.neighbors_l1() {
+                if self.is_open_position(p1) || p1 == pu {
+                    target_neighbor_positions.insert(p1);
+                }
+            }
+        }
 
-                    let mut target_neighbor_positions = HashSet::new();
-                    for tp in target_positions {
-                        for p1 in tp.neighbors_l1() {
-                            if self.is_open_position(p1) || p1 == pu {
-                                target_neighbor_positions.insert(p1);
-                            }
+        if !target_neighbor_positions.contains(&pu) {
+            // we are not yet in an attack position
+
+            // do a breadth-first search until a target neighbor is reached
+            // or all reachable positions are exhausted
+            let mut positions = HashSet::new();
+            let mut distances = HashMap::new();
+            positions.insert(pu);
+            let mut distance = 0;
+            distances.insert(pu, distance);
+            while !positions.is_empty() && positions.is_disjoint(&target_neighbor_positions) {
+                distance += 1;
+                let mut new_positions = HashSet::new();
+                for p in &positions {
+                    for p1 in p.neighbors_l1() {
+                        if self.is_open_position(p1) && !distances.contains_key(&p1) {
+                            new_positions.insert(p1);
+                            distances.insert(p1, distance);
                         }
                     }
+                }
+                positions = new_positions;
+            }
+            if positions.is_empty() {
+                return Ok(true);
+            }
 
-                    if !target_neighbor_positions.contains(&pu) {
-                        // we are not yet in an attack position
-
-                        // do a breadth-first search until a target neighbor is reached
-                        // or all reachable positions are exhausted
-                        let mut positions = HashSet::new();
-                        let mut distances = HashMap::new();
-                        positions.insert(pu);
-                        let mut distance = 0;
-                        distances.insert(pu, distance);
-                        while !positions.is_empty()
-                            && positions.is_disjoint(&target_neighbor_positions)
-                        {
-                            distance += 1;
-                            let mut new_positions = HashSet::new();
-                            for p in &positions {
-                                for p1 in p.neighbors_l1() {
-                                    if self.is_open_position(p1) && distances.get(&p1) == None {
-                                        new_positions.insert(p1);
-                                        distances.insert(p1, distance);
-                                    }
-                                }
-                            }
-                            positions = new_positions;
-                        }
-                        if positions.is_empty() {
-                            continue;
-                        }
-
-                        // find target position to move towards
-                        let mut target_positions = Vec::new();
-                        for &p in &positions {
-                            if target_neighbor_positions.contains(&p) {
-                                target_positions.push(p);
-                            }
-                        }
-                        target_positions.sort_by(reading_order_cmp);
-                        let target_position = target_positions[0];
-
-                        let mut positions = HashSet::new();
-                        positions.insert(target_position);
-                        while distance > 1 {
-                            let mut new_positions = HashSet::new();
-                            distance -= 1;
-                            for p in &positions {
-                                for np in p.neighbors_l1() {
-                                    if let Some(&d) = distances.get(&np) {
-                                        if d == distance {
-                                            new_positions.insert(np);
-                                        }
-                                    }
-                                }
+            // find target position to move towards
+            let mut target_positions = Vec::new();
+            for &p in &positions {
+                if target_neighbor_positions.contains(&p) {
+                    target_positions.push(p);
+                }
+            }
+            target_positions.sort_by(reading_order_cmp);
+            let target_position = target_positions[0];
+
+            let mut positions = HashSet::new();
+            positions.insert(target_position);
+            while distance > 1 {
+                let mut new_positions = HashSet::new();
+                distance -= 1;
+                for p in &positions {
+                    for np in p.neighbors_l1() {
+                        if let Some(&d) = distances.get(&np) {
+                            if d == distance {
+                                new_positions.insert(np);
                             }
-                            positions = new_positions;
                         }
-                        let mut positions = positions.into_iter().collect::<Vec<_>>();
-                        positions.sort_by(reading_order_cmp);
-
-                        self.move_unit(&u, positions[0]);
                     }
+                }
+                positions = new_positions;
+            }
+            let mut positions = positions.into_iter().collect::<Vec<_>>();
+            positions.sort_by(reading_order_cmp);
 
-                    // unit position after possible move
-                    let pu = u.borrow().position;
-                    if target_neighbor_positions.contains(&pu) {
-                        let mut targets = Vec::new();
-                        for np in pu.neighbors_l1() {
-                            if self.is_target_position(&u, np) {
-                                if let Some(u) = self.get_unit(np) {
-                                    targets.push(u);
-                                }
-                            }
-                        }
-                        targets.sort_by(attack_order_cmp);
-                        let target_position = targets[0].borrow().position;
-                        self.attack(&u, target_position)?;
-                    }
+            self.move_unit(u, positions[0]);
+        }
+
+        // unit position after possible move
+        let pu = self.units[u].position;
+        if target_neighbor_positions.contains(&pu) {
+            let mut targets = pu
+                .neighbors_l1()
+                .filter(|&np| self.is_target_position(u, np))
+                .map(|np| self.units[self.unit_indices[&np]])
+                .collect::<Vec<_>>();
+            targets.sort_by(|u0, u1| {
+                u0.hit_points
+                    .cmp(&u1.hit_points)
+                    .then(reading_order_cmp(&u0.position, &u1.position))
+            });
+            self.attack(u, targets[0].position)?;
+        }
+        Ok(true)
+    }
+    /// Runs the combat to its end.
+    ///
+    /// The trace function is called with the state initially and after every full round.
+    pub fn run<F>(&mut self, mut trace: F) -> Result<Outcome, Err>
+    where
+        F: FnMut(i64, &State),
+    {
+        let mut full_rounds = 0;
+        trace(full_rounds, self);
+        loop {
+            // play the units in turn order
+            for u in self.turns() {
+                // units may die before their turn
+                if self.units[u].is_alive() && !self.turn(u)? {
+                    return Ok(Outcome {
+                        full_rounds,
+                        hit_points: self.hit_points(),
+                        winner: self.units[u].unit_type,
+                    });
                 }
             }
+            full_rounds += 1;
+            trace(full_rounds, self);
         }
-        Ok(full_rounds * self.hit_points())
     }
 }
 
@@ -303,11 +351,10 @@ impl fmt::Display for State {
                 let p = p2d(x, y);
                 write!(f, "{}", self.map[p])?;
             }
-            write!(f, "   ")?;
-            let mut sep = "";
+            let mut sep = "   ";
             for x in self.map.bbox().x_range() {
-                if let Some(u) = self.units.get(&p2d(x, y)) {
-                    let u = u.borrow();
+                if let Some(&i) = self.unit_indices.get(&p2d(x, y)) {
+                    let u = &self.units[i];
                     write!(f, "{}{}({})", sep, u.unit_type.to_char(), u.hit_points)?;
                     sep = ", ";
                 }
@@ -318,6 +365,61 @@ impl fmt::Display for State {
     }
 }
 
+/// Prints the state as in the examples of the puzzle.
+fn print_round(round: i64, state: &State) {
+    match round {
+        0 => println!("Initially:"),
+        1 => println!("After 1 round:"),
+        _ => println!("After {} rounds:", round),
+    }
+    println!("{}", state);
+}
+
+/// Returns the outcome if no elf dies with the attack power.
+fn elves_win(map: &Array2d<i64, Square>, elf_attack_power: i64) -> Option<Outcome> {
+    let config = Config {
+        elf_attack_power,
+        elves_may_die: false,
+    };
+    State::from_map(map.clone(), config).run(|_, _| {}).ok()
+}
+
+/// Returns the minimal elf attack power for which no elf dies, and the outcome.
+///
+/// Tries the normal attack power first.  Otherwise finds an upper bound
+/// by doubling the attack power, then does a binary search.
+/// This assumes that a higher attack power never makes the elves worse off.
+fn min_elf_attack_power(map: &Array2d<i64, Square>) -> Option<(i64, Outcome)> {
+    if let Some(outcome) = elves_win(map, ATTACK_POWER) {
+        return Some((ATTACK_POWER, outcome));
+    }
+    let mut low = ATTACK_POWER;
+    let mut high = 2 * ATTACK_POWER;
+    let mut high_outcome = loop {
+        if let Some(outcome) = elves_win(map, high) {
+            break outcome;
+        }
+        if high >= HIT_POINTS {
+            // Even killing with a single attack is not enough.
+            return None;
+        }
+        low = high;
+        high = (2 * high).min(HIT_POINTS);
+    };
+    // The elves lose with the low attack power and win with the high one.
+    while high - low > 1 {
+        let mid = (low + high) / 2;
+        match elves_win(map, mid) {
+            Some(outcome) => {
+                high = mid;
+                high_outcome = outcome;
+            }
+            None => low = mid,
+        }
+    }
+    Some((high, high_outcome))
+}
+
 fn square(i: &str) -> IResult<&str, Square> {
     let p0 = value(Square::Open, char('.'));
     let p1 = value(Square::Wall, char('#'));
@@ -336,8 +438,11 @@ fn lines(i: &str) -> IResult<&str, Vec<Vec<Square>>> {
     many1(line)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // The option `--trace` prints the map after every round.
+    let trace = env::args().nth(1).is_some_and(|arg| arg == "--trace");
 
     // parse input
     let result = lines(&input_data);
@@ -346,22 +451,184 @@ fn main() {
     let map = Array2d::from_vec(result.unwrap().1);
 
     let mut state = State::from_map(map.clone(), Config::default());
-    let result_a = state.run().unwrap();
+    let outcome = state
+        .run(|round, state| {
+            if trace {
+                print_round(round, state);
+            }
+        })
+        .map_err(|_| util::runtime_error!("unexpected elf death"))?;
+    if trace {
+        println!("{}", outcome);
+    }
+    let result_a = outcome.score();
 
-    let mut eap = 3;
-    let result = loop {
-        let config = Config {
-            elf_attack_power: eap,
-            elves_may_die: false,
-        };
-        let mut state = State::from_map(map.clone(), config);
-        if let Ok(result) = state.run() {
-            break result;
-        }
-        eap += 1;
-    };
+    let (elf_attack_power, outcome) = min_elf_attack_power(&map)
+        .ok_or_else(|| util::runtime_error!("the elves can't win without losses"))?;
+    if trace {
+        println!("Elf attack power {}:", elf_attack_power);
+        println!("{}", outcome);
+    }
+    let result_b = outcome.score();
 
-    let result_b = result;
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lowdim::Array2d;
+
+    use crate::lines;
+    use crate::min_elf_attack_power;
+    use crate::Config;
+    use crate::State;
+    use crate::UnitType;
+    use crate::ATTACK_POWER;
+
+    fn score(data: &str) -> i64 {
+        let map = Array2d::from_vec(lines(data).unwrap().1);
+        let mut state = State::from_map(map, Config::default());
+        state.run(|_, _| {}).unwrap().score()
+    }
+
+    fn min_power(data: &str) -> (i64, i64) {
+        let map = Array2d::from_vec(lines(data).unwrap().1);
+        let (power, outcome) = min_elf_attack_power(&map).unwrap();
+        (power, outcome.score())
+    }
+
+    const EXAMPLE1: &str = "\
+#######
+#.G...#
+#...EG#
+#.#.#G#
+#..G#E#
+#.....#
+#######
+";
+    const EXAMPLE2: &str = "\
+#######
+#G..#E#
+#E#E.E#
+#G.##.#
+#...#E#
+#...E.#
+#######
+";
+    const EXAMPLE3: &str = "\
+#######
+#E..EG#
+#.#G.E#
+#E.##E#
+#G..#.#
+#..E#.#
+#######
+";
+    const EXAMPLE4: &str = "\
+#######
+#E.G#.#
+#.#G..#
+#G.#.G#
+#G..#.#
+#...E.#
+#######
+";
+    const EXAMPLE5: &str = "\
+#######
+#.E...#
+#.#..G#
+#.###.#
+#E#G#G#
+#...#G#
+#######
+";
+    const EXAMPLE6: &str = "\
+#########
+#G......#
+#.E.#...#
+#..##..G#
+#...##..#
+#...#...#
+#.G...G.#
+#.....G.#
+#########
+";
+
+    #[test]
+    fn test_trace() {
+        let map = Array2d::from_vec(lines(EXAMPLE1).unwrap().1);
+        let mut state = State::from_map(map, Config::default());
+        let mut trace = Vec::new();
+        let outcome = state
+            .run(|round, state| trace.push((round, state.to_string())))
+            .unwrap();
+        assert_eq!(outcome.full_rounds, 47);
+        assert_eq!(outcome.hit_points, 590);
+        assert_eq!(outcome.winner, UnitType::Goblin);
+        assert_eq!(trace.len(), 48);
+        assert_eq!(
+            trace[1],
+            (
+                1,
+                "\
+#######
+#..G..#   G(200)
+#...EG#   E(197), G(197)
+#.#G#G#   G(200), G(197)
+#...#E#   E(197)
+#.....#
+#######
+"
+                .to_string()
+            )
+        );
+        assert_eq!(
+            trace[2].1,
+            "\
+#######
+#...G.#   G(200)
+#..GEG#   G(200), E(188), G(194)
+#.#.#G#   G(194)
+#...#E#   E(194)
+#.....#
+#######
+"
+        );
+        assert_eq!(
+            trace[47].1,
+            "\
+#######
+#G....#   G(200)
+#.G...#   G(131)
+#.#.#G#   G(59)
+#...#.#
+#....G#   G(200)
+#######
+"
+        );
+    }
+
+    #[test]
+    fn test_score() {
+        assert_eq!(score(EXAMPLE1), 27730);
+        assert_eq!(score(EXAMPLE2), 36334);
+        assert_eq!(score(EXAMPLE3), 39514);
+        assert_eq!(score(EXAMPLE4), 27755);
+        assert_eq!(score(EXAMPLE5), 28944);
+        assert_eq!(score(EXAMPLE6), 18740);
+    }
+
+    #[test]
+    fn test_min_elf_attack_power() {
+        assert_eq!(min_power(EXAMPLE1), (15, 4988));
+        assert_eq!(min_power(EXAMPLE3), (4, 31284));
+        assert_eq!(min_power(EXAMPLE4), (15, 3478));
+        assert_eq!(min_power(EXAMPLE5), (12, 6474));
+        assert_eq!(min_power(EXAMPLE6), (34, 1140));
+        // The elf wins without a stronger attack.
+        assert_eq!(min_power("#EG#\n"), (ATTACK_POWER, 134));
+    }
 }
@@ -1,5 +1,9 @@
+use std::env;
+use std::fmt;
+use std::fs;
 use std::io;
-use std::iter::repeat;
+use std::ops::RangeInclusive;
+use std::path::PathBuf;
 use std::str::FromStr;
 
 use nom::branch::alt;
@@ -18,9 +22,6 @@ struct Vein {
     y1: i64,
 }
 
-#[derive(Clone, Debug)]
-enum Error {}
-
 fn int64(i: &str) -> IResult<&str, i64> {
     map_res(digit1, FromStr::from_str)(i)
 }
@@ -73,161 +74,339 @@ fn input(i: &str) -> IResult<&str, Vec<Vein>> {
     many1(vein)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+util::grid_cell! {
+    enum Square {
+        Sand => '.',
+        Clay => '#',
+        Flowing => '|',
+        Standing => '~',
+        Spring => '+',
+    }
+}
+impl Square {
+    /// The color of the square in an image.
+    fn color(self) -> [u8; 3] {
+        match self {
+            Square::Sand => [0xe6, 0xd2, 0xa0],
+            Square::Clay => [0x8b, 0x4a, 0x1c],
+            Square::Flowing => [0x8c, 0xd2, 0xff],
+            Square::Standing => [0x1e, 0x5a, 0xc8],
+            Square::Spring => [0xff, 0x00, 0x00],
+        }
+    }
+}
 
-    // parse input
-    let result = input(&input_data);
-    //println!("{:?}", result);
+const SPRING_X: i64 = 500;
 
-    let veins = result.unwrap().1;
-    let x_min = veins.iter().map(|v| v.x0).min().unwrap();
-    let x_max = veins.iter().map(|v| v.x1).max().unwrap();
-    let y_min = veins.iter().map(|v| v.y0).min().unwrap();
-    let y_max = veins.iter().map(|v| v.y1).max().unwrap();
+#[derive(Clone, Debug)]
+struct Ground {
+    veins: Vec<Vein>,
+    x_min: i64,
+    x_max: i64,
+    y_min: i64,
+    y_max: i64,
+    /// The x coordinate of the first column of the map.
+    x_base: i64,
+    /// The rows of the map from y = 0 to `y_max`.
+    map: Vec<Vec<Square>>,
+}
+impl Ground {
+    fn new(veins: Vec<Vein>) -> util::Result<Ground> {
+        let x_min = veins.iter().map(|v| v.x0).min().ok_or("no veins")?;
+        let x_max = veins.iter().map(|v| v.x1).max().ok_or("no veins")?;
+        let y_min = veins.iter().map(|v| v.y0).min().ok_or("no veins")?;
+        let y_max = veins.iter().map(|v| v.y1).max().ok_or("no veins")?;
+        if !(x_min..=x_max).contains(&SPRING_X) || y_min <= 0 {
+            return Err(util::runtime_error!("spring is not above the clay"));
+        }
 
-    // add borders to avoid range checking everywhere
-    let x_base = x_min - 2;
-    let y_base = 0;
-    let x_size = x_max - x_min + 5;
-    let y_size = y_max + 1;
+        // add borders to avoid range checking everywhere
+        let x_base = x_min - 2;
+        let x_size = x_max - x_min + 5;
+        let y_size = y_max + 1;
 
-    let mut map = repeat(repeat('.').take(x_size as usize).collect::<Vec<_>>())
-        .take(y_size as usize)
-        .collect::<Vec<_>>();
-    map[(0 - y_base) as usize][(500 - x_base) as usize] = '+';
-    for v in veins {
-        let Vein { x0, x1, y0, y1 } = v;
-        let x0 = (x0 - x_base) as usize;
-        let x1 = (x1 - x_base) as usize;
-        let y0 = (y0 - y_base) as usize;
-        let y1 = (y1 - y_base) as usize;
-        for y in y0..=y1 {
-            for x in x0..=x1 {
-                map[y][x] = '#';
+        let map = vec![vec![Square::Sand; x_size as usize]; y_size as usize];
+        let mut ground = Ground {
+            veins,
+            x_min,
+            x_max,
+            y_min,
+            y_max,
+            x_base,
+            map,
+        };
+        ground.set(SPRING_X, 0, Square::Spring);
+        for v in ground.veins.clone() {
+            for y in v.y0..=v.y1 {
+                for x in v.x0..=v.x1 {
+                    ground.set(x, y, Square::Clay);
+                }
             }
         }
+        Ok(ground)
     }
+    fn get(&self, x: i64, y: i64) -> Square {
+        self.map[y as usize][(x - self.x_base) as usize]
+    }
+    fn set(&mut self, x: i64, y: i64, square: Square) {
+        self.map[y as usize][(x - self.x_base) as usize] = square;
+    }
+    fn width(&self) -> usize {
+        self.map[0].len()
+    }
+    /// Lets the water flow from the spring until nothing changes anymore.
+    ///
+    /// Calls `on_step` after each step with the position which was considered.
+    fn run<F>(&mut self, mut on_step: F) -> util::Result<()>
+    where
+        F: FnMut(&Ground, i64, i64) -> util::Result<()>,
+    {
+        let mut open = vec![(SPRING_X, 0)];
+        while let Some((x0, y0)) = open.pop() {
+            // consider position below
+            let x1 = x0;
+            let y1 = y0 + 1;
+            if y1 <= self.y_max {
+                match self.get(x1, y1) {
+                    Square::Sand => {
+                        // sand, water flows down
+                        self.set(x1, y1, Square::Flowing);
+                        open.push((x1, y1));
+                    }
+                    Square::Clay | Square::Standing => {
+                        // clay or standing water, try flow sideways instead
+                        for dx in [-1, 1] {
+                            let x_side = x0 + dx;
+                            match self.get(x_side, y0) {
+                                Square::Sand => {
+                                    // sand, water flows sideways
+                                    self.set(x_side, y0, Square::Flowing);
+                                    open.push((x_side, y0));
+                                }
+                                Square::Clay => {
+                                    // clay, water is blocked
 
-    let mut open = vec![(500, 0)];
-    while let Some((x0, y0)) = open.pop() {
-        // consider position below
-        let x1 = x0;
-        let y1 = y0 + 1;
-        if y1 <= y_max {
-            let c1 = map[(y1 - y_base) as usize][(x1 - x_base) as usize];
-            match c1 {
-                '.' => {
-                    // sand, water flows down
-                    map[(y1 - y_base) as usize][(x1 - x_base) as usize] = '|';
-                    open.push((x1, y1));
-                }
-                '#' | '~' => {
-                    // clay or standing water, try flow sideways instead
-
-                    let x_left = x0 - 1;
-                    let y_left = y0;
-                    let c_left = map[(y_left - y_base) as usize][(x_left - x_base) as usize];
-                    match c_left {
-                        '.' => {
-                            // sand, water flows left
-                            map[(y_left - y_base) as usize][(x_left - x_base) as usize] = '|';
-                            open.push((x_left, y_left));
-                        }
-                        '#' => {
-                            // clay, water is blocked
-
-                            // check if flowing water reaches clay on other side
-                            let mut xt = x0;
-                            while map[(y0 - y_base) as usize][(xt - x_base) as usize] == '|' {
-                                xt += 1;
-                            }
-                            if map[(y0 - y_base) as usize][(xt - x_base) as usize] == '#' {
-                                // we have standing water
-                                for x in x0..=(xt - 1) {
-                                    map[(y0 - y_base) as usize][(x - x_base) as usize] = '~';
-                                    // reconsider positions above now standing water
-                                    if map[(y0 - 1 - y_base) as usize][(x - x_base) as usize] == '|'
-                                    {
-                                        open.push((x, y0 - 1));
+                                    // check if flowing water reaches clay on other side
+                                    let mut xt = x0;
+                                    while self.get(xt, y0) == Square::Flowing {
+                                        xt -= dx;
+                                    }
+                                    if self.get(xt, y0) == Square::Clay {
+                                        // we have standing water
+                                        for x in x0.min(xt + dx)..=x0.max(xt + dx) {
+                                            self.set(x, y0, Square::Standing);
+                                            // reconsider positions above now standing water
+                                            if self.get(x, y0 - 1) == Square::Flowing {
+                                                open.push((x, y0 - 1));
+                                            }
+                                        }
                                     }
                                 }
+                                _ => {}
                             }
                         }
-                        _ => {}
                     }
+                    _ => {}
+                }
+            }
+            on_step(self, x0, y0)?;
+        }
+        Ok(())
+    }
+    /// Checks that the water is consistent in the given rows.
+    ///
+    /// Clay must still be clay, and each stretch of standing water must have
+    /// clay at both ends and clay or standing water below.
+    fn check_rows(&self, ys: RangeInclusive<i64>) -> Result<(), String> {
+        for v in &self.veins {
+            for y in v.y0.max(*ys.start())..=v.y1.min(*ys.end()) {
+                for x in v.x0..=v.x1 {
+                    if self.get(x, y) != Square::Clay {
+                        return Err(format!("water inside clay at {},{}", x, y));
+                    }
+                }
+            }
+        }
+        let y_end = self.y_max.min(*ys.end());
+        for y in (*ys.start()).max(0)..=y_end {
+            let x_end = self.x_base + self.width() as i64;
+            for x in self.x_base..x_end {
+                if self.get(x, y) != Square::Standing {
+                    continue;
+                }
+                if y == self.y_max {
+                    return Err(format!("standing water at the bottom at {},{}", x, y));
+                }
+                if !matches!(self.get(x, y + 1), Square::Clay | Square::Standing) {
+                    return Err(format!(
+                        "standing water above {} at {},{}",
+                        self.get(x, y + 1),
+                        x,
+                        y
+                    ));
+                }
+                for dx in [-1, 1] {
+                    let side = self.get(x + dx, y);
+                    if !matches!(side, Square::Clay | Square::Standing) {
+                        return Err(format!("standing water next to {} at {},{}", side, x, y));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+    fn check(&self) -> Result<(), String> {
+        self.check_rows(0..=self.y_max)
+    }
+    /// Counts the squares within the vertical range of the clay.
+    fn count(&self, square: Square) -> usize {
+        (self.y_min..=self.y_max)
+            .map(|y| {
+                self.map[y as usize]
+                    .iter()
+                    .filter(|&&s| s == square)
+                    .count()
+            })
+            .sum()
+    }
+    /// Returns the ground as an image in binary PPM format.
+    fn to_ppm(&self) -> Vec<u8> {
+        let mut result = format!("P6\n{} {}\n255\n", self.width(), self.map.len()).into_bytes();
+        for row in &self.map {
+            for square in row {
+                result.extend(square.color());
+            }
+        }
+        result
+    }
+}
+impl fmt::Display for Ground {
+    /// Shows the ground in the notation of the puzzle,
+    /// with one column of sand on each side of the clay.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for y in 0..=self.y_max {
+            for x in (self.x_min - 1)..=(self.x_max + 1) {
+                write!(f, "{}", self.get(x, y))?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
 
-                    let x_right = x0 + 1;
-                    let y_right = y0;
-                    let c_right = map[(y_right - y_base) as usize][(x_right - x_base) as usize];
-                    match c_right {
-                        '.' => {
-                            // sand, water flows right
-                            map[(y_right - y_base) as usize][(x_right - x_base) as usize] = '|';
-                            open.push((x_right, y_right));
-                        }
-                        '#' => {
-                            // clay, water is blocked
-
-                            // check if flowing water reaches clay on other side
-                            let mut xt = x0;
-                            while map[(y0 - y_base) as usize][(xt - x_base) as usize] == '|' {
-                                xt -= 1;
-                            }
-                            if map[(y0 - y_base) as usize][(xt - x_base) as usize] == '#' {
-                                // we have standing water
-                                for x in (xt + 1)..=x0 {
-                                    map[(y0 - y_base) as usize][(x - x_base) as usize] = '~';
-                                    // reconsider positions above now standing water
-                                    if map[(y0 - 1 - y_base) as usize][(x - x_base) as usize] == '|'
-                                    {
-                                        open.push((x, y0 - 1));
-                                    }
-                                }
-                            }
-                        }
-                        _ => {}
+#[derive(Clone, Debug, Default)]
+struct Options {
+    /// Print the final state as text.
+    text: bool,
+    /// Write the final state as an image to this file.
+    ppm: Option<PathBuf>,
+    /// Write an image to this directory every given number of steps.
+    frames: Option<(PathBuf, usize)>,
+    /// Check the invariants of the water after every step.
+    check: bool,
+}
+impl Options {
+    fn from_args() -> util::Result<Options> {
+        let mut options = Options::default();
+        let mut args = env::args().skip(1);
+        while let Some(arg) = args.next() {
+            let mut value = || {
+                args.next()
+                    .ok_or_else(|| util::runtime_error!("missing argument for {}", arg))
+            };
+            match arg.as_str() {
+                "--text" => options.text = true,
+                "--ppm" => options.ppm = Some(PathBuf::from(value()?)),
+                "--frames" => {
+                    let dir = PathBuf::from(value()?);
+                    let every = value()?.parse::<usize>()?;
+                    if every == 0 {
+                        return Err(util::runtime_error!(
+                            "frames must be at least one step apart"
+                        ));
                     }
+                    options.frames = Some((dir, every));
                 }
-                _ => {}
+                "--check" => options.check = true,
+                _ => return Err(util::runtime_error!("unknown option {}", arg)),
             }
         }
+        Ok(options)
     }
+}
 
-    // for y in y_base..(y_base + y_size) {
-    //     for x in x_base..(x_base + x_size) {
-    //         let c = map[(y - y_base) as usize][(x - x_base) as usize];
-    //         print!("{}", c);
-    //     }
-    //     println!();
-    // }
+fn main() -> util::Result<()> {
+    let options = Options::from_args()?;
 
-    let mut count_flowing = 0;
-    let mut count_standing = 0;
-    for y in y_min..=y_max {
-        for x in x_base..(x_base + x_size) {
-            let c = map[(y - y_base) as usize][(x - x_base) as usize];
-            match c {
-                '|' => {
-                    count_flowing += 1;
-                }
-                '~' => {
-                    count_standing += 1;
-                }
-                _ => {}
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let result = input(&input_data);
+    //println!("{:?}", result);
+
+    let veins = result.unwrap().1;
+    let mut ground = Ground::new(veins)?;
+
+    let mut step = 0;
+    ground.run(|ground, _x, y| {
+        step += 1;
+        if options.check {
+            ground
+                .check_rows((y - 1)..=(y + 1))
+                .map_err(|e| util::runtime_error!("after step {}: {}", step, e))?;
+        }
+        if let Some((dir, every)) = &options.frames {
+            if step % every == 0 {
+                let path = dir.join(format!("frame_{:06}.ppm", step / every));
+                fs::write(path, ground.to_ppm())?;
             }
         }
+        Ok(())
+    })?;
+    if options.check {
+        ground.check()?;
+    }
+
+    if options.text {
+        print!("{}", ground);
     }
+    if let Some(path) = &options.ppm {
+        fs::write(path, ground.to_ppm())?;
+    }
+
+    let count_flowing = ground.count(Square::Flowing);
+    let count_standing = ground.count(Square::Standing);
 
     println!("a: {}", count_flowing + count_standing);
     println!("b: {}", count_standing);
+
+    Ok(())
 }
 
 #[cfg(test)]
 mod test {
+    use crate::input;
     use crate::vein;
+    use crate::Ground;
+    use crate::Square;
     use crate::Vein;
 
+    const EXAMPLE: &str = "\
+x=495, y=2..7
+y=7, x=495..501
+x=501, y=3..7
+x=498, y=2..4
+x=506, y=1..2
+x=498, y=10..13
+x=504, y=10..13
+y=13, x=498..504
+";
+
+    fn ground() -> Ground {
+        let (_, veins) = input(EXAMPLE).unwrap();
+        Ground::new(veins).unwrap()
+    }
+
     #[test]
     fn test_vein() {
         assert_eq!(
@@ -255,4 +434,63 @@ mod test {
             ))
         );
     }
+
+    #[test]
+    fn test_example() {
+        let mut ground = ground();
+        ground.run(|_, _, _| Ok(())).unwrap();
+        assert_eq!(
+            ground.to_string(),
+            "\
+......+.......
+......|.....#.
+.#..#||||...#.
+.#..#~~#|.....
+.#..#~~#|.....
+.#~~~~~#|.....
+.#~~~~~#|.....
+.#######|.....
+........|.....
+...|||||||||..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#~~~~~#|..
+...|#######|..
+"
+        );
+        assert_eq!(
+            ground.count(Square::Flowing) + ground.count(Square::Standing),
+            57
+        );
+        assert_eq!(ground.count(Square::Standing), 29);
+    }
+
+    #[test]
+    fn test_check() {
+        let mut ground = ground();
+        ground
+            .run(|ground, _, y| Ok(ground.check_rows((y - 1)..=(y + 1))?))
+            .unwrap();
+        assert_eq!(ground.check(), Ok(()));
+
+        let mut broken = ground.clone();
+        broken.set(495, 5, Square::Standing);
+        assert!(broken.check().is_err());
+        let mut broken = ground.clone();
+        broken.set(502, 6, Square::Standing);
+        assert!(broken.check().is_err());
+        let mut broken = ground;
+        broken.set(504, 9, Square::Standing);
+        assert!(broken.check().is_err());
+    }
+
+    #[test]
+    fn test_ppm() {
+        let ground = ground();
+        let ppm = ground.to_ppm();
+        let header = b"P6\n16 14\n255\n";
+        assert!(ppm.starts_with(header));
+        assert_eq!(ppm.len(), header.len() + 16 * 14 * 3);
+        assert_eq!(&ppm[header.len()..header.len() + 3], &Square::Sand.color());
+    }
 }
@@ -1,5 +1,8 @@
 use core::fmt;
 
+use std::cmp::Ordering;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::io;
 use std::str::FromStr;
 
@@ -15,6 +18,7 @@ use nom::sequence::tuple;
 use nom::IResult;
 
 use lowdim::p3d;
+use lowdim::v3d;
 use lowdim::Point3d;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
@@ -35,9 +39,6 @@ impl fmt::Display for Nanobot {
     }
 }
 
-#[derive(Clone, Debug)]
-enum Error {}
-
 fn int64(i: &str) -> IResult<&str, i64> {
     map_res(
         recognize(tuple((opt(char('-')), digit1))),
@@ -68,41 +69,222 @@ fn nanobots(i: &str) -> IResult<&str, Vec<Nanobot>> {
     many1(nanobot)(i)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn coords(p: Point3d) -> [i64; 3] {
+    [p.x(), p.y(), p.z()]
+}
 
-    // parse input
-    let result = nanobots(&input_data);
-    //println!("{:?}", result);
+/// An axis-aligned cube of points.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Cube {
+    min: Point3d,
+    size: i64,
+}
+impl Cube {
+    /// Returns the L1 distance from the point to the nearest point of the cube.
+    ///
+    /// For the L1 norm this is the sum of the distances along the axes.
+    fn distance_l1(&self, p: Point3d) -> i64 {
+        coords(self.min)
+            .into_iter()
+            .zip(coords(p))
+            .map(|(min, c)| {
+                let max = min + self.size - 1;
+                (min - c).max(0) + (c - max).max(0)
+            })
+            .sum()
+    }
+    /// Returns true if some point of the cube is in range of the nanobot.
+    fn intersects(&self, bot: &Nanobot) -> bool {
+        self.distance_l1(bot.pos) <= bot.r
+    }
+    fn children(&self) -> impl Iterator<Item = Cube> + '_ {
+        let size = self.size / 2;
+        (0..8).map(move |i| Cube {
+            min: self.min + size * v3d(i & 1, (i >> 1) & 1, (i >> 2) & 1),
+            size,
+        })
+    }
+}
 
-    let bots = result.unwrap().1;
-    // for b in &bots {
-    //     println!("{:?}", b);
-    // }
+/// A cube in the search, ordered by its priority.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Entry {
+    /// The number of nanobots in range of some point of the cube.
+    bots: usize,
+    /// The distance from the origin to the cube.
+    distance: i64,
+    cube: Cube,
+}
+impl Entry {
+    fn key(&self) -> (usize, Reverse<i64>, Reverse<i64>) {
+        (self.bots, Reverse(self.distance), Reverse(self.cube.size))
+    }
+}
+impl Ord for Entry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+impl PartialOrd for Entry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
+/// Returns the number of nanobots in range of the nanobot with the largest range.
+fn in_range_of_strongest(bots: &[Nanobot]) -> usize {
     let mut max_bot = bots[0];
-    for b in &bots {
+    for b in bots {
         if b.r > max_bot.r {
             max_bot = *b;
         }
     }
 
-    let result_a = bots
-        .iter()
+    bots.iter()
         .filter(|b| b.pos.distance_l1(max_bot.pos) <= max_bot.r)
-        .count();
+        .count()
+}
 
-    let p_min = p3d(std::i64::MIN, std::i64::MIN, std::i64::MIN);
-    let p_max = p3d(std::i64::MAX, std::i64::MAX, std::i64::MAX);
-    let min = bots.iter().map(|b| b.pos).fold(p_max, |m, p| m.min(p));
-    let max = bots.iter().map(|b| b.pos).fold(p_min, |m, p| m.max(p));
-    let d = max - min;
-    println!("min: {:?}", min);
-    println!("max: {:?}", max);
-    println!("d: {:?}", d);
+/// Returns the point in range of the most nanobots which is closest to the origin.
+///
+/// Subdivides cubes into octants, searching the cubes with the most nanobots
+/// in range first.  Since that count is an upper bound for all points
+/// in the cube, the first single point found is a best one.
+/// Among those with the same count cubes closer to the origin are searched first.
+fn best_position(bots: &[Nanobot]) -> Option<Point3d> {
+    if bots.is_empty() {
+        return None;
+    }
+    // The cube must contain all points in range and the origin,
+    // since a best point may be outside the box spanned by the bots.
+    let origin = p3d(0, 0, 0);
+    let min = bots
+        .iter()
+        .map(|b| b.pos + v3d(-b.r, -b.r, -b.r))
+        .fold(origin, |m, p| m.min(p));
+    let max = bots
+        .iter()
+        .map(|b| b.pos + v3d(b.r, b.r, b.r))
+        .fold(origin, |m, p| m.max(p));
+    let extent = coords(max)
+        .into_iter()
+        .zip(coords(min))
+        .map(|(max, min)| max - min + 1)
+        .max()?;
+    let mut size = 1;
+    while size < extent {
+        size *= 2;
+    }
 
-    let result_b = 0;
+    let entry = |cube: Cube| Entry {
+        bots: bots.iter().filter(|b| cube.intersects(b)).count(),
+        distance: cube.distance_l1(origin),
+        cube,
+    };
+    let mut queue = BinaryHeap::new();
+    queue.push(entry(Cube { min, size }));
+    while let Some(Entry { cube, .. }) = queue.pop() {
+        if cube.size == 1 {
+            return Some(cube.min);
+        }
+        for child in cube.children() {
+            queue.push(entry(child));
+        }
+    }
+    None
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let result = nanobots(&input_data);
+    //println!("{:?}", result);
+
+    let bots = result.unwrap().1;
+    // for b in &bots {
+    //     println!("{:?}", b);
+    // }
+
+    let result_a = in_range_of_strongest(&bots);
+
+    let best = best_position(&bots).ok_or_else(|| util::runtime_error!("no nanobots"))?;
+    let result_b = best.distance_l1(p3d(0, 0, 0));
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use lowdim::p3d;
+
+    use crate::best_position;
+    use crate::in_range_of_strongest;
+    use crate::nanobots;
+    use crate::Cube;
+    use crate::Nanobot;
+
+    #[test]
+    fn test_in_range_of_strongest() {
+        let data = "\
+pos=<0,0,0>, r=4
+pos=<1,0,0>, r=1
+pos=<4,0,0>, r=3
+pos=<0,2,0>, r=1
+pos=<0,5,0>, r=3
+pos=<0,0,3>, r=1
+pos=<1,1,1>, r=1
+pos=<1,1,2>, r=1
+pos=<1,3,1>, r=1
+";
+        let (_, bots) = nanobots(data).unwrap();
+        assert_eq!(in_range_of_strongest(&bots), 7);
+    }
+
+    #[test]
+    fn test_best_position() {
+        let data = "\
+pos=<10,12,12>, r=2
+pos=<12,14,12>, r=2
+pos=<16,12,12>, r=4
+pos=<14,14,14>, r=6
+pos=<50,50,50>, r=200
+pos=<10,10,10>, r=5
+";
+        let (_, bots) = nanobots(data).unwrap();
+        assert_eq!(best_position(&bots), Some(p3d(12, 12, 12)));
+    }
+
+    #[test]
+    fn test_best_position_outside_bot_box() {
+        let (_, bots) = nanobots("pos=<10,10,10>, r=100\n").unwrap();
+        assert_eq!(best_position(&bots), Some(p3d(0, 0, 0)));
+        let (_, bots) = nanobots("pos=<10,10,10>, r=100\npos=<20,20,20>, r=100\n").unwrap();
+        assert_eq!(best_position(&bots), Some(p3d(0, 0, 0)));
+        let (_, bots) = nanobots("pos=<10,10,10>, r=5\npos=<-10,-10,-10>, r=5\n").unwrap();
+        assert_eq!(
+            best_position(&bots).map(|p| p.distance_l1(p3d(0, 0, 0))),
+            Some(25)
+        );
+    }
+
+    #[test]
+    fn test_intersects() {
+        let cube = Cube {
+            min: p3d(0, 0, 0),
+            size: 4,
+        };
+        let bot = |x, y, z, r| Nanobot {
+            pos: p3d(x, y, z),
+            r,
+        };
+        assert!(cube.intersects(&bot(2, 2, 2, 0)));
+        assert!(cube.intersects(&bot(5, 5, 5, 6)));
+        assert!(!cube.intersects(&bot(5, 5, 5, 5)));
+        assert!(cube.intersects(&bot(-1, 1, 9, 7)));
+        assert!(!cube.intersects(&bot(-1, 1, 9, 6)));
+    }
 }
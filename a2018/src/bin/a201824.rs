@@ -1,5 +1,7 @@
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::env;
+use std::fmt;
 use std::io;
 use std::iter::repeat;
 use std::str::FromStr;
@@ -22,6 +24,14 @@ enum Side {
     ImmuneSystem,
     Infection,
 }
+impl fmt::Display for Side {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Side::ImmuneSystem => write!(f, "Immune System"),
+            Side::Infection => write!(f, "Infection"),
+        }
+    }
+}
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
 enum AttackType {
@@ -35,6 +45,8 @@ enum AttackType {
 #[derive(Clone, Debug, PartialEq, Eq)]
 struct Group {
     side: Side,
+    /// The number of the group in its army, starting at 1.
+    number: usize,
     n: i64,
     hit_points: i64,
     attack_damage: i64,
@@ -48,6 +60,12 @@ impl Group {
     fn is_enemy(&self, other: &Group) -> bool {
         self.side != other.side
     }
+    fn other_side(&self) -> Side {
+        match self.side {
+            Side::ImmuneSystem => Side::Infection,
+            Side::Infection => Side::ImmuneSystem,
+        }
+    }
     fn effective_power(&self) -> i64 {
         self.n * self.attack_damage
     }
@@ -115,9 +133,6 @@ impl Ord for SelectionPriority {
     }
 }
 
-#[derive(Clone, Debug)]
-enum Error {}
-
 fn int64(i: &str) -> IResult<&str, i64> {
     map_res(
         recognize(tuple((opt(char('-')), digit1))),
@@ -226,6 +241,7 @@ fn group(side: Side, i: &str) -> IResult<&str, Group> {
         i,
         Group {
             side,
+            number: 0,
             n,
             hit_points,
             attack_damage,
@@ -238,7 +254,11 @@ fn group(side: Side, i: &str) -> IResult<&str, Group> {
 }
 
 fn army<'a>(side: Side, i: &'a str) -> IResult<&'a str, Vec<Group>> {
-    separated_list1(line_ending, |i: &'a str| group(side, i))(i)
+    let (i, mut groups) = separated_list1(line_ending, |i: &'a str| group(side, i))(i)?;
+    for (k, g) in groups.iter_mut().enumerate() {
+        g.number = k + 1;
+    }
+    Ok((i, groups))
 }
 
 fn input(i: &str) -> IResult<&str, (Vec<Group>, Vec<Group>)> {
@@ -253,12 +273,110 @@ fn input(i: &str) -> IResult<&str, (Vec<Group>, Vec<Group>)> {
     Ok((i, (army1, army2)))
 }
 
-/// Run the fight until no change occurs.
-///
-/// Note that this doesn't guarantee that one side wins.
-fn fight(groups: Vec<Group>) -> Vec<Group> {
+/// The result of a fight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    /// One side is gone, and the other has units left.
+    Victory { side: Side, units: i64 },
+    /// A round passed without any units killed, so the fight would go on forever.
+    Stalemate,
+}
+
+/// The lines of a battle log, which are only built if it is enabled.
+struct Log {
+    lines: Option<Vec<String>>,
+}
+impl Log {
+    fn push<F>(&mut self, f: F)
+    where
+        F: FnOnce() -> String,
+    {
+        if let Some(lines) = &mut self.lines {
+            lines.push(f());
+        }
+    }
+    fn is_enabled(&self) -> bool {
+        self.lines.is_some()
+    }
+}
+
+/// Returns the living groups of a side, ordered by their numbers.
+fn army_groups(groups: &[Group], side: Side) -> Vec<usize> {
+    let mut result = (0..groups.len())
+        .filter(|&i| groups[i].side == side && groups[i].n > 0)
+        .collect::<Vec<_>>();
+    result.sort_by_key(|&i| groups[i].number);
+    result
+}
+
+/// Logs the remaining groups, as in the puzzle's example.
+fn log_armies(groups: &[Group], log: &mut Log) {
+    if !log.is_enabled() {
+        return;
+    }
+    for side in [Side::ImmuneSystem, Side::Infection] {
+        log.push(|| format!("{}:", side));
+        let army = army_groups(groups, side);
+        if army.is_empty() {
+            log.push(|| "No groups remain.".to_string());
+        }
+        for i in army {
+            log.push(|| format!("Group {} contains {} units", groups[i].number, groups[i].n));
+        }
+    }
+}
+
+/// Logs the damage the groups could deal each other, as in the puzzle's example.
+fn log_damage(groups: &[Group], log: &mut Log) {
+    if !log.is_enabled() {
+        return;
+    }
+    for side in [Side::Infection, Side::ImmuneSystem] {
+        for i in army_groups(groups, side) {
+            let g1 = &groups[i];
+            for j in army_groups(groups, g1.other_side()) {
+                let g2 = &groups[j];
+                let damage = g1.damage(g2);
+                if damage > 0 {
+                    log.push(|| {
+                        format!(
+                            "{} group {} would deal defending group {} {} damage",
+                            g1.side, g1.number, g2.number, damage
+                        )
+                    });
+                }
+            }
+        }
+    }
+    log.push(String::new);
+}
+
+/// Runs the fight until one side is gone or no units are killed in a round.
+fn fight(groups: Vec<Group>, log: &mut Log) -> Outcome {
     let mut groups = groups;
     loop {
+        let units = |side| {
+            groups
+                .iter()
+                .filter(|g| g.side == side)
+                .map(|g| g.n)
+                .sum::<i64>()
+        };
+        let n_immune_system = units(Side::ImmuneSystem);
+        let n_infection = units(Side::Infection);
+        log_armies(&groups, log);
+        if n_immune_system == 0 || n_infection == 0 {
+            let (side, units) = if n_infection == 0 {
+                (Side::ImmuneSystem, n_immune_system)
+            } else {
+                (Side::Infection, n_infection)
+            };
+            return Outcome::Victory { side, units };
+        }
+
+        log.push(String::new);
+        log_damage(&groups, log);
+
         let mut attacks = repeat(None).take(groups.len()).collect::<Vec<_>>();
         let mut attacked_by = repeat(None).take(groups.len()).collect::<Vec<_>>();
 
@@ -275,7 +393,7 @@ fn fight(groups: Vec<Group>) -> Vec<Group> {
                 let mut max_j = None;
                 for j in 0..groups.len() {
                     let g2 = &groups[j];
-                    if g1.is_enemy(g2) && attacked_by[j] == None {
+                    if g1.is_enemy(g2) && attacked_by[j].is_none() {
                         let damage = g1.damage(g2);
                         let effective_power = g2.effective_power();
                         let initiative = g2.initiative;
@@ -304,85 +422,133 @@ fn fight(groups: Vec<Group>) -> Vec<Group> {
         let mut is = (0..groups.len()).collect::<Vec<_>>();
         is.sort_by(|a, b| groups[*b].initiative.cmp(&groups[*a].initiative));
 
-        for k in 0..groups.len() {
-            let i = is[k];
+        for &i in &is {
             if let Some(j) = attacks[i] {
                 let g1 = &groups[i];
                 let g2 = &groups[j];
-                let damage = g1.damage(g2);
-                let kills = damage / g2.hit_points;
-                groups[j].n = (g2.n - kills).max(0);
-
-                change |= kills > 0;
+                // groups killed earlier in the round don't attack
+                if g1.n > 0 {
+                    let damage = g1.damage(g2);
+                    let kills = (damage / g2.hit_points).min(g2.n);
+                    log.push(|| {
+                        format!(
+                            "{} group {} attacks defending group {}, killing {} units",
+                            g1.side, g1.number, g2.number, kills
+                        )
+                    });
+                    groups[j].n -= kills;
+
+                    change |= kills > 0;
+                }
             }
         }
+        log.push(String::new);
 
         if !change {
-            break;
+            return Outcome::Stalemate;
         }
 
         // remove empty groups
         groups = groups.into_iter().filter(|g| g.n > 0).collect::<Vec<_>>();
     }
+}
+
+fn boosted(groups: &[Group], boost: i64) -> Vec<Group> {
+    let mut groups = groups.to_vec();
+    for g in &mut groups {
+        if g.side == Side::ImmuneSystem {
+            g.attack_damage += boost;
+        }
+    }
     groups
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+/// Returns a boost from which on all fights are the same.
+///
+/// From this boost on every immune system group kills any infection group
+/// it damages completely, and the order of the immune system groups
+/// by effective power no longer depends on the boost.
+/// It is dominated by the total hit points of the strongest infection group,
+/// which are of the order of 10^8 for actual inputs.
+fn max_useful_boost(groups: &[Group]) -> i64 {
+    let max_hit_points = groups
+        .iter()
+        .filter(|g| g.side == Side::Infection)
+        .map(|g| g.n * g.hit_points)
+        .max()
+        .unwrap_or(0);
+    let max_units = groups.iter().map(|g| g.n).max().unwrap_or(0);
+    let max_damage = groups.iter().map(|g| g.attack_damage).max().unwrap_or(0);
+    max_hit_points.max(max_units * max_damage)
+}
 
-    // parse input
-    let result = input(&input_data);
-    //println!("{:?}", result);
+/// Returns the smallest boost with which the immune system wins, and its remaining units.
+///
+/// The outcome is not monotonic in the boost, since a larger boost changes
+/// the order of target selection and may lead to a stalemate or a loss.
+/// So a binary search could miss the smallest boost, and all boosts are tried in order.
+/// This is fast if some small boost wins, but if none wins it runs a fight
+/// for every boost up to `max_useful_boost`, so it effectively does not terminate.
+fn min_boost(groups: &[Group]) -> Option<(i64, i64)> {
+    (0..=max_useful_boost(groups)).find_map(|boost| {
+        let mut log = Log { lines: None };
+        match fight(boosted(groups, boost), &mut log) {
+            Outcome::Victory {
+                side: Side::ImmuneSystem,
+                units,
+            } => Some((boost, units)),
+            _ => None,
+        }
+    })
+}
 
-    let armies = result.unwrap().1;
+fn print_log(log: Log) {
+    for line in log.lines.unwrap_or_default() {
+        println!("{}", line);
+    }
+}
 
-    let result1 = {
-        let mut groups = armies
-            .0
-            .iter()
-            .chain(armies.1.iter())
-            .cloned()
-            .collect::<Vec<_>>();
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
-        groups = fight(groups);
-        groups.iter().map(|g| g.n).sum::<i64>()
+    // The option `--log` prints the battles.
+    let print = env::args().nth(1).is_some_and(|arg| arg == "--log");
+    let new_log = || Log {
+        lines: if print { Some(Vec::new()) } else { None },
     };
 
-    let mut boost = 1;
-    let result2 = {
-        loop {
-            let mut groups = armies
-                .0
-                .iter()
-                .chain(armies.1.iter())
-                .cloned()
-                .collect::<Vec<_>>();
+    // parse input
+    let result = input(&input_data);
+    //println!("{:?}", result);
 
-            for g in &mut groups {
-                if g.side == Side::ImmuneSystem {
-                    g.attack_damage += boost;
-                }
-            }
-            groups = fight(groups);
-            let n_immune_system = groups
-                .iter()
-                .filter(|g| g.side == Side::ImmuneSystem)
-                .map(|g| g.n)
-                .sum::<i64>();
-            let n_infection = groups
-                .iter()
-                .filter(|g| g.side == Side::Infection)
-                .map(|g| g.n)
-                .sum::<i64>();
-            if n_immune_system > 0 && n_infection == 0 {
-                break n_immune_system;
-            }
-            boost += 1;
-        }
+    let armies = result.unwrap().1;
+    let groups = armies
+        .0
+        .iter()
+        .chain(armies.1.iter())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut log = new_log();
+    let result1 = match fight(groups.clone(), &mut log) {
+        Outcome::Victory { units, .. } => units,
+        Outcome::Stalemate => return Err(util::runtime_error!("stalemate without boost")),
     };
+    print_log(log);
+
+    let (boost, result2) = min_boost(&groups)
+        .ok_or_else(|| util::runtime_error!("no boost lets the immune system win"))?;
+    if print {
+        println!("Boost {}:", boost);
+        let mut log = new_log();
+        fight(boosted(&groups, boost), &mut log);
+        print_log(log);
+    }
 
     println!("1: {}", result1);
     println!("2: {}", result2);
+
+    Ok(())
 }
 
 #[cfg(test)]
@@ -390,13 +556,34 @@ mod test {
     use std::collections::HashSet;
 
     use crate::attack_types;
+    use crate::boosted;
+    use crate::fight;
     use crate::group;
+    use crate::input;
+    use crate::min_boost;
     use crate::opt_weaknesses_immunities_clause;
     use crate::weaknesses;
     use crate::AttackType::*;
     use crate::Group;
+    use crate::Log;
+    use crate::Outcome;
     use crate::Side;
 
+    const EXAMPLE: &str = "\
+Immune System:
+17 units each with 5390 hit points (weak to radiation, bludgeoning) with an attack that does 4507 fire damage at initiative 2
+989 units each with 1274 hit points (immune to fire; weak to bludgeoning, slashing) with an attack that does 25 slashing damage at initiative 3
+
+Infection:
+801 units each with 4706 hit points (weak to radiation) with an attack that does 116 bludgeoning damage at initiative 1
+4485 units each with 2961 hit points (immune to radiation; weak to fire, cold) with an attack that does 12 slashing damage at initiative 4
+";
+
+    fn example() -> Vec<Group> {
+        let (_, (army1, army2)) = input(EXAMPLE).unwrap();
+        army1.into_iter().chain(army2).collect()
+    }
+
     #[test]
     fn test_attack_types() {
         assert_eq!(
@@ -452,6 +639,7 @@ mod test {
             ),
             Ok(("\n", Group {
                 side: Side::ImmuneSystem,
+                number: 0,
                 n: 8138,
                 hit_points: 8987,
                 attack_damage: 10,
@@ -461,4 +649,92 @@ mod test {
                 immunities: HashSet::new()}))
         );
     }
+
+    #[test]
+    fn test_fight() {
+        let mut log = Log { lines: None };
+        assert_eq!(
+            fight(example(), &mut log),
+            Outcome::Victory {
+                side: Side::Infection,
+                units: 5216
+            }
+        );
+        assert_eq!(
+            fight(boosted(&example(), 1570), &mut log),
+            Outcome::Victory {
+                side: Side::ImmuneSystem,
+                units: 51
+            }
+        );
+    }
+
+    #[test]
+    fn test_stalemate() {
+        let mut groups = example();
+        groups[0].n = 0;
+        for g in &mut groups {
+            g.weaknesses.clear();
+            g.immunities.extend([Slashing, Bludgeoning]);
+        }
+        let mut log = Log { lines: None };
+        assert_eq!(fight(groups, &mut log), Outcome::Stalemate);
+    }
+
+    #[test]
+    fn test_min_boost() {
+        assert_eq!(min_boost(&example()), Some((1570, 51)));
+    }
+
+    #[test]
+    fn test_log() {
+        let mut log = Log {
+            lines: Some(Vec::new()),
+        };
+        fight(example(), &mut log);
+        let lines = log.lines.unwrap();
+        assert_eq!(
+            lines[..7].join("\n"),
+            "\
+Immune System:
+Group 1 contains 17 units
+Group 2 contains 989 units
+Infection:
+Group 1 contains 801 units
+Group 2 contains 4485 units
+"
+        );
+        for line in [
+            "Infection group 1 would deal defending group 1 185832 damage",
+            "Infection group 2 would deal defending group 2 107640 damage",
+            "Immune System group 1 would deal defending group 2 153238 damage",
+            "Immune System group 2 would deal defending group 1 24725 damage",
+        ] {
+            assert!(lines[..17].contains(&line.to_string()));
+        }
+        let attacks = lines
+            .iter()
+            .skip_while(|line| !line.contains("attacks"))
+            .take(5)
+            .cloned()
+            .collect::<Vec<_>>();
+        assert_eq!(
+            attacks.join("\n"),
+            "\
+Infection group 2 attacks defending group 2, killing 84 units
+Immune System group 2 attacks defending group 1, killing 4 units
+Immune System group 1 attacks defending group 2, killing 51 units
+Infection group 1 attacks defending group 1, killing 17 units
+"
+        );
+        assert_eq!(
+            lines[lines.len() - 5..].join("\n"),
+            "\
+Immune System:
+No groups remain.
+Infection:
+Group 1 contains 782 units
+Group 2 contains 4434 units"
+        );
+    }
 }
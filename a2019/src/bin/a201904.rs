@@ -1,3 +1,31 @@
+use core::fmt;
+
+use std::io;
+
+use nom::character::complete::char;
+use nom::IResult;
+
+use util::parse::parse_input;
+use util::parse::uint;
+
+#[derive(Clone, Copy, Debug)]
+struct Range {
+    min: i64,
+    max: i64,
+}
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}-{}", self.min, self.max)
+    }
+}
+
+fn range(i: &str) -> IResult<&str, Range> {
+    let (i, min) = uint(i)?;
+    let (i, _) = char('-')(i)?;
+    let (i, max) = uint(i)?;
+    Ok((i, Range { min, max }))
+}
+
 fn accepted_a(n: i64) -> bool {
     let mut n = n;
     let mut equal_found = false;
@@ -43,8 +71,14 @@ fn accepted_b(n: i64) -> bool {
     equal_found
 }
 
-fn main() {
-    let (a, b) = (138307, 654504);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let range = parse_input(&input_data, range)?;
+    util::validate(&input_data, "", &range);
+
+    let (a, b) = (range.min, range.max);
     let mut count_a = 0;
     for n in a..=b {
         if accepted_a(n) {
@@ -63,4 +97,6 @@ fn main() {
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -2,12 +2,12 @@ use core::iter::repeat;
 
 use std::io;
 
-fn main() {
+use util::ocr::recognize_grid;
+
+fn main() -> util::Result<()> {
     let mut input = String::new();
 
-    io::stdin()
-        .read_line(&mut input)
-        .expect("Failed to read line");
+    io::stdin().read_line(&mut input)?;
 
     let digits = input
         .chars()
@@ -47,24 +47,16 @@ fn main() {
             }
         }
     }
-    for row in &image {
-        for opt in row {
-            if let Some(d) = opt {
-                if *d == 0 {
-                    print!(".");
-                } else {
-                    print!("#");
-                }
-            } else {
-                print!(" ");
-            }
-        }
-        println!();
-    }
+    let pixels = image
+        .iter()
+        .map(|row| row.iter().map(|&opt| opt == Some(1)).collect::<Vec<_>>())
+        .collect::<Vec<_>>();
 
     let result_a = min_count0_12.unwrap();
-    let result_b = "KYHFE";
+    let result_b = recognize_grid(&pixels)?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
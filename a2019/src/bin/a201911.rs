@@ -18,6 +18,8 @@ use lowdim::v2d;
 use lowdim::Point2d;
 use lowdim::Vec2d;
 
+use util::ocr::recognize_points;
+
 fn int64(i: &str) -> IResult<&str, i64> {
     map_res(
         recognize(tuple((opt(char('-')), digit1))),
@@ -275,32 +277,19 @@ impl Robot {
     fn painted_panels_count(&self) -> usize {
         self.plane.len()
     }
-    fn dump_plane(&self) {
-        let mut x_min = std::i64::MAX;
-        let mut x_max = std::i64::MIN;
-        let mut y_min = std::i64::MAX;
-        let mut y_max = std::i64::MIN;
-        for &p in self.plane.keys() {
-            x_min = x_min.min(p.x());
-            x_max = x_max.max(p.x());
-            y_min = y_min.min(p.y());
-            y_max = y_max.max(p.y());
-        }
-        for y in (-y_max)..=(-y_min) {
-            for x in x_min..=x_max {
-                if let Some(&c) = self.plane.get(&p2d(x, -y)) {
-                    print!("{}", vec!['.', '#'][c as usize]);
-                } else {
-                    print!(" ")
-                }
-            }
-            println!();
-        }
+    /// Reads the letters painted white, with the y axis pointing up.
+    fn painted_text(&self) -> util::Result<String> {
+        recognize_points(
+            self.plane
+                .iter()
+                .filter(|&(_, &c)| c == WHITE)
+                .map(|(p, _)| (p.x(), -p.y())),
+        )
     }
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -352,11 +341,11 @@ fn main() {
             robot_b.step(c, t);
         }
     }
-    robot_b.dump_plane();
-
     let result_a = robot_a.painted_panels_count();
-    let result_b = "KRZEAJHB";
+    let result_b = robot_b.painted_text()?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
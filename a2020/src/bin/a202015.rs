@@ -1,4 +1,34 @@
+use core::fmt;
+
 use std::collections::HashMap;
+use std::io;
+
+use nom::character::complete::char;
+use nom::multi::separated_list1;
+use nom::IResult;
+
+use util::parse::parse_input;
+use util::parse::uint;
+
+#[derive(Clone, Debug)]
+struct Input {
+    numbers: Vec<usize>,
+}
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut sep = "";
+        for n in &self.numbers {
+            write!(f, "{}{}", sep, n)?;
+            sep = ",";
+        }
+        writeln!(f)
+    }
+}
+
+fn input(i: &str) -> IResult<&str, Input> {
+    let (i, numbers) = separated_list1(char(','), uint)(i)?;
+    Ok((i, Input { numbers }))
+}
 
 fn play(input: Vec<usize>, target_turn: usize) -> usize {
     let mut turn = 1;
@@ -30,9 +60,17 @@ fn play(input: Vec<usize>, target_turn: usize) -> usize {
     result
 }
 
-fn main() {
-    let result_a = play(vec![1, 0, 15, 2, 10, 13], 2020);
-    let result_b = play(vec![1, 0, 15, 2, 10, 13], 30_000_000);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
+
+    let result_a = play(input.numbers.clone(), 2020);
+    let result_b = play(input.numbers, 30_000_000);
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
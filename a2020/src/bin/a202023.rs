@@ -1,5 +1,11 @@
 use core::fmt;
 
+use std::io;
+
+use nom::character::complete::digit1;
+
+use util::parse::parse_input;
+
 #[derive(Clone, Debug)]
 struct Links {
     /// The label of the cup before this one
@@ -157,9 +163,20 @@ impl fmt::Display for Circle {
     }
 }
 
-fn main() {
-    //let (input, n) = (vec![3, 8, 9, 1, 2, 5, 4, 6, 7], 10);
-    let (input, n) = (vec![4, 6, 7, 5, 2, 8, 1, 9, 3], 100);
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let input_str = parse_input(&input_data, digit1)?;
+    util::validate(&input_data, "", &input_str);
+
+    let input = input_str
+        .chars()
+        .map(|c| c.to_digit(10).map(|d| d as usize))
+        .collect::<Option<Vec<_>>>()
+        .ok_or("invalid digit")?;
+
+    let n = 100;
 
     let mut circle = Circle::from(&input);
     for _ in 0..n {
@@ -181,4 +198,6 @@ fn main() {
         circle.do_move();
     }
     println!("b: {}", circle.solution_b());
+
+    Ok(())
 }
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::collections::HashSet;
+use std::env;
 use std::fmt;
 use std::io;
 
@@ -10,70 +12,16 @@ use nom::multi::many1;
 use nom::multi::separated_list1;
 use nom::IResult;
 
-use lowdim::p2d;
-use lowdim::v2d;
-use lowdim::Point2d;
-use lowdim::Vec2d;
-
-#[derive(Clone, Copy, Debug)]
-enum HexDir {
-    E,
-    NE,
-    NW,
-    W,
-    SW,
-    SE,
-}
-const DIRS: [HexDir; 6] = [
-    HexDir::E,
-    HexDir::NE,
-    HexDir::NW,
-    HexDir::W,
-    HexDir::SW,
-    HexDir::SE,
-];
-impl HexDir {
-    fn to_str(&self) -> &str {
-        match self {
-            HexDir::E => "e",
-            HexDir::NE => "ne",
-            HexDir::NW => "nw",
-            HexDir::W => "w",
-            HexDir::SW => "sw",
-            HexDir::SE => "se",
-        }
-    }
-}
-impl HexDir {
-    /// Get the vector corresponding to a direction.
-    ///
-    /// One step in x direction is east, in y direction northwest,
-    /// same as for Eisenstein numbers.
-    fn to_v2d(&self) -> Vec2d {
-        match self {
-            HexDir::E => v2d(1, 0),
-            HexDir::NE => v2d(1, 1),
-            HexDir::NW => v2d(0, 1),
-            HexDir::W => v2d(-1, 0),
-            HexDir::SW => v2d(-1, -1),
-            HexDir::SE => v2d(0, -1),
-        }
-    }
-}
-impl fmt::Display for HexDir {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_str())
-    }
-}
+use util::hex::render;
+use util::hex::Hex;
+use util::hex::Orientation;
+use util::hex::PointyDir;
 
 #[derive(Clone, Debug)]
-struct Tile(Vec<HexDir>);
+struct Tile(Vec<PointyDir>);
 impl Tile {
-    fn pos(&self) -> Point2d {
-        self.0
-            .iter()
-            .map(HexDir::to_v2d)
-            .fold(p2d(0, 0), |p, v| p + v)
+    fn pos(&self) -> Hex {
+        self.0.iter().map(|d| d.to_hex()).sum()
     }
 }
 impl fmt::Display for Tile {
@@ -85,14 +33,14 @@ impl fmt::Display for Tile {
     }
 }
 
-fn dir(i: &str) -> IResult<&str, HexDir> {
+fn dir(i: &str) -> IResult<&str, PointyDir> {
     alt((
-        value(HexDir::E, tag("e")),
-        value(HexDir::NE, tag("ne")),
-        value(HexDir::NW, tag("nw")),
-        value(HexDir::W, tag("w")),
-        value(HexDir::SW, tag("sw")),
-        value(HexDir::SE, tag("se")),
+        value(PointyDir::E, tag("e")),
+        value(PointyDir::NE, tag("ne")),
+        value(PointyDir::NW, tag("nw")),
+        value(PointyDir::W, tag("w")),
+        value(PointyDir::SW, tag("sw")),
+        value(PointyDir::SE, tag("se")),
     ))(i)
 }
 
@@ -105,12 +53,23 @@ fn input(i: &str) -> IResult<&str, Vec<Tile>> {
     separated_list1(line_ending, tile)(i)
 }
 
-fn hex_neighbors(p: Point2d) -> Vec<Point2d> {
-    DIRS.iter().map(|d| p + d.to_v2d()).collect::<Vec<_>>()
+/// Renders the black tiles as `#` and the white tiles between them as `.`.
+fn floor_map(black_positions: &HashSet<Hex>) -> String {
+    let mut map = black_positions
+        .iter()
+        .flat_map(|p| p.neighbors())
+        .map(|p| (p, '.'))
+        .collect::<HashMap<_, _>>();
+    for &p in black_positions {
+        map.insert(p, '#');
+    }
+    render(Orientation::Pointy, &map)
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let map = env::args().nth(1).is_some_and(|arg| arg == "--map");
+
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -135,18 +94,18 @@ fn main() {
     for _ in 0..100 {
         let neighbor_positions = black_positions
             .iter()
-            .flat_map(|&p| hex_neighbors(p).into_iter())
-            .collect::<HashSet<Point2d>>();
+            .flat_map(|p| p.neighbors())
+            .collect::<HashSet<Hex>>();
         let positions = black_positions
             .union(&neighbor_positions)
             .cloned()
-            .collect::<HashSet<Point2d>>();
+            .collect::<HashSet<Hex>>();
         let new_black_positions = positions
             .into_iter()
             .filter(|&p| {
                 let black = black_positions.contains(&p);
-                let count = hex_neighbors(p)
-                    .iter()
+                let count = p
+                    .neighbors()
                     .filter(|np| black_positions.contains(np))
                     .count();
                 if black {
@@ -160,7 +119,13 @@ fn main() {
         black_positions = new_black_positions;
     }
 
+    if map {
+        print!("{}", floor_map(&black_positions));
+    }
+
     let result_b = black_positions.len();
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -14,9 +14,10 @@ use nom::multi::separated_list1;
 use nom::IResult;
 
 use lowdim::p2d;
-use lowdim::BBox2d;
 use lowdim::Point2d;
 
+use util::ocr::recognize_points;
+
 #[derive(Clone, Copy, Debug)]
 enum Instruction {
     FoldAlongX(i64),
@@ -98,8 +99,8 @@ fn input(i: &str) -> IResult<&str, Input> {
     Ok((i, Input { dots, instructions }))
 }
 
-fn main() {
-    let input_data = io::read_to_string(io::stdin()).expect("I/O error");
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
     let result = input(&input_data);
@@ -123,20 +124,10 @@ fn main() {
             .map(|p| instruction.apply(p))
             .collect::<HashSet<_>>();
     }
-    let bbox = BBox2d::enclosing(&dots).unwrap();
-    for y in bbox.y_range() {
-        for x in bbox.x_range() {
-            if dots.contains(&p2d(x, y)) {
-                print!("#");
-            } else {
-                print!(".");
-            }
-        }
-        println!();
-    }
-    println!();
-    let result_b = "LKREBPRK";
+    let result_b = recognize_points(dots.iter().map(|p| (p.x(), p.y())))?;
 
     println!("a: {}", result_a);
     println!("b: {}", result_b);
+
+    Ok(())
 }
@@ -15,6 +15,8 @@ use nom::multi::many0;
 use nom::sequence::tuple;
 use nom::IResult;
 
+use util::ocr::recognize_points;
+
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum Instruction {
     Addx(i64),
@@ -123,18 +125,16 @@ fn main() -> util::Result<()> {
 
     let mut cpu = Cpu::new();
     let mut instructions = input.iter();
-    for _ in 0..6 {
+    let mut lit_pixels = Vec::new();
+    for y in 0..6 {
         for x in 0..40 {
             cpu.step(&mut instructions);
             if (cpu.x - x).abs() <= 1 {
-                print!("#");
-            } else {
-                print!(".");
+                lit_pixels.push((x, y));
             }
         }
-        println!();
     }
-    let result2 = "ZRARLFZU";
+    let result2 = recognize_points(lit_pixels)?;
 
     println!("Part 1: {}", result1);
     println!("Part 2: {}", result2);
@@ -118,7 +118,7 @@ fn main() -> util::Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let map = input.clone();
     let start_node = map
@@ -2,6 +2,8 @@ use core::fmt;
 use core::str::FromStr;
 
 use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::env;
 use std::io;
 
 use nom::branch::alt;
@@ -19,10 +21,12 @@ use nom::IResult;
 
 use lowdim::p2d;
 use lowdim::v2d;
+use lowdim::v3d;
 use lowdim::Array2d;
 use lowdim::BBox2d;
 use lowdim::Point2d;
 use lowdim::Vec2d;
+use lowdim::Vec3d;
 
 use util::runtime_error;
 
@@ -219,142 +223,263 @@ impl State {
     }
 }
 
-fn main() -> util::Result<()> {
-    let input_data = io::read_to_string(io::stdin())?;
-
-    // parse input
-    let result = input(&input_data).map_err(|e| e.to_owned())?;
+fn dirs() -> [Vec2d; 4] {
+    [v2d(1, 0), v2d(0, 1), v2d(-1, 0), v2d(0, -1)]
+}
 
-    let input = result.1;
-    // println!("{}", input);
+fn dir_name(v: Vec2d) -> char {
+    match facing(v) {
+        Ok(0) => 'E',
+        Ok(1) => 'S',
+        Ok(2) => 'W',
+        Ok(3) => 'N',
+        _ => '?',
+    }
+}
 
-    let mut state = State::start(&input.map)?;
-    let map = &input.map.map;
-    for instruction in &input.path.instructions {
-        match instruction {
-            Instruction::Forward(n) => {
-                let mut next_pos = state.pos;
-                let mut steps = 0;
-                while steps < *n {
-                    next_pos = (next_pos + state.dir) % map.bbox();
-                    match map[next_pos] {
-                        Square::None => {
-                            // Move over this square, it doesn't exist.
-                        }
-                        Square::Open => {
-                            // We made a step.
-                            state.pos = next_pos;
-                            steps += 1;
-                        }
-                        Square::Wall => {
-                            // We hit a wall, stay where we are and finish.
-                            break;
-                        }
-                    }
-                }
-            }
-            Instruction::Left => {
-                state.rotate_left();
-            }
-            Instruction::Right => {
-                state.rotate_right();
-            }
+/// A face of the cube with its orientation in space.
+#[derive(Clone, Copy, Debug)]
+struct Face {
+    /// The position of the face in the net, in units of the face size.
+    pos: Point2d,
+    /// The directions in space of moving right and down on the map.
+    right: Vec3d,
+    down: Vec3d,
+    /// The direction in space in which the face points outward.
+    normal: Vec3d,
+}
+impl Face {
+    fn new(pos: Point2d) -> Face {
+        Face {
+            pos,
+            right: v3d(1, 0, 0),
+            down: v3d(0, 1, 0),
+            normal: v3d(0, 0, 1),
         }
     }
-    let result1 = state.password()?;
+    /// Returns the direction in space of a direction on the map.
+    fn dir_3d(&self, dir: Vec2d) -> Vec3d {
+        dir.x() * self.right + dir.y() * self.down
+    }
+    /// Returns the neighboring face in the net, folded over the edge in direction `dir`.
+    fn fold(&self, dir: Vec2d) -> Face {
+        // The neighbor points outward in the direction we move to,
+        // and moving on in this direction goes inward.
+        let pos = self.pos + dir;
+        let normal = self.dir_3d(dir);
+        let (right, down) = if dir.x() != 0 {
+            (-dir.x() * self.normal, self.down)
+        } else {
+            (self.right, -dir.y() * self.normal)
+        };
+        Face {
+            pos,
+            right,
+            down,
+            normal,
+        }
+    }
+}
+
+/// An edge of a face, identified by the direction in which it is crossed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Edge {
+    face: usize,
+    dir: Vec2d,
+}
 
-    // Shape of the cube surface:
-    //
-    //   0 1 2 3
-    // 0   aCbDc
-    //     A#|#E
-    // 1   d-eFf
-    //     B#F
-    // 2 gBh-i
-    //   A#|#E
-    // 3 j-kGl
-    //   C#G
-    // 4 mDn
+/// Two edges which are glued together when folding the net.
+///
+/// If `reversed` is set the squares along the edges are glued in opposite order.
+#[derive(Clone, Copy, Debug)]
+struct Gluing {
+    edge0: Edge,
+    edge1: Edge,
+    reversed: bool,
+}
 
-    let mut state = State::start(&input.map)?;
-    let mut jumps = HashMap::new();
-    // The origin.
-    let po = p2d(0_i64, 0_i64);
-    // Corner points, need to be scaled up.
-    let (pa, pb, pc) = (p2d(1, 0), p2d(2, 0), p2d(3, 0));
-    let (pd, pe, pf) = (p2d(1, 1), p2d(2, 1), p2d(3, 1));
-    let (pg, ph, pi) = (p2d(0, 2), p2d(1, 2), p2d(2, 2));
-    let (pj, pk, pl) = (p2d(0, 3), p2d(1, 3), p2d(2, 3));
-    let (pm, pn) = (p2d(0, 4), p2d(1, 4));
-    // Directions
-    let dir_e = v2d(1, 0);
-    let dir_n = v2d(0, -1);
-    let dir_w = v2d(-1, 0);
-    let dir_s = v2d(0, 1);
-
-    // p00 is glued to p10, and p01 to p11.
-    // The direction is the direction across the edge.
-    for ((p00, p01, dir0), (p10, p11, dir1)) in vec![
-        ((pa, pd, dir_w), (pj, pg, dir_w)), // edge A
-        ((pd, ph, dir_w), (pg, ph, dir_n)), // edge B
-        ((pa, pb, dir_n), (pj, pm, dir_w)), // edge C
-        ((pb, pc, dir_n), (pm, pn, dir_s)), // edge D
-        ((pc, pf, dir_e), (pl, pi, dir_e)), // edge E
-        ((pe, pf, dir_s), (pe, pi, dir_e)), // edge F
-        ((pk, pl, dir_s), (pk, pn, dir_e)), // edge G
-    ] {
-        // Compute start and step for side 0 of the edge.
-
-        // Scale up the first point.
-        let mut p0 = po + 50 * (p00 - po);
-        if dir0.x() > 0 || dir0.y() > 0 {
-            // Correct right and bottom edges.
-            // Move back to a real square as the jump-off point.
-            p0 -= dir0;
+#[derive(Clone, Debug)]
+struct Cube {
+    /// The side length of a face.
+    size: i64,
+    faces: Vec<Face>,
+    gluings: Vec<Gluing>,
+}
+impl Cube {
+    /// Detects the faces of the net on the map and folds them into a cube.
+    fn fold(map: &Map) -> util::Result<Cube> {
+        let bbox = map.bbox();
+        let area = bbox.iter().filter(|&p| map.map[p] != Square::None).count() as i64;
+        let size = (1..)
+            .find(|s| 6 * s * s >= area)
+            .ok_or_else(|| runtime_error!("map is empty"))?;
+        if 6 * size * size != area {
+            return Err(runtime_error!("area {} is not that of a cube", area));
         }
-        let v0 = p01 - p00;
-        if v0.x() < 0 || v0.y() < 0 {
-            // Correct for reversed direction of an edge.
-            // Go one step along the edge so that offset 49 maps to zero
-            // and vice-versa.
-            p0 += v0;
+
+        let mut face_positions = Vec::new();
+        for p in bbox.iter() {
+            if p.x() % size == 0 && p.y() % size == 0 && map.map[p] != Square::None {
+                face_positions.push(p2d(p.x() / size, p.y() / size));
+            }
+        }
+        if face_positions.len() != 6 {
+            return Err(runtime_error!(
+                "found {} faces of size {} instead of 6",
+                face_positions.len(),
+                size
+            ));
         }
 
-        // Compute start and step for side 1.
-        let mut p1 = po + 50 * (p10 - po);
-        if dir1.x() > 0 || dir1.y() > 0 {
-            p1 -= dir1;
+        // Fold the net by a breadth-first search over its faces.
+        let mut faces = HashMap::new();
+        let mut queue = VecDeque::new();
+        let start_face = Face::new(face_positions[0]);
+        faces.insert(start_face.pos, start_face);
+        queue.push_back(start_face);
+        while let Some(face) = queue.pop_front() {
+            for dir in dirs() {
+                let new_pos = face.pos + dir;
+                if face_positions.contains(&new_pos) && !faces.contains_key(&new_pos) {
+                    let new_face = face.fold(dir);
+                    faces.insert(new_pos, new_face);
+                    queue.push_back(new_face);
+                }
+            }
         }
-        let v1 = p11 - p10;
-        if v1.x() < 0 || v1.y() < 0 {
-            p1 += v1;
+        let faces = face_positions
+            .iter()
+            .map(|pos| faces.get(pos).copied())
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(|| runtime_error!("faces are not connected"))?;
+        for (i, f0) in faces.iter().enumerate() {
+            for f1 in &faces[(i + 1)..] {
+                if f0.normal == f1.normal {
+                    return Err(runtime_error!("net does not fold into a cube"));
+                }
+            }
         }
 
-        for i in 0..50 {
-            // Jump from side 0 to side 1.
-            let state_in = State {
-                pos: p0 + i * v0,
-                dir: dir0,
-            };
-            let state_out = State {
-                pos: p1 + i * v1,
-                dir: -dir1,
-            };
-            jumps.insert(state_in, state_out);
-
-            // Jump from side 1 to side 0.
-            let state_in = State {
-                pos: p1 + i * v1,
-                dir: dir1,
-            };
-            let state_out = State {
-                pos: p0 + i * v0,
-                dir: -dir0,
-            };
-            jumps.insert(state_in, state_out);
+        // Glue the edges which are not connected in the net.
+        let mut gluings = Vec::new();
+        for (face0, f0) in faces.iter().enumerate() {
+            for dir0 in dirs() {
+                if face_positions.contains(&(f0.pos + dir0)) {
+                    continue;
+                }
+                let normal1 = f0.dir_3d(dir0);
+                let face1 = faces
+                    .iter()
+                    .position(|f| f.normal == normal1)
+                    .ok_or_else(|| runtime_error!("no face adjacent to an edge"))?;
+                if face0 > face1 {
+                    // Each pair of edges is only glued once.
+                    continue;
+                }
+                let f1 = &faces[face1];
+                let dir1 = dirs()
+                    .into_iter()
+                    .find(|&d| f1.dir_3d(d) == f0.normal)
+                    .ok_or_else(|| runtime_error!("no matching edge"))?;
+                let reversed = f0.dir_3d(tangent(dir0)) != f1.dir_3d(tangent(dir1));
+                gluings.push(Gluing {
+                    edge0: Edge {
+                        face: face0,
+                        dir: dir0,
+                    },
+                    edge1: Edge {
+                        face: face1,
+                        dir: dir1,
+                    },
+                    reversed,
+                });
+            }
         }
+
+        Ok(Cube {
+            size,
+            faces,
+            gluings,
+        })
+    }
+    /// Returns the position of the square at offset `t` along an edge.
+    fn edge_pos(&self, edge: Edge, t: i64) -> Point2d {
+        let s1 = self.size - 1;
+        let face = &self.faces[edge.face];
+        let origin = p2d(face.pos.x() * self.size, face.pos.y() * self.size);
+        let start = origin
+            + v2d(
+                if edge.dir.x() > 0 { s1 } else { 0 },
+                if edge.dir.y() > 0 { s1 } else { 0 },
+            );
+        start + t * tangent(edge.dir)
     }
+    /// Returns the jumps over the glued edges, in both directions.
+    fn jumps(&self) -> HashMap<State, State> {
+        let mut jumps = HashMap::new();
+        for g in &self.gluings {
+            for t0 in 0..self.size {
+                let t1 = if g.reversed { self.size - 1 - t0 } else { t0 };
+                let p0 = self.edge_pos(g.edge0, t0);
+                let p1 = self.edge_pos(g.edge1, t1);
+                jumps.insert(
+                    State {
+                        pos: p0,
+                        dir: g.edge0.dir,
+                    },
+                    State {
+                        pos: p1,
+                        dir: -g.edge1.dir,
+                    },
+                );
+                jumps.insert(
+                    State {
+                        pos: p1,
+                        dir: g.edge1.dir,
+                    },
+                    State {
+                        pos: p0,
+                        dir: -g.edge0.dir,
+                    },
+                );
+            }
+        }
+        jumps
+    }
+}
+impl fmt::Display for Cube {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "face size {}", self.size)?;
+        for g in &self.gluings {
+            let f0 = &self.faces[g.edge0.face];
+            let f1 = &self.faces[g.edge1.face];
+            writeln!(
+                f,
+                "face {} ({}, {}) {} <-> face {} ({}, {}) {}{}",
+                g.edge0.face,
+                f0.pos.x(),
+                f0.pos.y(),
+                dir_name(g.edge0.dir),
+                g.edge1.face,
+                f1.pos.x(),
+                f1.pos.y(),
+                dir_name(g.edge1.dir),
+                if g.reversed { " reversed" } else { "" },
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the direction along an edge which is crossed in direction `dir`.
+fn tangent(dir: Vec2d) -> Vec2d {
+    v2d(dir.y().abs(), dir.x().abs())
+}
 
+fn walk_on_cube(input: &Input, cube: &Cube) -> util::Result<State> {
+    let map = &input.map.map;
+    let jumps = cube.jumps();
+    let mut state = State::start(&input.map)?;
     for instruction in &input.path.instructions {
         match instruction {
             Instruction::Forward(n) => {
@@ -392,6 +517,58 @@ fn main() -> util::Result<()> {
             }
         }
     }
+    Ok(state)
+}
+
+fn main() -> util::Result<()> {
+    let input_data = io::read_to_string(io::stdin())?;
+
+    // parse input
+    let result = input(&input_data).map_err(|e| e.to_owned())?;
+
+    let input = result.1;
+    util::validate(&input_data, result.0, &input);
+
+    let mut state = State::start(&input.map)?;
+    let map = &input.map.map;
+    for instruction in &input.path.instructions {
+        match instruction {
+            Instruction::Forward(n) => {
+                let mut next_pos = state.pos;
+                let mut steps = 0;
+                while steps < *n {
+                    next_pos = (next_pos + state.dir) % map.bbox();
+                    match map[next_pos] {
+                        Square::None => {
+                            // Move over this square, it doesn't exist.
+                        }
+                        Square::Open => {
+                            // We made a step.
+                            state.pos = next_pos;
+                            steps += 1;
+                        }
+                        Square::Wall => {
+                            // We hit a wall, stay where we are and finish.
+                            break;
+                        }
+                    }
+                }
+            }
+            Instruction::Left => {
+                state.rotate_left();
+            }
+            Instruction::Right => {
+                state.rotate_right();
+            }
+        }
+    }
+    let result1 = state.password()?;
+
+    let cube = Cube::fold(&input.map)?;
+    if env::args().any(|arg| arg == "--gluing") {
+        print!("{}", cube);
+    }
+    let state = walk_on_cube(&input, &cube)?;
     let result2 = state.password()?;
 
     println!("Part 1: {}", result1);
@@ -402,11 +579,115 @@ fn main() -> util::Result<()> {
 
 #[cfg(test)]
 mod tests {
+    use lowdim::p2d;
     use lowdim::v2d;
+    use lowdim::Array2d;
+    use lowdim::Point2d;
+    use lowdim::Vec3d;
 
     use util::Result;
 
+    use crate::dirs;
     use crate::facing;
+    use crate::input;
+    use crate::walk_on_cube;
+    use crate::Cube;
+    use crate::Face;
+    use crate::Map;
+    use crate::Square;
+    use crate::State;
+
+    const EXAMPLE: &str = concat!(
+        "        ...#\n",
+        "        .#..\n",
+        "        #...\n",
+        "        ....\n",
+        "...#.......#\n",
+        "........#...\n",
+        "..#....#....\n",
+        "..........#.\n",
+        "        ...#....\n",
+        "        .....#..\n",
+        "        .#......\n",
+        "        ......#.\n",
+        "\n",
+        "10R5L5R10L4R5L5\n",
+    );
+
+    /// The eleven nets of the cube, with one character per face.
+    const NETS: [&[&str]; 11] = [
+        &["#   ", "####", "#   "],
+        &["#   ", "####", " #  "],
+        &["#   ", "####", "  # "],
+        &["#   ", "####", "   #"],
+        &[" #  ", "####", " #  "],
+        &[" #  ", "####", "  # "],
+        &["##  ", " ###", " #  "],
+        &["##  ", " ###", "  # "],
+        &["##  ", " ###", "   #"],
+        &["##  ", " ## ", "  ##"],
+        &["###  ", "  ###"],
+    ];
+
+    /// Returns the face positions of a net in all its rotations and reflections.
+    fn layouts(net: &[&str]) -> Vec<Vec<Point2d>> {
+        let mut faces = Vec::new();
+        for (y, row) in net.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    faces.push(p2d(x as i64, y as i64));
+                }
+            }
+        }
+        let mut result = Vec::new();
+        for _ in 0..2 {
+            for _ in 0..4 {
+                faces = faces.iter().map(|p| p2d(p.y(), -p.x())).collect();
+                let x_min = faces.iter().map(|p| p.x()).min().unwrap();
+                let y_min = faces.iter().map(|p| p.y()).min().unwrap();
+                faces = faces
+                    .iter()
+                    .map(|p| p2d(p.x() - x_min, p.y() - y_min))
+                    .collect();
+                result.push(faces.clone());
+            }
+            faces = faces.iter().map(|p| p2d(-p.x(), p.y())).collect();
+        }
+        result
+    }
+
+    /// Returns a map with open squares on the faces of the given size.
+    fn net_map(faces: &[Point2d], size: i64) -> Map {
+        let width = faces.iter().map(|p| p.x()).max().unwrap() + 1;
+        let height = faces.iter().map(|p| p.y()).max().unwrap() + 1;
+        let rows = (0..(height * size))
+            .map(|y| {
+                (0..(width * size))
+                    .map(|x| {
+                        if faces.contains(&p2d(x / size, y / size)) {
+                            Square::Open
+                        } else {
+                            Square::None
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        Map::new(Array2d::from_vec(rows))
+    }
+
+    /// Returns the face of a square and the center of the square in space,
+    /// where the cube extends from `-size` to `size` in each coordinate.
+    fn center_3d(cube: &Cube, pos: Point2d) -> (&Face, Vec3d) {
+        let face_pos = p2d(pos.x() / cube.size, pos.y() / cube.size);
+        let face = cube.faces.iter().find(|f| f.pos == face_pos).unwrap();
+        let u = 2 * (pos.x() % cube.size) + 1 - cube.size;
+        let v = 2 * (pos.y() % cube.size) + 1 - cube.size;
+        (
+            face,
+            cube.size * face.normal + u * face.right + v * face.down,
+        )
+    }
 
     #[test]
     fn test_facing() -> util::Result<()> {
@@ -416,4 +697,78 @@ mod tests {
         assert_eq!(3, facing(v2d(0, -1))?);
         Ok(())
     }
+
+    #[test]
+    fn test_example_cube() -> Result<()> {
+        let (_, input) = input(EXAMPLE).map_err(|e| e.to_owned())?;
+        let cube = Cube::fold(&input.map)?;
+        assert_eq!(4, cube.size);
+        assert_eq!(7, cube.gluings.len());
+        assert_eq!(5031, walk_on_cube(&input, &cube)?.password()?);
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_nets() -> Result<()> {
+        for net in NETS {
+            for faces in layouts(net) {
+                for size in [1, 2, 5] {
+                    let map = net_map(&faces, size);
+                    let cube = Cube::fold(&map)?;
+                    assert_eq!(size, cube.size);
+
+                    // Each edge on the boundary of the net is glued exactly once.
+                    let mut boundary = Vec::new();
+                    for (i, face) in cube.faces.iter().enumerate() {
+                        for dir in dirs() {
+                            if !faces.contains(&(face.pos + dir)) {
+                                boundary.push((i, facing(dir)?));
+                            }
+                        }
+                    }
+                    let mut glued = Vec::new();
+                    for g in &cube.gluings {
+                        for edge in [g.edge0, g.edge1] {
+                            glued.push((edge.face, facing(edge.dir)?));
+                        }
+                    }
+                    boundary.sort();
+                    glued.sort();
+                    assert_eq!(14, boundary.len());
+                    assert_eq!(boundary, glued);
+
+                    // Walking off an edge lands on the map, and turning around
+                    // and walking back returns to the start.
+                    let jumps = cube.jumps();
+                    assert_eq!(14 * size as usize, jumps.len());
+                    for (from, to) in &jumps {
+                        assert_eq!(
+                            None,
+                            map.map
+                                .get(from.pos + from.dir)
+                                .filter(|&&s| s != Square::None)
+                        );
+                        assert_eq!(Some(&Square::Open), map.map.get(to.pos));
+                        let back = State {
+                            pos: to.pos,
+                            dir: -to.dir,
+                        };
+                        let expected = State {
+                            pos: from.pos,
+                            dir: -from.dir,
+                        };
+                        assert_eq!(Some(&expected), jumps.get(&back));
+
+                        // In space both squares touch the same point of the glued edge,
+                        // and we move away from the edge we came from.
+                        let (face0, center0) = center_3d(&cube, from.pos);
+                        let (face1, center1) = center_3d(&cube, to.pos);
+                        assert_eq!(center0 + face0.dir_3d(from.dir), center1 + face0.normal);
+                        assert_eq!(-face0.normal, face1.dir_3d(to.dir));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
 }
@@ -196,7 +196,7 @@ fn main() -> util::Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut state = State::new(&input.map);
     for _ in 0..10 {
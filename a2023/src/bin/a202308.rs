@@ -123,7 +123,7 @@ impl fmt::Display for Input {
         }
         writeln!(f)?;
         writeln!(f)?;
-        writeln!(f, "{}", self.map)?;
+        write!(f, "{}", self.map)?;
         Ok(())
     }
 }
@@ -238,7 +238,7 @@ fn main() -> util::Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut instructions = input.instructions.iter().copied().cycle();
 
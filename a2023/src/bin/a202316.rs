@@ -172,7 +172,7 @@ fn main() -> util::Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     // for y in input.bbox().y_range() {
     //     for x in input.bbox().x_range() {
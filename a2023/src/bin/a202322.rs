@@ -97,7 +97,7 @@ fn main() -> util::Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut bricks = input.bricks.clone();
     let bbox = BBox3d::enclosing(bricks.iter().flat_map(|b| once(&b.p0).chain(once(&b.p1))))
@@ -5,100 +5,50 @@ use core::hash::Hasher;
 use std::collections::HashSet;
 use std::io;
 
-use nom::branch::alt;
-use nom::character::complete::char;
-use nom::character::complete::line_ending;
-use nom::combinator::value;
-use nom::multi::many1;
-use nom::multi::separated_list1;
 use nom::IResult;
 
 use pathfinding::prelude::astar;
 use pathfinding::prelude::dijkstra_all;
 
-use lowdim::p2d;
 use lowdim::v2d;
-use lowdim::Array2d;
-use lowdim::BBox2d;
 use lowdim::Point2d;
 use lowdim::Vec2d;
 
+use util::parse::parse_input;
+use util::CharGrid;
+
 type Error = Box<dyn std::error::Error>;
 type Result<T> = std::result::Result<T, Error>;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Square {
-    Empty,
-    Wall,
-    Start,
-    Goal,
-}
-impl Square {
-    fn to_char(self) -> char {
-        match self {
-            Square::Empty => '.',
-            Square::Wall => '#',
-            Square::Start => 'S',
-            Square::Goal => 'E',
-        }
-    }
-}
-impl fmt::Display for Square {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_char())
+util::grid_cell! {
+    enum Square {
+        Empty => '.',
+        Wall => '#',
+        Start => 'S',
+        Goal => 'E',
     }
 }
 
-fn square(i: &str) -> IResult<&str, Square> {
-    alt((
-        value(Square::Empty, char('.')),
-        value(Square::Wall, char('#')),
-        value(Square::Start, char('S')),
-        value(Square::Goal, char('E')),
-    ))(i)
-}
-
 #[derive(Clone, Debug)]
 struct Input {
-    map: Array2d<i64, Square>,
+    map: CharGrid<Square>,
 }
 impl Input {
     fn start_pos(&self) -> Result<Point2d> {
-        Ok(self
-            .map
-            .bbox()
-            .iter()
-            .find(|&p| self.map[p] == Square::Start)
-            .ok_or("start not found")?)
+        self.map.find_unique(Square::Start)
     }
     fn goal_pos(&self) -> Result<Point2d> {
-        Ok(self
-            .map
-            .bbox()
-            .iter()
-            .find(|&p| self.map[p] == Square::Goal)
-            .ok_or("start not found")?)
-    }
-    fn bbox(&self) -> BBox2d {
-        self.map.bbox()
+        self.map.find_unique(Square::Goal)
     }
 }
 impl fmt::Display for Input {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for y in self.bbox().y_range() {
-            for x in self.bbox().x_range() {
-                let p = p2d(x, y);
-                write!(f, "{}", self.map[p])?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        write!(f, "{}", self.map)
     }
 }
 
 fn input(i: &str) -> IResult<&str, Input> {
-    let (i, lines) = separated_list1(line_ending, many1(square))(i)?;
-    let map = Array2d::from_vec(lines);
+    let (i, map) = CharGrid::parse(i)?;
     Ok((i, Input { map }))
 }
 
@@ -201,10 +151,8 @@ fn main() -> Result<()> {
     let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
-    let result = input(&input_data).map_err(|e| e.to_owned())?;
-
-    let input = result.1;
-    // println!("{}", input);
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
 
     let start_node = Node::start(&input);
     let search_result = astar(
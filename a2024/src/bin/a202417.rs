@@ -85,7 +85,7 @@ struct Input {
 impl fmt::Display for Input {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         writeln!(f, "{}", self.registers)?;
-        writeln!(f, "{}", self.program)?;
+        write!(f, "{}", self.program)?;
         Ok(())
     }
 }
@@ -237,7 +237,7 @@ fn main() -> Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut state = State::init(input.registers);
     state.run(&input.program)?;
@@ -53,6 +53,7 @@ impl fmt::Display for Input {
             sep = ", ";
         }
         writeln!(f)?;
+        writeln!(f)?;
         for d in &self.designs {
             writeln!(f, "{}", d)?;
         }
@@ -75,7 +76,7 @@ fn main() -> Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut count = 0;
     for design in &input.designs {
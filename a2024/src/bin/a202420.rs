@@ -155,7 +155,7 @@ fn main() -> Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     // Cost from the start to all track positions.
     let start_node_costs = dijkstra_all(&Node::start(&input), Node::successors);
@@ -2,33 +2,18 @@ use std::collections::HashSet;
 use std::fmt;
 use std::io;
 
-use nom::branch::alt;
-use nom::character::complete::char;
-use nom::character::complete::line_ending;
-use nom::combinator::value;
-use nom::multi::many1;
-use nom::multi::separated_list1;
 use nom::IResult;
 
-use lowdim::p2d;
-use lowdim::Array2d;
 use lowdim::BBox2d;
 use lowdim::Point2d;
 
-type Error = Box<dyn std::error::Error>;
-type Result<T> = std::result::Result<T, Error>;
+use util::parse::parse_input;
+use util::CharGrid;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Square {
-    Empty,
-    Paper,
-}
-impl Square {
-    fn to_char(self) -> char {
-        match self {
-            Square::Empty => '.',
-            Square::Paper => '@',
-        }
+util::grid_cell! {
+    enum Square {
+        Empty => '.',
+        Paper => '@',
     }
 }
 impl Square {
@@ -36,22 +21,10 @@ impl Square {
         *self == Square::Paper
     }
 }
-impl fmt::Display for Square {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_char())
-    }
-}
-
-fn square(i: &str) -> IResult<&str, Square> {
-    alt((
-        value(Square::Empty, char('.')),
-        value(Square::Paper, char('@')),
-    ))(i)
-}
 
 #[derive(Clone, Debug)]
 struct Input {
-    map: Array2d<i64, Square>,
+    map: CharGrid<Square>,
 }
 impl Input {
     fn bbox(&self) -> BBox2d {
@@ -78,31 +51,21 @@ impl Input {
 }
 impl fmt::Display for Input {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for y in self.bbox().y_range() {
-            for x in self.bbox().x_range() {
-                let p = p2d(x, y);
-                write!(f, "{}", self.map[p])?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        write!(f, "{}", self.map)
     }
 }
 
 fn input(i: &str) -> IResult<&str, Input> {
-    let (i, lines) = separated_list1(line_ending, many1(square))(i)?;
-    let map = Array2d::from_vec(lines);
+    let (i, map) = CharGrid::parse(i)?;
     Ok((i, Input { map }))
 }
 
-fn main() -> Result<()> {
+fn main() -> util::Result<()> {
     let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
-    let result = input(&input_data).map_err(|e| e.to_owned())?;
-
-    let input = result.1;
-    // println!("{}", input);
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
 
     let result1 = input
         .bbox()
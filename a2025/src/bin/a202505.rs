@@ -1,23 +1,14 @@
-use core::str::FromStr;
-
 use std::fmt;
 use std::fmt::Display;
 use std::io;
 
 use nom::character::complete::char;
-use nom::character::complete::digit1;
 use nom::character::complete::line_ending;
-use nom::combinator::map_res;
-use nom::combinator::recognize;
-use nom::multi::separated_list1;
 use nom::IResult;
 
-type Error = Box<dyn std::error::Error>;
-type Result<T> = std::result::Result<T, Error>;
-
-fn uint(i: &str) -> IResult<&str, i64> {
-    map_res(recognize(digit1), FromStr::from_str)(i)
-}
+use util::parse::lines;
+use util::parse::parse_input;
+use util::parse::uint;
 
 #[derive(Clone, Copy, Debug)]
 struct IdRange {
@@ -71,10 +62,10 @@ impl Display for Input {
 }
 
 fn input(i: &str) -> IResult<&str, Input> {
-    let (i, fresh_ranges) = separated_list1(line_ending, id_range)(i)?;
+    let (i, fresh_ranges) = lines(id_range)(i)?;
     let (i, _) = line_ending(i)?;
     let (i, _) = line_ending(i)?;
-    let (i, ingredients) = separated_list1(line_ending, uint)(i)?;
+    let (i, ingredients) = lines(uint)(i)?;
     Ok((
         i,
         Input {
@@ -84,14 +75,12 @@ fn input(i: &str) -> IResult<&str, Input> {
     ))
 }
 
-fn main() -> Result<()> {
+fn main() -> util::Result<()> {
     let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
-    let result = input(&input_data).map_err(|e| e.to_owned())?;
-
-    let input = result.1;
-    // println!("{}", input);
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
 
     let result1 = input
         .ingredients
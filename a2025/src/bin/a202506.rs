@@ -244,7 +244,7 @@ fn main() -> Result<()> {
     let result = input(&input_data).map_err(|e| e.to_owned())?;
 
     let input = result.1;
-    // println!("{}", input);
+    util::validate(&input_data, result.0, &input);
 
     let mut acc = input.ops().iter().map(Op::unit).collect::<Vec<_>>();
     for row in &input.row_numbers() {
@@ -4,35 +4,19 @@ use std::fmt;
 use std::io;
 
 use lowdim::v2d;
-use nom::branch::alt;
-use nom::character::complete::char;
-use nom::character::complete::line_ending;
-use nom::combinator::value;
-use nom::multi::many1;
-use nom::multi::separated_list1;
 use nom::IResult;
 
-use lowdim::p2d;
-use lowdim::Array2d;
 use lowdim::BBox2d;
 use lowdim::Point2d;
 
-type Error = Box<dyn std::error::Error>;
-type Result<T> = std::result::Result<T, Error>;
+use util::parse::parse_input;
+use util::CharGrid;
 
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
-enum Square {
-    Empty,
-    Splitter,
-    Start,
-}
-impl Square {
-    fn to_char(self) -> char {
-        match self {
-            Square::Empty => '.',
-            Square::Splitter => '^',
-            Square::Start => 'S',
-        }
+util::grid_cell! {
+    enum Square {
+        Empty => '.',
+        Splitter => '^',
+        Start => 'S',
     }
 }
 impl Square {
@@ -40,27 +24,14 @@ impl Square {
         *self == Square::Splitter
     }
 }
-impl fmt::Display for Square {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "{}", self.to_char())
-    }
-}
-
-fn square(i: &str) -> IResult<&str, Square> {
-    alt((
-        value(Square::Empty, char('.')),
-        value(Square::Splitter, char('^')),
-        value(Square::Start, char('S')),
-    ))(i)
-}
 
 #[derive(Clone, Debug)]
 struct Input {
-    map: Array2d<i64, Square>,
+    map: CharGrid<Square>,
 }
 impl Input {
-    fn start_pos(&self) -> Option<Point2d> {
-        self.bbox().iter().find(|&p| self.map[p] == Square::Start)
+    fn start_pos(&self) -> util::Result<Point2d> {
+        self.map.find_unique(Square::Start)
     }
     fn bbox(&self) -> BBox2d {
         self.map.bbox()
@@ -75,37 +46,27 @@ impl Input {
 }
 impl fmt::Display for Input {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        for y in self.bbox().y_range() {
-            for x in self.bbox().x_range() {
-                let p = p2d(x, y);
-                write!(f, "{}", self.map[p])?;
-            }
-            writeln!(f)?;
-        }
-        Ok(())
+        write!(f, "{}", self.map)
     }
 }
 
 fn input(i: &str) -> IResult<&str, Input> {
-    let (i, lines) = separated_list1(line_ending, many1(square))(i)?;
-    let map = Array2d::from_vec(lines);
+    let (i, map) = CharGrid::parse(i)?;
     Ok((i, Input { map }))
 }
 
-fn main() -> Result<()> {
+fn main() -> util::Result<()> {
     let input_data = io::read_to_string(io::stdin())?;
 
     // parse input
-    let result = input(&input_data).map_err(|e| e.to_owned())?;
-
-    let input = result.1;
-    // println!("{}", input);
+    let input = parse_input(&input_data, input)?;
+    util::validate(&input_data, "", &input);
 
     let down = v2d(0, 1);
     let down_left = v2d(-1, 1);
     let down_right = v2d(1, 1);
 
-    let start_pos = input.start_pos().ok_or("beam start not found")?;
+    let start_pos = input.start_pos()?;
 
     let mut beams = HashSet::new();
     beams.insert(start_pos);
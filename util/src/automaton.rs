@@ -0,0 +1,400 @@
+//! One-dimensional cellular automata with two states, like the plant pots.
+//!
+//! A row stores its live cells packed into bits, starting at the leftmost
+//! live cell, so it can grow in both directions and rows which differ
+//! only by a translation have the same bits.
+//! Evolving a row records the generations until a pattern repeats,
+//! after which any later generation is a translated copy of a recorded one.
+
+use core::fmt;
+
+use std::collections::HashMap;
+
+use nom::branch::alt;
+use nom::bytes::complete::tag;
+use nom::character::complete::char;
+use nom::character::complete::line_ending;
+use nom::combinator::value;
+use nom::multi::many1;
+use nom::IResult;
+
+use crate::parse::lines;
+use crate::Result;
+
+/// The maximal radius of a neighborhood, which limits the size of the rule table.
+pub const MAX_RADIUS: usize = 7;
+
+const WORD_BITS: i64 = 64;
+
+/// A neighborhood from left to right, and whether it leads to a live cell.
+pub type Rule = (Vec<bool>, bool);
+
+/// A row of cells, of which finitely many are alive.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Row {
+    /// The position of the leftmost live cell, or 0 if there is none.
+    start: i64,
+    /// Bit `k` of word `k / 64` is set if cell `start + k` is alive.
+    ///
+    /// The last word is nonzero, so there are no words if no cell is alive.
+    bits: Vec<u64>,
+}
+impl Row {
+    /// Creates a row where the given cells are alive.
+    pub fn new<I>(live_cells: I) -> Row
+    where
+        I: IntoIterator<Item = i64>,
+    {
+        let mut positions = live_cells.into_iter().collect::<Vec<_>>();
+        positions.sort();
+        let start = positions.first().copied().unwrap_or(0);
+        let mut bits = Vec::new();
+        for x in positions {
+            let k = x - start;
+            let w = (k / WORD_BITS) as usize;
+            if w >= bits.len() {
+                bits.resize(w + 1, 0);
+            }
+            bits[w] |= 1 << (k % WORD_BITS);
+        }
+        Row { start, bits }
+    }
+    /// Creates a row from the states of consecutive cells starting at `start`.
+    pub fn from_cells(start: i64, cells: &[bool]) -> Row {
+        Row::new((start..).zip(cells).filter(|&(_, &c)| c).map(|(x, _)| x))
+    }
+    /// Returns true if the cell is alive.
+    pub fn get(&self, x: i64) -> bool {
+        let k = x - self.start;
+        let w = k.div_euclid(WORD_BITS);
+        k >= 0
+            && (w as usize) < self.bits.len()
+            && (self.bits[w as usize] >> (k % WORD_BITS)) & 1 != 0
+    }
+    /// Returns the position of the leftmost live cell, if any.
+    pub fn first(&self) -> Option<i64> {
+        if self.bits.is_empty() {
+            None
+        } else {
+            Some(self.start)
+        }
+    }
+    /// Returns the position of the rightmost live cell, if any.
+    pub fn last(&self) -> Option<i64> {
+        let w = self.bits.last()?;
+        let k = (self.bits.len() as i64 - 1) * WORD_BITS
+            + (WORD_BITS - 1 - i64::from(w.leading_zeros()));
+        Some(self.start + k)
+    }
+    /// Returns the positions of the live cells from left to right.
+    pub fn live_cells(&self) -> impl Iterator<Item = i64> + '_ {
+        self.bits.iter().enumerate().flat_map(move |(w, &word)| {
+            (0..WORD_BITS)
+                .filter(move |k| (word >> k) & 1 != 0)
+                .map(move |k| self.start + w as i64 * WORD_BITS + k)
+        })
+    }
+    /// Returns the number of live cells.
+    pub fn count(&self) -> i64 {
+        self.bits.iter().map(|w| i64::from(w.count_ones())).sum()
+    }
+    /// Returns the sum of the positions of the live cells.
+    pub fn position_sum(&self) -> i64 {
+        self.live_cells().sum()
+    }
+    /// Returns the row moved to the right by `shift` cells.
+    pub fn translate(&self, shift: i64) -> Row {
+        let start = if self.bits.is_empty() {
+            0
+        } else {
+            self.start + shift
+        };
+        Row {
+            start,
+            bits: self.bits.clone(),
+        }
+    }
+}
+impl fmt::Display for Row {
+    /// Shows the cells from the leftmost to the rightmost live one.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let (Some(first), Some(last)) = (self.first(), self.last()) {
+            for x in first..=last {
+                write!(f, "{}", if self.get(x) { '#' } else { '.' })?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The rules of an automaton, where the next state of a cell is determined
+/// by its neighborhood of `radius` cells to each side.
+#[derive(Clone, Debug)]
+pub struct Automaton {
+    radius: usize,
+    /// The next state for each neighborhood, with the leftmost cell as the highest bit.
+    table: Vec<bool>,
+}
+impl Automaton {
+    /// Creates an automaton from the neighborhoods in which a cell is alive
+    /// in the next generation.
+    ///
+    /// Neighborhoods without a rule lead to a dead cell.
+    /// Since rows must stay finite, an empty neighborhood must not lead to a live cell.
+    pub fn new<I>(radius: usize, rules: I) -> Result<Automaton>
+    where
+        I: IntoIterator<Item = (Vec<bool>, bool)>,
+    {
+        if radius > MAX_RADIUS {
+            return Err(format!("radius {} is larger than {}", radius, MAX_RADIUS).into());
+        }
+        let width = 2 * radius + 1;
+        let mut table = vec![false; 1 << width];
+        for (neighborhood, alive) in rules {
+            if neighborhood.len() != width {
+                return Err(format!(
+                    "rule for {} cells in an automaton of radius {}",
+                    neighborhood.len(),
+                    radius
+                )
+                .into());
+            }
+            let index = neighborhood
+                .iter()
+                .fold(0, |index, &c| (index << 1) | usize::from(c));
+            table[index] = alive;
+        }
+        if table[0] {
+            return Err("empty neighborhood leads to infinitely many live cells".into());
+        }
+        Ok(Automaton { radius, table })
+    }
+    /// Creates an automaton whose radius is given by the length of the rules.
+    pub fn from_rules(rules: Vec<Rule>) -> Result<Automaton> {
+        let width = rules
+            .first()
+            .map_or(1, |(neighborhood, _)| neighborhood.len());
+        if width % 2 != 1 {
+            return Err(format!("rule for {} cells has no center", width).into());
+        }
+        Automaton::new(width / 2, rules)
+    }
+    pub fn radius(&self) -> usize {
+        self.radius
+    }
+    /// Computes the next generation.
+    pub fn step(&self, row: &Row) -> Row {
+        let (Some(first), Some(last)) = (row.first(), row.last()) else {
+            return Row::new([]);
+        };
+        let r = self.radius as i64;
+        let mask = self.table.len() - 1;
+        // The neighborhood of the cell left of the first one is empty.
+        let mut window = 0;
+        let mut live_cells = Vec::new();
+        for x in (first - r)..=(last + r) {
+            window = ((window << 1) | usize::from(row.get(x + r))) & mask;
+            if self.table[window] {
+                live_cells.push(x);
+            }
+        }
+        Row::new(live_cells)
+    }
+    /// Computes the generations of a row until a pattern repeats,
+    /// but at most `max_generations`.
+    pub fn evolve(&self, row: Row, max_generations: usize) -> Evolution {
+        let mut generations = HashMap::new();
+        let mut rows = vec![row];
+        loop {
+            let g = rows.len() - 1;
+            let row = &rows[g];
+            if let Some(&(g0, start0)) = generations.get(&row.bits) {
+                let repeat = Repeat {
+                    first: g0,
+                    period: g - g0,
+                    shift: row.start - start0,
+                };
+                rows.pop();
+                return Evolution {
+                    rows,
+                    repeat: Some(repeat),
+                };
+            }
+            if g == max_generations {
+                return Evolution { rows, repeat: None };
+            }
+            generations.insert(row.bits.clone(), (g, row.start));
+            let next = self.step(row);
+            rows.push(next);
+        }
+    }
+}
+
+/// A pattern which repeats, translated by `shift` cells every `period` generations.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Repeat {
+    /// The first generation of the repeating pattern.
+    pub first: usize,
+    pub period: usize,
+    pub shift: i64,
+}
+
+/// The generations of a row, up to a repetition if one was found.
+#[derive(Clone, Debug)]
+pub struct Evolution {
+    /// The rows of the generations which were computed, starting with generation 0.
+    rows: Vec<Row>,
+    repeat: Option<Repeat>,
+}
+impl Evolution {
+    pub fn repeat(&self) -> Option<Repeat> {
+        self.repeat
+    }
+    /// Returns the row of any generation, if it was computed or follows from a repetition.
+    pub fn row(&self, generation: u64) -> Option<Row> {
+        self.row_shift(generation)
+            .map(|(row, shift)| row.translate(shift))
+    }
+    /// Returns the sum of the positions of the live cells in any generation,
+    /// if it was computed or follows from a repetition.
+    pub fn position_sum(&self, generation: u64) -> Option<i64> {
+        self.row_shift(generation)
+            .map(|(row, shift)| row.position_sum() + row.count() * shift)
+    }
+    /// Returns a recorded row and the shift which turns it into the generation.
+    fn row_shift(&self, generation: u64) -> Option<(&Row, i64)> {
+        if let Some(row) = usize::try_from(generation)
+            .ok()
+            .and_then(|g| self.rows.get(g))
+        {
+            return Some((row, 0));
+        }
+        let repeat = self.repeat?;
+        let offset = generation - repeat.first as u64;
+        let period = repeat.period as u64;
+        let row = &self.rows[repeat.first + (offset % period) as usize];
+        let shift = i64::try_from(offset / period)
+            .ok()?
+            .checked_mul(repeat.shift)?;
+        Some((row, shift))
+    }
+}
+
+/// Parses cells shown as `#` for alive and `.` for dead.
+pub fn cells(i: &str) -> IResult<&str, Vec<bool>> {
+    many1(alt((value(true, char('#')), value(false, char('.')))))(i)
+}
+
+/// Parses a rule like `..#.# => #`.
+pub fn rule(i: &str) -> IResult<&str, Rule> {
+    let (i, neighborhood) = cells(i)?;
+    let (i, _) = tag(" => ")(i)?;
+    let (i, alive) = alt((value(true, char('#')), value(false, char('.'))))(i)?;
+    Ok((i, (neighborhood, alive)))
+}
+
+/// Parses the initial state and the rules in the notation of the plant pots.
+///
+/// The first cell of the initial state is at position 0.
+pub fn parse_pots(i: &str) -> IResult<&str, (Row, Vec<Rule>)> {
+    let (i, _) = tag("initial state: ")(i)?;
+    let (i, cells) = cells(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, _) = line_ending(i)?;
+    let (i, rules) = lines(rule)(i)?;
+    Ok((i, (Row::from_cells(0, &cells), rules)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::automaton::parse_pots;
+    use crate::automaton::Automaton;
+    use crate::automaton::Repeat;
+    use crate::automaton::Row;
+    use crate::parse::parse_input;
+
+    /// The rule which moves every cell one step to the right.
+    fn shift_right() -> Automaton {
+        Automaton::new(
+            1,
+            [
+                (vec![true, false, false], true),
+                (vec![true, true, false], true),
+                (vec![true, false, true], true),
+                (vec![true, true, true], true),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_row() {
+        let row = Row::from_cells(-3, &[false, true, true, false, true]);
+        assert_eq!(row.first(), Some(-2));
+        assert_eq!(row.last(), Some(1));
+        assert_eq!(row.live_cells().collect::<Vec<_>>(), vec![-2, -1, 1]);
+        assert_eq!(row.count(), 3);
+        assert_eq!(row.position_sum(), -2);
+        assert_eq!(row.to_string(), "##.#");
+        assert!(row.get(-1) && !row.get(0) && !row.get(-100) && !row.get(100));
+        assert_eq!(row.translate(3), Row::new([1, 2, 4]));
+
+        let wide = Row::new([-70, 0, 130]);
+        assert_eq!(wide.live_cells().collect::<Vec<_>>(), vec![-70, 0, 130]);
+        assert_eq!(wide.last(), Some(130));
+        assert_eq!(Row::new([]).last(), None);
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!(Automaton::new(1, [(vec![false, false, false], true)]).is_err());
+        assert!(Automaton::new(2, [(vec![true, false, false], true)]).is_err());
+    }
+
+    #[test]
+    fn test_parse_pots() {
+        let data = "initial state: #.##\n\n#.. => #\n... => .\n";
+        let (row, rules) = parse_input(data, parse_pots).unwrap();
+        assert_eq!(row, Row::new([0, 2, 3]));
+        let automaton = Automaton::from_rules(rules).unwrap();
+        assert_eq!(automaton.radius(), 1);
+        assert_eq!(automaton.step(&row), Row::new([4]));
+        assert!(Automaton::from_rules(vec![(vec![true, false], true)]).is_err());
+        assert_eq!(Automaton::from_rules(vec![]).unwrap().radius(), 0);
+    }
+
+    #[test]
+    fn test_glider() {
+        let automaton = shift_right();
+        let row = Row::new([0, 1, 3]);
+        assert_eq!(automaton.step(&row), Row::new([1, 2, 4]));
+        let evolution = automaton.evolve(row, 100);
+        assert_eq!(
+            evolution.repeat(),
+            Some(Repeat {
+                first: 0,
+                period: 1,
+                shift: 1
+            })
+        );
+        assert_eq!(evolution.row(1_000), Some(Row::new([1_000, 1_001, 1_003])));
+        assert_eq!(evolution.position_sum(1_000), Some(3_004));
+    }
+
+    #[test]
+    fn test_growth() {
+        // Rule 90 grows a Sierpinski triangle and never repeats.
+        let rules = [
+            (vec![true, false, false], true),
+            (vec![false, false, true], true),
+            (vec![true, true, false], true),
+            (vec![false, true, true], true),
+        ];
+        let automaton = Automaton::new(1, rules).unwrap();
+        let evolution = automaton.evolve(Row::new([0]), 8);
+        assert_eq!(evolution.repeat(), None);
+        assert_eq!(evolution.row(2), Some(Row::new([-2, 2])));
+        assert_eq!(evolution.row(8), Some(Row::new([-8, 8])));
+        assert_eq!(evolution.row(9), None);
+    }
+}
@@ -0,0 +1,177 @@
+//! Validates the parsers of all solutions that support it.
+//!
+//! Builds the workspace, then runs each instrumented solution with `AOC_VALIDATE`
+//! set on each of its data files and reports inputs that don't survive
+//! a round trip through the parser and `Display`.
+//! Solutions without the hook are listed as not validated on each of their data files,
+//! also if the other solution of the same day is instrumented.
+//! Instrumenting a solution is opt-in, so not all days are covered.
+//!
+//! Run it from anywhere in the workspace with
+//! ```
+//! cargo run --bin validate_parsers
+//! ```
+//! An optional argument restricts the check to solutions whose name starts with it,
+//! for example `a2023`.
+
+use std::env;
+use std::fs;
+use std::fs::File;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::process::Command;
+
+use util::validate::VALIDATE_VAR;
+use util::validate::VALIDATION_FAILED;
+
+/// Marks a solution calling the validation hook.
+const HOOK: &str = "util::validate(";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Outcome {
+    Ok,
+    Failed,
+    Error,
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .parent()
+        .expect("util is not in a workspace")
+        .to_path_buf()
+}
+
+/// Returns the source file of a solution, if it exists.
+fn source_path(root: &Path, name: &str) -> Option<PathBuf> {
+    let year = name.get(0..5)?;
+    let path = root
+        .join(year)
+        .join("src")
+        .join("bin")
+        .join(format!("{}.rs", name));
+    if path.exists() {
+        Some(path)
+    } else {
+        None
+    }
+}
+
+/// Returns the solutions for a data file, and whether each of them is instrumented.
+///
+/// The data file `a201507_test.txt` belongs to `a201507`,
+/// and `a201811.txt` belongs to both `a201811a` and `a201811b`.
+fn solutions_for(root: &Path, data_file: &Path) -> Vec<(String, bool)> {
+    let day = day_of(data_file);
+    [day.to_string(), format!("{}a", day), format!("{}b", day)]
+        .into_iter()
+        .filter_map(|name| {
+            let source = fs::read_to_string(source_path(root, &name)?).ok()?;
+            Some((name, source.contains(HOOK)))
+        })
+        .collect::<Vec<_>>()
+}
+
+/// Returns the day of a data file, like `a201507` for `a201507_test.txt`.
+fn day_of(data_file: &Path) -> &str {
+    let stem = data_file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or_default();
+    stem.split('_').next().unwrap_or_default()
+}
+
+fn data_files(root: &Path) -> util::Result<Vec<PathBuf>> {
+    let mut result = Vec::new();
+    for year_entry in fs::read_dir(root.join("data"))? {
+        let year_path = year_entry?.path();
+        if year_path.is_dir() {
+            for entry in fs::read_dir(year_path)? {
+                let path = entry?.path();
+                if path.extension().is_some_and(|e| e == "txt") {
+                    result.push(path);
+                }
+            }
+        }
+    }
+    result.sort();
+    Ok(result)
+}
+
+fn run(exe_dir: &Path, name: &str, data_file: &Path) -> util::Result<(Outcome, String)> {
+    let output = Command::new(exe_dir.join(name))
+        .env(VALIDATE_VAR, "1")
+        .stdin(File::open(data_file)?)
+        .output()?;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let outcome = match output.status.code() {
+        Some(0) => Outcome::Ok,
+        Some(VALIDATION_FAILED) => Outcome::Failed,
+        _ => Outcome::Error,
+    };
+    Ok((outcome, stdout + &stderr))
+}
+
+fn main() -> util::Result<()> {
+    let prefix = env::args().nth(1).unwrap_or_default();
+    let root = workspace_root();
+
+    let status = Command::new(env::var("CARGO").unwrap_or_else(|_| "cargo".to_string()))
+        .args(["build", "--release", "--workspace", "--bins"])
+        .current_dir(&root)
+        .status()?;
+    if !status.success() {
+        return Err(util::runtime_error!("build failed"));
+    }
+    let target_dir = env::var_os("CARGO_TARGET_DIR")
+        .map(PathBuf::from)
+        .unwrap_or_else(|| root.join("target"));
+    let exe_dir = target_dir.join("release");
+
+    let mut ok_count = 0;
+    let mut failed_count = 0;
+    let mut not_validated = Vec::new();
+    for data_file in data_files(&root)? {
+        let file_name = data_file.file_name().unwrap_or_default().to_string_lossy();
+        let solutions = solutions_for(&root, &data_file)
+            .into_iter()
+            .filter(|(name, _)| name.starts_with(&prefix))
+            .collect::<Vec<_>>();
+        if solutions.is_empty() {
+            // Also report data files without any solution, unless they are filtered out.
+            if day_of(&data_file).starts_with(&prefix) {
+                not_validated.push(file_name.into_owned());
+            }
+            continue;
+        }
+        for (name, instrumented) in solutions {
+            if !instrumented {
+                not_validated.push(format!("{} < {}", name, file_name));
+                continue;
+            }
+            let (outcome, output) = run(&exe_dir, &name, &data_file)?;
+            if outcome == Outcome::Ok {
+                ok_count += 1;
+            } else {
+                failed_count += 1;
+                println!("{} < {}: {:?}", name, file_name, outcome);
+                print!("{}", output);
+            }
+        }
+    }
+    for file_name in &not_validated {
+        println!("{}: not validated", file_name);
+    }
+    println!(
+        "{} ok, {} failed, {} not validated",
+        ok_count,
+        failed_count,
+        not_validated.len()
+    );
+
+    if failed_count > 0 {
+        process::exit(1);
+    }
+    Ok(())
+}
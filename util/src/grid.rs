@@ -0,0 +1,211 @@
+use core::fmt;
+use core::ops::Index;
+use core::ops::IndexMut;
+use core::str::FromStr;
+
+use nom::character::complete::none_of;
+use nom::combinator::map_opt;
+use nom::IResult;
+
+use lowdim::p2d;
+use lowdim::Array2d;
+use lowdim::BBox2d;
+use lowdim::Point2d;
+
+use crate::parse::grid_of;
+use crate::parse::parse_input;
+use crate::Error;
+use crate::Result;
+
+/// A cell of a map given as a grid of characters.
+pub trait GridCell: Copy + Eq {
+    /// Returns the cell for a character, or `None` if there is none.
+    fn from_char(c: char) -> Option<Self>;
+    fn to_char(self) -> char;
+}
+
+/// Defines an enum implementing `GridCell`, `TryFrom<char>` and `Display`.
+///
+/// ```
+/// util::grid_cell! {
+///     enum Square {
+///         Empty => '.',
+///         Wall => '#',
+///     }
+/// }
+/// ```
+#[macro_export]
+macro_rules! grid_cell {
+    (
+        $(#[$attr:meta])*
+        $vis:vis enum $name:ident {
+            $($(#[$variant_attr:meta])* $variant:ident => $c:literal),+ $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+        $vis enum $name {
+            $($(#[$variant_attr])* $variant),+
+        }
+        impl $crate::GridCell for $name {
+            fn from_char(c: char) -> Option<Self> {
+                match c {
+                    $($c => Some($name::$variant),)+
+                    _ => None,
+                }
+            }
+            fn to_char(self) -> char {
+                match self {
+                    $($name::$variant => $c),+
+                }
+            }
+        }
+        impl core::convert::TryFrom<char> for $name {
+            type Error = char;
+            fn try_from(c: char) -> core::result::Result<Self, char> {
+                <Self as $crate::GridCell>::from_char(c).ok_or(c)
+            }
+        }
+        impl core::fmt::Display for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+                write!(f, "{}", <Self as $crate::GridCell>::to_char(*self))
+            }
+        }
+    };
+}
+
+/// A rectangular map of cells which is parsed from and displayed as characters.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CharGrid<C> {
+    map: Array2d<i64, C>,
+}
+impl<C> CharGrid<C>
+where
+    C: GridCell,
+{
+    pub fn new(map: Array2d<i64, C>) -> CharGrid<C> {
+        CharGrid { map }
+    }
+    /// Parses the grid, one row per line starting at y = 0.
+    pub fn parse(i: &str) -> IResult<&str, CharGrid<C>> {
+        let (i, rows) = grid_of(map_opt(none_of("\r\n"), C::from_char))(i)?;
+        Ok((i, CharGrid::new(Array2d::from_vec(rows))))
+    }
+    pub fn map(&self) -> &Array2d<i64, C> {
+        &self.map
+    }
+    pub fn into_map(self) -> Array2d<i64, C> {
+        self.map
+    }
+    pub fn bbox(&self) -> BBox2d {
+        self.map.bbox()
+    }
+    /// Returns the cell at a position, or `None` if it is outside the grid.
+    pub fn get(&self, p: Point2d) -> Option<C> {
+        self.map.get(p).copied()
+    }
+    /// Returns the positions containing the given cell.
+    pub fn find_all(&self, cell: C) -> impl Iterator<Item = Point2d> + '_ {
+        self.bbox().iter().filter(move |&p| self.map[p] == cell)
+    }
+    /// Returns the position of a cell which must occur exactly once, like a start or goal.
+    pub fn find_unique(&self, cell: C) -> Result<Point2d> {
+        let mut ps = self.find_all(cell);
+        let p = ps
+            .next()
+            .ok_or_else(|| format!("no '{}' found in grid", cell.to_char()))?;
+        if let Some(p1) = ps.next() {
+            return Err(format!(
+                "'{}' found more than once in grid, at ({}, {}) and ({}, {})",
+                cell.to_char(),
+                p.x(),
+                p.y(),
+                p1.x(),
+                p1.y(),
+            )
+            .into());
+        }
+        Ok(p)
+    }
+    /// Returns the orthogonal neighbors of a position that are inside the grid.
+    pub fn neighbors_l1(&self, p: Point2d) -> impl Iterator<Item = Point2d> + '_ {
+        p.neighbors_l1().filter(move |np| self.bbox().contains(np))
+    }
+    /// Returns the orthogonal and diagonal neighbors of a position that are inside the grid.
+    pub fn neighbors_l_infty(&self, p: Point2d) -> impl Iterator<Item = Point2d> + '_ {
+        p.neighbors_l_infty()
+            .filter(move |np| self.bbox().contains(np))
+    }
+}
+impl<C> Index<Point2d> for CharGrid<C> {
+    type Output = C;
+    fn index(&self, p: Point2d) -> &C {
+        &self.map[p]
+    }
+}
+impl<C> IndexMut<Point2d> for CharGrid<C> {
+    fn index_mut(&mut self, p: Point2d) -> &mut C {
+        &mut self.map[p]
+    }
+}
+impl<C> FromStr for CharGrid<C>
+where
+    C: GridCell,
+{
+    type Err = Error;
+    fn from_str(s: &str) -> Result<CharGrid<C>> {
+        parse_input(s, CharGrid::parse)
+    }
+}
+impl<C> fmt::Display for CharGrid<C>
+where
+    C: GridCell,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bbox = self.bbox();
+        for y in bbox.y_range() {
+            for x in bbox.x_range() {
+                write!(f, "{}", self.map[p2d(x, y)].to_char())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use lowdim::p2d;
+
+    use crate::CharGrid;
+
+    crate::grid_cell! {
+        enum Square {
+            Empty => '.',
+            Wall => '#',
+            Start => 'S',
+        }
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let text = "#.S\n..#\n";
+        let grid = text.parse::<CharGrid<Square>>().unwrap();
+        assert_eq!(grid[p2d(2, 0)], Square::Start);
+        assert_eq!(grid.to_string(), text);
+    }
+
+    #[test]
+    fn test_find_unique() {
+        let grid = "#.S\n..#\n".parse::<CharGrid<Square>>().unwrap();
+        assert_eq!(grid.find_unique(Square::Start).unwrap(), p2d(2, 0));
+        assert!(grid.find_unique(Square::Wall).is_err());
+    }
+
+    #[test]
+    fn test_neighbors() {
+        let grid = "#.S\n..#\n".parse::<CharGrid<Square>>().unwrap();
+        assert_eq!(grid.neighbors_l1(p2d(0, 0)).count(), 2);
+        assert_eq!(grid.neighbors_l_infty(p2d(1, 1)).count(), 5);
+    }
+}
@@ -0,0 +1,313 @@
+//! Searching for MD5 hashes with special properties, as in the hash-mining puzzles.
+//!
+//! The hashes are of a common prefix followed by a decimal nonce.
+//! The prefix is hashed only once, and the nonces are checked
+//! without allocating, in batches which are split across threads.
+//! Results are still returned in the order of the nonces.
+
+use std::collections::VecDeque;
+use std::thread;
+
+/// The number of plain hashes computed by one batch of worker threads.
+const BATCH_SIZE: u64 = 1 << 16;
+
+/// The minimal number of nonces handled by one batch, even for stretched hashes.
+const MIN_BATCH_SIZE: u64 = 1 << 10;
+
+/// An MD5 digest as raw bytes.
+pub type Digest = [u8; 16];
+
+/// The MD5 state after hashing a common prefix.
+#[derive(Clone)]
+pub struct PrefixHasher {
+    context: md5::Context,
+}
+impl PrefixHasher {
+    pub fn new(prefix: &[u8]) -> PrefixHasher {
+        let mut context = md5::Context::new();
+        context.consume(prefix);
+        PrefixHasher { context }
+    }
+    /// Returns the hash of the prefix followed by the suffix.
+    pub fn hash(&self, suffix: &[u8]) -> Digest {
+        let mut context = self.context.clone();
+        context.consume(suffix);
+        context.compute().0
+    }
+    /// Returns the hash of the prefix followed by the nonce in decimal.
+    pub fn hash_nonce(&self, nonce: u64) -> Digest {
+        let mut buffer = [0; 20];
+        self.hash(decimal(nonce, &mut buffer))
+    }
+}
+
+/// Writes a number in decimal to the end of a buffer and returns the digits.
+fn decimal(mut n: u64, buffer: &mut [u8; 20]) -> &[u8] {
+    let mut start = buffer.len();
+    loop {
+        start -= 1;
+        buffer[start] = b'0' + (n % 10) as u8;
+        n /= 10;
+        if n == 0 {
+            break;
+        }
+    }
+    &buffer[start..]
+}
+
+/// Returns the nibble at an index of the digest, starting with the most significant.
+pub fn nibble(digest: &Digest, i: usize) -> u8 {
+    let b = digest[i / 2];
+    if i & 1 == 0 {
+        b >> 4
+    } else {
+        b & 0xf
+    }
+}
+
+/// Returns the nibbles of the digest, as in its hexadecimal representation.
+pub fn nibbles(digest: &Digest) -> [u8; 32] {
+    let mut result = [0; 32];
+    for (i, n) in result.iter_mut().enumerate() {
+        *n = nibble(digest, i);
+    }
+    result
+}
+
+/// Returns true if the hexadecimal representation starts with `n` zeros.
+pub fn has_leading_zeros(digest: &Digest, n: usize) -> bool {
+    (0..n).all(|i| nibble(digest, i) == 0)
+}
+
+/// Returns the lowercase hexadecimal representation of the digest.
+pub fn to_hex(digest: &Digest) -> [u8; 32] {
+    nibbles(digest).map(|n| b"0123456789abcdef"[usize::from(n)])
+}
+
+/// Rehashes the hexadecimal representation of the digest the given number of times.
+pub fn stretch(digest: &Digest, reps: usize) -> Digest {
+    let mut digest = *digest;
+    for _ in 0..reps {
+        digest = md5::compute(to_hex(&digest)).0;
+    }
+    digest
+}
+
+/// Computes hashes for the nonces in a range in parallel.
+///
+/// The hashes are stretched by `stretch_reps` repetitions.
+/// Returns the values of `f` that are not `None`, in the order of the nonces.
+fn batch<T, F>(hasher: &PrefixHasher, stretch_reps: usize, start: u64, len: u64, f: &F) -> Vec<T>
+where
+    T: Send,
+    F: Fn(u64, Digest) -> Option<T> + Sync,
+{
+    let threads = thread::available_parallelism().map_or(1, |n| n.get()) as u64;
+    let chunk_size = len.div_ceil(threads);
+    thread::scope(|s| {
+        let handles = (0..threads)
+            .map(|t| {
+                let chunk_start = start + t * chunk_size;
+                let chunk_end = (chunk_start + chunk_size).min(start + len);
+                s.spawn(move || {
+                    (chunk_start..chunk_end)
+                        .filter_map(|nonce| {
+                            let digest = stretch(&hasher.hash_nonce(nonce), stretch_reps);
+                            f(nonce, digest)
+                        })
+                        .collect::<Vec<_>>()
+                })
+            })
+            .collect::<Vec<_>>();
+        handles
+            .into_iter()
+            .flat_map(|h| h.join().expect("hash worker panicked"))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// The nonces whose hashes satisfy a predicate, in increasing order.
+pub struct Search<P> {
+    hasher: PrefixHasher,
+    predicate: P,
+    next_nonce: u64,
+    found: VecDeque<(u64, Digest)>,
+}
+impl<P> Iterator for Search<P>
+where
+    P: Fn(&Digest) -> bool + Sync,
+{
+    type Item = (u64, Digest);
+    fn next(&mut self) -> Option<(u64, Digest)> {
+        while self.found.is_empty() {
+            let predicate = &self.predicate;
+            let found = batch(
+                &self.hasher,
+                0,
+                self.next_nonce,
+                BATCH_SIZE,
+                &|nonce, digest| {
+                    if predicate(&digest) {
+                        Some((nonce, digest))
+                    } else {
+                        None
+                    }
+                },
+            );
+            self.found.extend(found);
+            self.next_nonce += BATCH_SIZE;
+        }
+        self.found.pop_front()
+    }
+}
+
+/// Searches the nonces from 0 on whose hashes with the prefix satisfy the predicate.
+pub fn search<P>(prefix: &[u8], predicate: P) -> Search<P>
+where
+    P: Fn(&Digest) -> bool + Sync,
+{
+    Search {
+        hasher: PrefixHasher::new(prefix),
+        predicate,
+        next_nonce: 0,
+        found: VecDeque::new(),
+    }
+}
+
+/// Searches the nonces whose hashes with the prefix start with `n` hexadecimal zeros.
+pub fn search_leading_zeros(prefix: &[u8], n: usize) -> Search<impl Fn(&Digest) -> bool + Sync> {
+    search(prefix, move |digest| has_leading_zeros(digest, n))
+}
+
+/// The stretched hashes for all nonces from 0 on.
+pub struct Hashes {
+    hasher: PrefixHasher,
+    stretch_reps: usize,
+    batch_size: u64,
+    next_nonce: u64,
+    buffer: VecDeque<Digest>,
+}
+impl Hashes {
+    pub fn new(prefix: &[u8], stretch_reps: usize) -> Hashes {
+        Hashes {
+            hasher: PrefixHasher::new(prefix),
+            stretch_reps,
+            // Keep the work per batch similar, so few unneeded hashes are computed.
+            batch_size: (BATCH_SIZE / (stretch_reps as u64 + 1)).max(MIN_BATCH_SIZE),
+            next_nonce: 0,
+            buffer: VecDeque::new(),
+        }
+    }
+}
+impl Iterator for Hashes {
+    type Item = Digest;
+    fn next(&mut self) -> Option<Digest> {
+        if self.buffer.is_empty() {
+            let hashes = batch(
+                &self.hasher,
+                self.stretch_reps,
+                self.next_nonce,
+                self.batch_size,
+                &|_, digest| Some(digest),
+            );
+            self.buffer.extend(hashes);
+            self.next_nonce += self.batch_size;
+        }
+        self.buffer.pop_front()
+    }
+}
+
+/// The number of following hashes searched for a quintuple.
+const KEY_LOOKAHEAD: usize = 1000;
+
+/// Returns the first nibble that occurs three times in a row.
+fn first_triple(nibbles: &[u8; 32]) -> Option<u8> {
+    nibbles
+        .windows(3)
+        .find(|w| w[0] == w[1] && w[1] == w[2])
+        .map(|w| w[0])
+}
+
+/// Returns true if the nibble occurs five times in a row.
+fn has_quintuple(nibbles: &[u8; 32], n: u8) -> bool {
+    nibbles.windows(5).any(|w| w.iter().all(|&m| m == n))
+}
+
+/// The indices of one-time pad keys, in increasing order.
+///
+/// The hash for an index is a key if it contains a triple of some nibble
+/// and one of the following 1000 hashes contains a quintuple of the same nibble.
+pub struct PadKeys {
+    hashes: Hashes,
+    window: VecDeque<[u8; 32]>,
+    next_index: u64,
+}
+impl PadKeys {
+    pub fn new(salt: &[u8], stretch_reps: usize) -> PadKeys {
+        PadKeys {
+            hashes: Hashes::new(salt, stretch_reps),
+            window: VecDeque::new(),
+            next_index: 0,
+        }
+    }
+}
+impl Iterator for PadKeys {
+    type Item = u64;
+    fn next(&mut self) -> Option<u64> {
+        loop {
+            while self.window.len() <= KEY_LOOKAHEAD {
+                self.window.push_back(nibbles(&self.hashes.next()?));
+            }
+            let nibbles = self.window.pop_front()?;
+            let index = self.next_index;
+            self.next_index += 1;
+            if let Some(n) = first_triple(&nibbles) {
+                if self.window.iter().any(|ns| has_quintuple(ns, n)) {
+                    return Some(index);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::hash_search::has_leading_zeros;
+    use crate::hash_search::search_leading_zeros;
+    use crate::hash_search::stretch;
+    use crate::hash_search::to_hex;
+    use crate::hash_search::PadKeys;
+    use crate::hash_search::PrefixHasher;
+
+    #[test]
+    fn test_hash_nonce() {
+        let hasher = PrefixHasher::new(b"abc");
+        assert_eq!(hasher.hash_nonce(18), md5::compute(b"abc18").0);
+        assert_eq!(hasher.hash_nonce(0), md5::compute(b"abc0").0);
+        assert_eq!(
+            &to_hex(&hasher.hash_nonce(3231929)),
+            b"00000155f8105dff7f56ee10fa9b9abd"
+        );
+    }
+
+    #[test]
+    fn test_search_leading_zeros() {
+        let mut nonces = search_leading_zeros(b"abcdef", 5).map(|(nonce, _)| nonce);
+        assert_eq!(nonces.next(), Some(609043));
+        assert!(has_leading_zeros(
+            &PrefixHasher::new(b"abcdef").hash_nonce(609043),
+            5
+        ));
+    }
+
+    #[test]
+    fn test_stretch() {
+        let digest = stretch(&PrefixHasher::new(b"abc").hash_nonce(0), 2016);
+        assert_eq!(&to_hex(&digest), b"a107ff634856bb300138cac6568c0f24");
+    }
+
+    #[test]
+    fn test_pad_keys() {
+        assert_eq!(PadKeys::new(b"abc", 0).nth(63), Some(22728));
+    }
+}
@@ -0,0 +1,367 @@
+//! Coordinates on a grid of hexagons.
+//!
+//! Uses axial coordinates `(q, r)`, where the third cube coordinate `s`
+//! is determined by `q + r + s = 0`.
+//! As on a screen, `r` increases towards the south.
+//! The same coordinates serve for flat-top and pointy-top hexagons,
+//! only the names of the directions and the rendering differ.
+
+use core::fmt;
+use core::iter;
+use core::ops;
+use core::str::FromStr;
+
+use std::collections::HashMap;
+
+use crate::Error;
+
+/// A hexagon, or a vector between hexagons, in axial coordinates.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Hex {
+    pub q: i64,
+    pub r: i64,
+}
+
+/// The six unit vectors in clockwise order, starting with `(1, 0)`.
+const UNITS: [Hex; 6] = [
+    Hex::new(1, 0),
+    Hex::new(0, 1),
+    Hex::new(-1, 1),
+    Hex::new(-1, 0),
+    Hex::new(0, -1),
+    Hex::new(1, -1),
+];
+
+impl Hex {
+    pub const ORIGIN: Hex = Hex::new(0, 0);
+
+    pub const fn new(q: i64, r: i64) -> Hex {
+        Hex { q, r }
+    }
+    pub fn from_cube(q: i64, r: i64, s: i64) -> Hex {
+        assert_eq!(q + r + s, 0, "cube coordinates must sum to zero");
+        Hex::new(q, r)
+    }
+    pub fn s(self) -> i64 {
+        -self.q - self.r
+    }
+    /// Returns the cube coordinates `(q, r, s)`.
+    pub fn cube(self) -> (i64, i64, i64) {
+        (self.q, self.r, self.s())
+    }
+    /// Returns the number of steps to the origin.
+    pub fn norm(self) -> i64 {
+        self.q.abs().max(self.r.abs()).max(self.s().abs())
+    }
+    /// Returns the number of steps between two hexagons.
+    pub fn distance(self, other: Hex) -> i64 {
+        (self - other).norm()
+    }
+    /// Returns the six adjacent hexagons.
+    pub fn neighbors(self) -> impl Iterator<Item = Hex> {
+        UNITS.into_iter().map(move |u| self + u)
+    }
+    /// Rotates by 60 degrees clockwise around the origin.
+    pub fn rotate_right(self) -> Hex {
+        Hex::new(-self.r, -self.s())
+    }
+    /// Rotates by 60 degrees counterclockwise around the origin.
+    pub fn rotate_left(self) -> Hex {
+        Hex::new(-self.s(), -self.q)
+    }
+    /// Returns the hexagons at the given distance, in clockwise order.
+    pub fn ring(self, radius: i64) -> Vec<Hex> {
+        if radius == 0 {
+            return vec![self];
+        }
+        let mut result = Vec::new();
+        let mut h = self + radius * UNITS[4];
+        for u in UNITS {
+            for _ in 0..radius {
+                result.push(h);
+                h += u;
+            }
+        }
+        result
+    }
+    /// Returns the hexagons up to the given distance, ring by ring from the center.
+    pub fn spiral(self, radius: i64) -> Vec<Hex> {
+        (0..=radius).flat_map(|r| self.ring(r)).collect()
+    }
+}
+
+impl ops::Add for Hex {
+    type Output = Hex;
+    fn add(self, other: Hex) -> Hex {
+        Hex::new(self.q + other.q, self.r + other.r)
+    }
+}
+impl ops::AddAssign for Hex {
+    fn add_assign(&mut self, other: Hex) {
+        *self = *self + other;
+    }
+}
+impl ops::Sub for Hex {
+    type Output = Hex;
+    fn sub(self, other: Hex) -> Hex {
+        Hex::new(self.q - other.q, self.r - other.r)
+    }
+}
+impl ops::Neg for Hex {
+    type Output = Hex;
+    fn neg(self) -> Hex {
+        Hex::new(-self.q, -self.r)
+    }
+}
+impl ops::Mul<Hex> for i64 {
+    type Output = Hex;
+    fn mul(self, other: Hex) -> Hex {
+        Hex::new(self * other.q, self * other.r)
+    }
+}
+impl iter::Sum for Hex {
+    fn sum<I>(iter: I) -> Hex
+    where
+        I: Iterator<Item = Hex>,
+    {
+        iter.fold(Hex::ORIGIN, |h0, h1| h0 + h1)
+    }
+}
+
+/// The directions between flat-top hexagons, which are stacked in columns.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum FlatDir {
+    N,
+    NE,
+    SE,
+    S,
+    SW,
+    NW,
+}
+impl FlatDir {
+    pub const ALL: [FlatDir; 6] = [
+        FlatDir::N,
+        FlatDir::NE,
+        FlatDir::SE,
+        FlatDir::S,
+        FlatDir::SW,
+        FlatDir::NW,
+    ];
+    pub fn to_hex(self) -> Hex {
+        match self {
+            FlatDir::N => Hex::new(0, -1),
+            FlatDir::NE => Hex::new(1, -1),
+            FlatDir::SE => Hex::new(1, 0),
+            FlatDir::S => Hex::new(0, 1),
+            FlatDir::SW => Hex::new(-1, 1),
+            FlatDir::NW => Hex::new(-1, 0),
+        }
+    }
+    pub fn to_str(self) -> &'static str {
+        match self {
+            FlatDir::N => "n",
+            FlatDir::NE => "ne",
+            FlatDir::SE => "se",
+            FlatDir::S => "s",
+            FlatDir::SW => "sw",
+            FlatDir::NW => "nw",
+        }
+    }
+}
+impl FromStr for FlatDir {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<FlatDir, Error> {
+        FlatDir::ALL
+            .into_iter()
+            .find(|d| d.to_str() == s)
+            .ok_or_else(|| format!("unknown direction '{}'", s).into())
+    }
+}
+impl fmt::Display for FlatDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+/// The directions between pointy-top hexagons, which are stacked in rows.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PointyDir {
+    E,
+    NE,
+    NW,
+    W,
+    SW,
+    SE,
+}
+impl PointyDir {
+    pub const ALL: [PointyDir; 6] = [
+        PointyDir::E,
+        PointyDir::NE,
+        PointyDir::NW,
+        PointyDir::W,
+        PointyDir::SW,
+        PointyDir::SE,
+    ];
+    pub fn to_hex(self) -> Hex {
+        match self {
+            PointyDir::E => Hex::new(1, 0),
+            PointyDir::NE => Hex::new(1, -1),
+            PointyDir::NW => Hex::new(0, -1),
+            PointyDir::W => Hex::new(-1, 0),
+            PointyDir::SW => Hex::new(-1, 1),
+            PointyDir::SE => Hex::new(0, 1),
+        }
+    }
+    pub fn to_str(self) -> &'static str {
+        match self {
+            PointyDir::E => "e",
+            PointyDir::NE => "ne",
+            PointyDir::NW => "nw",
+            PointyDir::W => "w",
+            PointyDir::SW => "sw",
+            PointyDir::SE => "se",
+        }
+    }
+}
+impl FromStr for PointyDir {
+    type Err = Error;
+    fn from_str(s: &str) -> Result<PointyDir, Error> {
+        PointyDir::ALL
+            .into_iter()
+            .find(|d| d.to_str() == s)
+            .ok_or_else(|| format!("unknown direction '{}'", s).into())
+    }
+}
+impl fmt::Display for PointyDir {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.to_str())
+    }
+}
+
+/// Whether the hexagons have a flat or a pointy top.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Orientation {
+    Flat,
+    Pointy,
+}
+
+/// Renders a map of hexagons as text, with one character per hexagon.
+///
+/// Flat-top hexagons are placed in columns which are shifted by half a line
+/// against each other, pointy-top hexagons in rows which are shifted by half
+/// a character.  Positions without a hexagon are left blank.
+pub fn render(orientation: Orientation, map: &HashMap<Hex, char>) -> String {
+    // Use doubled coordinates to place the hexagons on a rectangular grid.
+    let place = |h: Hex| match orientation {
+        Orientation::Flat => (h.q, 2 * h.r + h.q),
+        Orientation::Pointy => (2 * h.q + h.r, h.r),
+    };
+    let cells = map
+        .iter()
+        .map(|(&h, &c)| (place(h), c))
+        .collect::<HashMap<_, _>>();
+    let (Some(x_min), Some(y_min), Some(y_max)) = (
+        cells.keys().map(|&(x, _)| x).min(),
+        cells.keys().map(|&(_, y)| y).min(),
+        cells.keys().map(|&(_, y)| y).max(),
+    ) else {
+        return String::new();
+    };
+    let x_max = cells.keys().map(|&(x, _)| x).max().unwrap_or(x_min);
+
+    let mut result = String::new();
+    for y in y_min..=y_max {
+        let line = (x_min..=x_max)
+            .map(|x| cells.get(&(x, y)).copied().unwrap_or(' '))
+            .collect::<String>();
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::hex::render;
+    use crate::hex::FlatDir;
+    use crate::hex::Hex;
+    use crate::hex::Orientation;
+    use crate::hex::PointyDir;
+
+    #[test]
+    fn test_distance() {
+        let steps = |s: &str| {
+            s.split(',')
+                .map(|d| d.parse::<FlatDir>().unwrap().to_hex())
+                .sum::<Hex>()
+                .norm()
+        };
+        assert_eq!(steps("ne,ne,ne"), 3);
+        assert_eq!(steps("ne,ne,sw,sw"), 0);
+        assert_eq!(steps("ne,ne,s,s"), 2);
+        assert_eq!(steps("se,sw,se,sw,sw"), 3);
+        assert_eq!(Hex::new(2, -1).distance(Hex::new(-1, 1)), 3);
+        assert!("x".parse::<FlatDir>().is_err());
+    }
+
+    #[test]
+    fn test_directions() {
+        let flat = FlatDir::ALL.map(FlatDir::to_hex);
+        let pointy = PointyDir::ALL.map(PointyDir::to_hex);
+        let mut neighbors = Hex::ORIGIN.neighbors().collect::<Vec<_>>();
+        for mut dirs in [flat, pointy] {
+            dirs.sort();
+            neighbors.sort();
+            assert_eq!(dirs.to_vec(), neighbors);
+        }
+        assert_eq!("nw".parse::<PointyDir>().unwrap(), PointyDir::NW);
+        assert_eq!(PointyDir::SE.to_string(), "se");
+    }
+
+    #[test]
+    fn test_rotate() {
+        let h = Hex::new(3, -1);
+        assert_eq!(h.rotate_right().rotate_left(), h);
+        let mut g = h;
+        for _ in 0..6 {
+            assert_eq!(g.norm(), h.norm());
+            g = g.rotate_right();
+        }
+        assert_eq!(g, h);
+        assert_eq!(PointyDir::E.to_hex().rotate_right(), PointyDir::SE.to_hex());
+        assert_eq!(FlatDir::N.to_hex().rotate_left(), FlatDir::NW.to_hex());
+    }
+
+    #[test]
+    fn test_ring_and_spiral() {
+        let center = Hex::new(1, 2);
+        assert_eq!(center.ring(0), vec![center]);
+        for radius in 1..5 {
+            let ring = center.ring(radius);
+            assert_eq!(ring.len(), 6 * radius as usize);
+            assert!(ring.iter().all(|&h| h.distance(center) == radius));
+            for i in 0..ring.len() {
+                assert_eq!(ring[i].distance(ring[(i + 1) % ring.len()]), 1);
+            }
+        }
+        let mut spiral = center.spiral(3);
+        assert_eq!(spiral.len(), 37);
+        spiral.sort();
+        spiral.dedup();
+        assert_eq!(spiral.len(), 37);
+    }
+
+    #[test]
+    fn test_render() {
+        let map = Hex::ORIGIN
+            .spiral(1)
+            .into_iter()
+            .map(|h| (h, if h == Hex::ORIGIN { 'o' } else { '*' }))
+            .collect::<HashMap<_, _>>();
+        assert_eq!(render(Orientation::Pointy, &map), " * *\n* o *\n * *\n");
+        assert_eq!(render(Orientation::Flat, &map), " *\n* *\n o\n* *\n *\n");
+        assert_eq!(render(Orientation::Pointy, &HashMap::new()), "");
+    }
+}
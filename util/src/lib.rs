@@ -22,3 +22,22 @@ pub use int_disjoint_sets::IntDisjointSets;
 mod knot_hash;
 pub use knot_hash::knot_hash;
 pub use knot_hash::KnotHashState;
+
+pub mod parse;
+
+mod grid;
+pub use grid::CharGrid;
+pub use grid::GridCell;
+
+pub mod validate;
+pub use validate::validate;
+
+pub mod ocr;
+
+pub mod hash_search;
+
+pub mod tsp;
+
+pub mod hex;
+
+pub mod automaton;
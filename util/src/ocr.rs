@@ -0,0 +1,302 @@
+//! Recognition of the letters that some puzzles draw on a pixel grid.
+
+use core::fmt;
+
+use std::collections::HashMap;
+use std::error;
+
+use crate::Result;
+
+/// The letters 6 pixels high, mostly 4 pixels wide.
+const FONT_6: &[(char, &str)] = &[
+    ('A', ".##.\n#..#\n#..#\n####\n#..#\n#..#"),
+    ('B', "###.\n#..#\n###.\n#..#\n#..#\n###."),
+    ('C', ".##.\n#..#\n#...\n#...\n#..#\n.##."),
+    ('E', "####\n#...\n###.\n#...\n#...\n####"),
+    ('F', "####\n#...\n###.\n#...\n#...\n#..."),
+    ('G', ".##.\n#..#\n#...\n#.##\n#..#\n.###"),
+    ('H', "#..#\n#..#\n####\n#..#\n#..#\n#..#"),
+    ('I', "###\n.#.\n.#.\n.#.\n.#.\n###"),
+    ('J', "..##\n...#\n...#\n...#\n#..#\n.##."),
+    ('K', "#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#"),
+    ('L', "#...\n#...\n#...\n#...\n#...\n####"),
+    ('O', ".##.\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('P', "###.\n#..#\n#..#\n###.\n#...\n#..."),
+    ('R', "###.\n#..#\n#..#\n###.\n#.#.\n#..#"),
+    ('S', ".###\n#...\n#...\n.##.\n...#\n###."),
+    ('U', "#..#\n#..#\n#..#\n#..#\n#..#\n.##."),
+    ('Y', "#...#\n#...#\n.#.#.\n..#..\n..#..\n..#.."),
+    ('Z', "####\n...#\n..#.\n.#..\n#...\n####"),
+];
+
+/// The letters 10 pixels high and 6 pixels wide.
+const FONT_10: &[(char, &str)] = &[
+    (
+        'A',
+        "..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'B',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.",
+    ),
+    (
+        'C',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.",
+    ),
+    (
+        'E',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'F',
+        "######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'G',
+        ".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#",
+    ),
+    (
+        'H',
+        "#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#",
+    ),
+    (
+        'J',
+        "...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..",
+    ),
+    (
+        'K',
+        "#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#",
+    ),
+    (
+        'L',
+        "#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######",
+    ),
+    (
+        'N',
+        "#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#",
+    ),
+    (
+        'P',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....",
+    ),
+    (
+        'R',
+        "#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#",
+    ),
+    (
+        'X',
+        "#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#",
+    ),
+    (
+        'Z',
+        "######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######",
+    ),
+];
+
+/// A glyph that could not be recognized, with its position among the glyphs.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct UnrecognizedGlyph {
+    pub index: usize,
+    pub glyph: String,
+}
+
+/// An error listing the glyphs which could not be recognized.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct OcrError {
+    /// The text with `?` for unrecognized glyphs.
+    pub text: String,
+    pub unrecognized: Vec<UnrecognizedGlyph>,
+}
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "unrecognized glyphs in \"{}\":", self.text)?;
+        for u in &self.unrecognized {
+            writeln!(f, "glyph {}:", u.index)?;
+            writeln!(f, "{}", u.glyph)?;
+        }
+        Ok(())
+    }
+}
+impl error::Error for OcrError {}
+
+fn font(height: usize) -> Option<HashMap<String, char>> {
+    let glyphs = match height {
+        6 => FONT_6,
+        10 => FONT_10,
+        _ => return None,
+    };
+    Some(
+        glyphs
+            .iter()
+            .map(|&(c, glyph)| (glyph.to_string(), c))
+            .collect::<HashMap<_, _>>(),
+    )
+}
+
+/// Recognizes the letters drawn by the lit pixels of a grid.
+///
+/// The rows of the grid are given from top to bottom.
+/// Surrounding empty rows and columns are ignored.
+/// Letters are usually separated by an empty column, but may touch.
+pub fn recognize_grid(rows: &[Vec<bool>]) -> Result<String> {
+    let lit_rows = (0..rows.len())
+        .filter(|&y| rows[y].iter().any(|&b| b))
+        .collect::<Vec<_>>();
+    let (y_min, y_max) = match (lit_rows.first(), lit_rows.last()) {
+        (Some(&y_min), Some(&y_max)) => (y_min, y_max),
+        _ => return Ok(String::new()),
+    };
+    let rows = &rows[y_min..=y_max];
+    let height = rows.len();
+    let font = font(height).ok_or_else(|| format!("no font for letters {} pixels high", height))?;
+
+    let max_glyph_width = font.keys().map(|g| g.find('\n').unwrap_or(0)).max();
+    let max_glyph_width = max_glyph_width.unwrap_or(0);
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let is_lit = |x: usize, y: usize| rows[y].get(x).copied().unwrap_or(false);
+    let is_empty_column = |x: usize| (0..height).all(|y| !is_lit(x, y));
+    let glyph = |x_start: usize, x_end: usize| {
+        (0..height)
+            .map(|y| {
+                (x_start..x_end)
+                    .map(|x| if is_lit(x, y) { '#' } else { '.' })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let mut text = String::new();
+    let mut unrecognized = Vec::new();
+    let mut x = 0;
+    while x < width {
+        if is_empty_column(x) {
+            x += 1;
+            continue;
+        }
+        let x_start = x;
+        while x < width && !is_empty_column(x) {
+            x += 1;
+        }
+        let x_end = x;
+
+        // Some wide letters touch their neighbor, so split by trying glyph widths.
+        let mut x0 = x_start;
+        while x0 < x_end {
+            let max_width = max_glyph_width.min(x_end - x0);
+            let found = (1..=max_width)
+                .rev()
+                .find_map(|w| font.get(&glyph(x0, x0 + w)).map(|&c| (c, w)));
+            if let Some((c, w)) = found {
+                text.push(c);
+                x0 += w;
+            } else {
+                unrecognized.push(UnrecognizedGlyph {
+                    index: text.chars().count(),
+                    glyph: glyph(x0, x_end),
+                });
+                text.push('?');
+                x0 = x_end;
+            }
+        }
+    }
+    if unrecognized.is_empty() {
+        Ok(text)
+    } else {
+        Err(Box::new(OcrError { text, unrecognized }))
+    }
+}
+
+/// Recognizes the letters drawn by a set of lit pixels given as `(x, y)`.
+///
+/// The y axis points downward.
+pub fn recognize_points<I>(points: I) -> Result<String>
+where
+    I: IntoIterator<Item = (i64, i64)>,
+{
+    let points = points.into_iter().collect::<Vec<_>>();
+    if points.is_empty() {
+        return Ok(String::new());
+    }
+    let x_min = points.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let x_max = points.iter().map(|&(x, _)| x).max().unwrap_or(0);
+    let y_min = points.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let y_max = points.iter().map(|&(_, y)| y).max().unwrap_or(0);
+    let width = usize::try_from(x_max - x_min + 1)?;
+    let height = usize::try_from(y_max - y_min + 1)?;
+    let mut rows = vec![vec![false; width]; height];
+    for (x, y) in points {
+        rows[usize::try_from(y - y_min)?][usize::try_from(x - x_min)?] = true;
+    }
+    recognize_grid(&rows)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ocr::recognize_grid;
+    use crate::ocr::recognize_points;
+    use crate::ocr::OcrError;
+
+    fn grid(s: &str) -> Vec<Vec<bool>> {
+        s.lines()
+            .map(|line| line.chars().map(|c| c == '#').collect::<Vec<_>>())
+            .collect::<Vec<_>>()
+    }
+
+    #[test]
+    fn test_font_6() {
+        let rows = grid(concat!(
+            "#..#.###...##....\n",
+            "#..#.#..#.#..#...\n",
+            "####.###..#......\n",
+            "#..#.#..#.#......\n",
+            "#..#.#..#.#..#...\n",
+            "#..#.###...##....\n",
+        ));
+        assert_eq!(recognize_grid(&rows).unwrap(), "HBC");
+    }
+
+    #[test]
+    fn test_font_10() {
+        let rows = grid(concat!(
+            "#....#..######\n",
+            "#....#.......#\n",
+            "#....#.......#\n",
+            "#....#......#.\n",
+            "######.....#..\n",
+            "#....#....#...\n",
+            "#....#...#....\n",
+            "#....#..#.....\n",
+            "#....#..#.....\n",
+            "#....#..######\n",
+        ));
+        assert_eq!(recognize_grid(&rows).unwrap(), "HZ");
+    }
+
+    #[test]
+    fn test_points() {
+        let points = [(10, 5), (11, 5), (10, 6), (10, 7)];
+        assert!(recognize_points(points).is_err());
+        let mut points = (0..6).map(|y| (3, y + 1)).collect::<Vec<_>>();
+        points.extend((4..7).map(|x| (x, 6)));
+        assert_eq!(recognize_points(points).unwrap(), "L");
+    }
+
+    #[test]
+    fn test_unrecognized() {
+        let rows = grid(concat!(
+            "#..#.#...\n",
+            "#..#.#...\n",
+            "####.#...\n",
+            "#..#.#...\n",
+            "#..#.#...\n",
+            "#..#.##..\n",
+        ));
+        let e = recognize_grid(&rows).unwrap_err();
+        let e = e.downcast_ref::<OcrError>().unwrap();
+        assert_eq!(e.text, "H?");
+        assert_eq!(e.unrecognized[0].index, 1);
+        assert_eq!(e.unrecognized[0].glyph, "#.\n#.\n#.\n#.\n#.\n##");
+    }
+}
@@ -0,0 +1,251 @@
+//! Common `nom` parsers shared by the solutions.
+
+use core::fmt;
+use core::str::FromStr;
+
+use std::error;
+
+use nom::character::complete::char;
+use nom::character::complete::digit1;
+use nom::character::complete::line_ending;
+use nom::character::complete::none_of;
+use nom::combinator::all_consuming;
+use nom::combinator::map_opt;
+use nom::combinator::map_res;
+use nom::combinator::opt;
+use nom::combinator::recognize;
+use nom::error::ErrorKind;
+use nom::multi::many0;
+use nom::multi::many1;
+use nom::multi::separated_list1;
+use nom::sequence::pair;
+use nom::sequence::terminated;
+use nom::sequence::tuple;
+use nom::IResult;
+use nom::Parser;
+
+use crate::Error;
+use crate::Result;
+
+/// Maximal number of characters of the offending input shown in an error.
+const SNIPPET_LEN: usize = 20;
+
+/// Parses an optionally negative decimal integer.
+pub fn int<T>(i: &str) -> IResult<&str, T>
+where
+    T: FromStr,
+{
+    map_res(
+        recognize(tuple((opt(char('-')), digit1))),
+        FromStr::from_str,
+    )(i)
+}
+
+/// Parses an unsigned decimal integer.
+pub fn uint<T>(i: &str) -> IResult<&str, T>
+where
+    T: FromStr,
+{
+    map_res(recognize(digit1), FromStr::from_str)(i)
+}
+
+/// Parses an optionally negative decimal integer as an `i64`.
+pub fn int64(i: &str) -> IResult<&str, i64> {
+    int(i)
+}
+
+/// Parses one or more items separated by line endings.
+///
+/// The line ending after the last item is not consumed.
+pub fn lines<'a, O, F>(mut item: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    move |i| separated_list1(line_ending, |i| item.parse(i))(i)
+}
+
+/// Parses one or more blocks separated by blank lines.
+///
+/// The block parser must not consume the line ending at the end of its last line.
+pub fn blocks<'a, O, F>(mut block: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<O>>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    move |i| separated_list1(pair(line_ending, line_ending), |i| block.parse(i))(i)
+}
+
+/// Parses a single grid cell from a character.
+pub fn cell<C>(i: &str) -> IResult<&str, C>
+where
+    C: TryFrom<char>,
+{
+    map_opt(none_of("\r\n"), |c| C::try_from(c).ok())(i)
+}
+
+/// Parses a rectangular grid of cells, one row per line.
+///
+/// The rows are returned from top to bottom, which fits `Array2d::from_vec`.
+pub fn grid_of<'a, C, F>(mut cell: F) -> impl FnMut(&'a str) -> IResult<&'a str, Vec<Vec<C>>>
+where
+    F: Parser<&'a str, C, nom::error::Error<&'a str>>,
+{
+    move |i| {
+        let start = i;
+        let (i, rows) = lines(many1(|i| cell.parse(i)))(i)?;
+        if rows.iter().any(|row| row.len() != rows[0].len()) {
+            return Err(nom::Err::Failure(nom::error::Error::new(
+                start,
+                ErrorKind::Verify,
+            )));
+        }
+        Ok((i, rows))
+    }
+}
+
+/// Parses a rectangular grid of cells converted from characters.
+pub fn grid<C>(i: &str) -> IResult<&str, Vec<Vec<C>>>
+where
+    C: TryFrom<char>,
+{
+    grid_of(cell::<C>)(i)
+}
+
+/// Requires the parser to consume the entire input except for trailing line endings.
+pub fn entire<'a, O, F>(parser: F) -> impl FnMut(&'a str) -> IResult<&'a str, O>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    all_consuming(terminated(parser, many0(line_ending)))
+}
+
+/// Parses the entire input, converting a failure into a `ParseError`.
+pub fn parse_input<'a, O, F>(input: &'a str, parser: F) -> Result<O>
+where
+    F: Parser<&'a str, O, nom::error::Error<&'a str>>,
+{
+    entire(parser)(input)
+        .map(|(_, o)| o)
+        .map_err(|e| to_error(input, e))
+}
+
+/// Converts a `nom` error for the given input into a `ParseError`.
+pub fn to_error(input: &str, e: nom::Err<nom::error::Error<&str>>) -> Error {
+    match e {
+        nom::Err::Incomplete(_) => Box::new(ParseError::at_end(input)),
+        nom::Err::Error(e) | nom::Err::Failure(e) => {
+            Box::new(ParseError::new(input, e.input, e.code))
+        }
+    }
+}
+
+/// A parse error with the position of the offending input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseError {
+    /// The line number, starting at 1.
+    pub line: usize,
+    /// The column in characters, starting at 1.
+    pub column: usize,
+    /// The start of the offending input, up to the end of the line.
+    pub snippet: String,
+    pub kind: ErrorKind,
+}
+impl ParseError {
+    /// Creates an error for the position of `rest` within `input`.
+    ///
+    /// `rest` must be a suffix of `input`, as in the errors returned by `nom`.
+    pub fn new(input: &str, rest: &str, kind: ErrorKind) -> ParseError {
+        let offset = input.len() - rest.len();
+        let before = &input[..offset];
+        let line = before.matches('\n').count() + 1;
+        let line_start = before.rfind('\n').map_or(0, |p| p + 1);
+        let column = before[line_start..].chars().count() + 1;
+        let snippet = rest
+            .lines()
+            .next()
+            .unwrap_or("")
+            .chars()
+            .take(SNIPPET_LEN)
+            .collect::<String>();
+        ParseError {
+            line,
+            column,
+            snippet,
+            kind,
+        }
+    }
+    fn at_end(input: &str) -> ParseError {
+        ParseError::new(input, "", ErrorKind::Eof)
+    }
+}
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "parse error at line {}, column {} ({:?})",
+            self.line, self.column, self.kind
+        )?;
+        if self.snippet.is_empty() {
+            write!(f, " at end of line")
+        } else {
+            write!(f, " at \"{}\"", self.snippet)
+        }
+    }
+}
+impl error::Error for ParseError {}
+
+#[cfg(test)]
+mod tests {
+    use nom::error::ErrorKind;
+
+    use crate::parse::blocks;
+    use crate::parse::grid;
+    use crate::parse::int64;
+    use crate::parse::lines;
+    use crate::parse::parse_input;
+    use crate::parse::uint;
+    use crate::parse::ParseError;
+
+    #[test]
+    fn test_int64() {
+        assert_eq!(int64("-42,"), Ok((",", -42)));
+        assert_eq!(int64("17"), Ok(("", 17)));
+    }
+
+    #[test]
+    fn test_uint() {
+        assert_eq!(uint::<usize>("42\n"), Ok(("\n", 42)));
+        assert!(uint::<usize>("-42").is_err());
+    }
+
+    #[test]
+    fn test_blocks() {
+        assert_eq!(
+            blocks(lines(int64))("1\n2\n\n3\n"),
+            Ok(("\n", vec![vec![1, 2], vec![3]]))
+        );
+    }
+
+    #[test]
+    fn test_grid() {
+        assert_eq!(
+            parse_input("#.\n.#\n", grid::<char>).unwrap(),
+            vec![vec!['#', '.'], vec!['.', '#']]
+        );
+        assert!(parse_input("#.\n.\n", grid::<char>).is_err());
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let e = parse_input("1\n2\nx3\n", lines(int64)).unwrap_err();
+        let e = e.downcast_ref::<ParseError>().unwrap();
+        assert_eq!(
+            e,
+            &ParseError {
+                line: 3,
+                column: 1,
+                snippet: "x3".to_string(),
+                kind: ErrorKind::Eof,
+            }
+        );
+    }
+}
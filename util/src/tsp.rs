@@ -0,0 +1,194 @@
+//! Exact solutions of small travelling salesman problems.
+//!
+//! Uses the Held-Karp dynamic programming algorithm over subsets of nodes
+//! represented as bitmasks, which takes time `O(2^n n^2)` for `n` nodes.
+
+/// The maximal number of nodes.
+///
+/// The tables have `2^n n` entries of nine bytes each, so for 18 nodes
+/// they take about 42 MB, and the search takes about `8 * 10^7` steps.
+pub const MAX_NODES: usize = 18;
+
+/// Marks a table entry which no path reaches.
+const UNREACHED: u8 = u8::MAX;
+/// Marks a table entry for a path consisting only of its first node.
+const FIRST: u8 = u8::MAX - 1;
+
+/// Whether the best route is the one with minimal or maximal cost.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Objective {
+    Min,
+    Max,
+}
+impl Objective {
+    /// Returns true if the cost is better than the old one, if any.
+    fn improves(self, cost: i64, old: Option<i64>) -> bool {
+        match (self, old) {
+            (_, None) => true,
+            (Objective::Min, Some(old)) => cost < old,
+            (Objective::Max, Some(old)) => cost > old,
+        }
+    }
+}
+
+/// Whether the route ends anywhere or returns to its start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Path,
+    Tour,
+}
+
+/// A route visiting all nodes.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Route {
+    pub cost: i64,
+    /// The nodes in the order of the route.
+    ///
+    /// For a tour the return to the first node is not repeated.
+    pub nodes: Vec<usize>,
+}
+
+/// Returns a best route visiting all nodes.
+///
+/// `costs[i][j]` is the cost of going from node `i` to node `j`.
+/// The costs must be small enough that the cost of a route does not overflow.
+/// If `start` is given the route starts there, otherwise at any node.
+/// Since a tour has no start, it is returned starting at node 0 in that case.
+///
+/// Returns `None` if there are no nodes or too many.
+pub fn solve(
+    costs: &[Vec<i64>],
+    objective: Objective,
+    shape: Shape,
+    start: Option<usize>,
+) -> Option<Route> {
+    let n = costs.len();
+    if n == 0 || n > MAX_NODES {
+        return None;
+    }
+    let start = match (shape, start) {
+        (Shape::Tour, None) => Some(0),
+        _ => start,
+    };
+
+    // best[mask * n + last] is the cost of the best path visiting the nodes in `mask`
+    // and ending at `last`, and prev[mask * n + last] the node before `last`.
+    // The cost is only valid if the path exists, that is the entry of `prev`
+    // is not `UNREACHED`.
+    let mask_count = 1 << n;
+    let mut best = vec![0_i64; mask_count * n];
+    let mut prev = vec![UNREACHED; mask_count * n];
+    for first in 0..n {
+        if start.is_none() || start == Some(first) {
+            prev[(1 << first) * n + first] = FIRST;
+        }
+    }
+    for mask in 1..mask_count {
+        for last in 0..n {
+            if prev[mask * n + last] == UNREACHED {
+                continue;
+            }
+            let cost = best[mask * n + last];
+            for (next, &step_cost) in costs[last].iter().enumerate() {
+                if mask & (1 << next) == 0 {
+                    let next_mask = mask | (1 << next);
+                    let next_cost = cost + step_cost;
+                    let i = next_mask * n + next;
+                    let old = if prev[i] == UNREACHED {
+                        None
+                    } else {
+                        Some(best[i])
+                    };
+                    if objective.improves(next_cost, old) {
+                        best[i] = next_cost;
+                        prev[i] = last as u8;
+                    }
+                }
+            }
+        }
+    }
+
+    let full = mask_count - 1;
+    let mut result: Option<(i64, usize)> = None;
+    for last in 0..n {
+        if prev[full * n + last] != UNREACHED {
+            let cost = best[full * n + last];
+            let cost = match (shape, start) {
+                (Shape::Tour, Some(s)) => cost + costs[last][s],
+                _ => cost,
+            };
+            if objective.improves(cost, result.map(|(c, _)| c)) {
+                result = Some((cost, last));
+            }
+        }
+    }
+    let (cost, last) = result?;
+
+    let mut nodes = vec![last];
+    let mut mask = full;
+    let mut node = last;
+    while prev[mask * n + node] != FIRST {
+        let p = usize::from(prev[mask * n + node]);
+        mask &= !(1 << node);
+        node = p;
+        nodes.push(node);
+    }
+    nodes.reverse();
+    Some(Route { cost, nodes })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::tsp::solve;
+    use crate::tsp::Objective;
+    use crate::tsp::Shape;
+    use crate::tsp::MAX_NODES;
+
+    fn costs() -> Vec<Vec<i64>> {
+        // London, Dublin, Belfast
+        vec![vec![0, 464, 518], vec![464, 0, 141], vec![518, 141, 0]]
+    }
+
+    #[test]
+    fn test_path() {
+        let route = solve(&costs(), Objective::Min, Shape::Path, None).unwrap();
+        assert_eq!(route.cost, 605);
+        assert!(route.nodes == vec![0, 1, 2] || route.nodes == vec![2, 1, 0]);
+        let route = solve(&costs(), Objective::Max, Shape::Path, None).unwrap();
+        assert_eq!(route.cost, 982);
+    }
+
+    #[test]
+    fn test_fixed_start() {
+        let route = solve(&costs(), Objective::Min, Shape::Path, Some(2)).unwrap();
+        assert_eq!(route.cost, 605);
+        assert_eq!(route.nodes, vec![2, 1, 0]);
+        let route = solve(&costs(), Objective::Min, Shape::Path, Some(1)).unwrap();
+        assert_eq!(route.cost, 141 + 518);
+        assert_eq!(route.nodes, vec![1, 2, 0]);
+    }
+
+    #[test]
+    fn test_tour() {
+        let costs = vec![
+            vec![0, 1, 10, 1],
+            vec![1, 0, 1, 10],
+            vec![10, 1, 0, 1],
+            vec![1, 10, 1, 0],
+        ];
+        let route = solve(&costs, Objective::Min, Shape::Tour, None).unwrap();
+        assert_eq!(route.cost, 4);
+        assert_eq!(route.nodes[0], 0);
+        assert_eq!(route.nodes.len(), 4);
+        let route = solve(&costs, Objective::Max, Shape::Tour, Some(1)).unwrap();
+        assert_eq!(route.cost, 22);
+        assert_eq!(route.nodes[0], 1);
+    }
+
+    #[test]
+    fn test_node_count() {
+        assert_eq!(solve(&[], Objective::Min, Shape::Path, None), None);
+        let costs = vec![vec![1; MAX_NODES + 1]; MAX_NODES + 1];
+        assert_eq!(solve(&costs, Objective::Min, Shape::Path, None), None);
+    }
+}
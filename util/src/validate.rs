@@ -0,0 +1,161 @@
+use core::fmt;
+
+use std::env;
+use std::error;
+use std::process;
+
+use nom::error::ErrorKind;
+
+use crate::parse::ParseError;
+use crate::Result;
+
+/// Environment variable which enables parser validation.
+pub const VALIDATE_VAR: &str = "AOC_VALIDATE";
+
+/// Exit status of a solution whose parser failed validation.
+pub const VALIDATION_FAILED: i32 = 3;
+
+/// Maximal number of differing lines shown in a diff.
+const MAX_DIFF_LINES: usize = 20;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RoundTripError {
+    /// The parser stopped before the end of the input.
+    Unconsumed(ParseError),
+    /// Displaying the parsed value does not reproduce the input.
+    Mismatch(String),
+}
+impl fmt::Display for RoundTripError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RoundTripError::Unconsumed(e) => write!(f, "input not consumed: {}", e),
+            RoundTripError::Mismatch(diff) => {
+                writeln!(f, "displayed value differs from input (-input +displayed):")?;
+                write!(f, "{}", diff)
+            }
+        }
+    }
+}
+impl error::Error for RoundTripError {}
+
+/// Checks that the parser consumed the input and that the parsed value displays as the input.
+///
+/// `rest` is the remaining input returned by the parser.
+/// Differences in line endings at the end of lines and of the input are ignored.
+pub fn check_round_trip<T>(input: &str, rest: &str, value: &T) -> Result<()>
+where
+    T: fmt::Display,
+{
+    if !rest.trim_end_matches(['\r', '\n']).is_empty() {
+        let e = ParseError::new(input, rest, ErrorKind::Eof);
+        return Err(Box::new(RoundTripError::Unconsumed(e)));
+    }
+    let displayed = value.to_string();
+    let input_lines = input.lines().collect::<Vec<_>>();
+    let displayed_lines = displayed.lines().collect::<Vec<_>>();
+    if input_lines != displayed_lines {
+        let diff = line_diff(&input_lines, &displayed_lines);
+        return Err(Box::new(RoundTripError::Mismatch(diff)));
+    }
+    Ok(())
+}
+
+/// Validates the parser if the environment variable `AOC_VALIDATE` is set.
+///
+/// In that case the result is printed and the process exits,
+/// with status `VALIDATION_FAILED` if the round trip check fails.
+/// Otherwise this does nothing.
+pub fn validate<T>(input: &str, rest: &str, value: &T)
+where
+    T: fmt::Display,
+{
+    if env::var_os(VALIDATE_VAR).is_some() {
+        match check_round_trip(input, rest, value) {
+            Ok(()) => {
+                println!("Round trip: ok");
+                process::exit(0);
+            }
+            Err(e) => {
+                println!("Round trip: {}", e);
+                process::exit(VALIDATION_FAILED);
+            }
+        }
+    }
+}
+
+/// Returns the differing lines, based on a longest common subsequence.
+///
+/// Lines only in `old` are prefixed with `-`, lines only in `new` with `+`,
+/// both preceded by the line number in the respective text.
+pub fn line_diff(old: &[&str], new: &[&str]) -> String {
+    let n = old.len();
+    let m = new.len();
+
+    // lcs[i][j] is the length of the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0_usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut lines = Vec::new();
+    let mut i = 0;
+    let mut j = 0;
+    while i < n || j < m {
+        if i < n && j < m && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j == m || (i < n && lcs[i + 1][j] >= lcs[i][j + 1]) {
+            lines.push(format!("{:5} -{}", i + 1, old[i]));
+            i += 1;
+        } else {
+            lines.push(format!("{:5} +{}", j + 1, new[j]));
+            j += 1;
+        }
+    }
+
+    let mut result = String::new();
+    for line in lines.iter().take(MAX_DIFF_LINES) {
+        result.push_str(line);
+        result.push('\n');
+    }
+    if lines.len() > MAX_DIFF_LINES {
+        result.push_str(&format!("... {} more\n", lines.len() - MAX_DIFF_LINES));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::validate::check_round_trip;
+    use crate::validate::line_diff;
+    use crate::validate::RoundTripError;
+
+    #[test]
+    fn test_line_diff() {
+        assert_eq!(
+            line_diff(&["a", "b", "c"], &["a", "x", "c", "d"]),
+            "    2 -b\n    2 +x\n    4 +d\n"
+        );
+    }
+
+    #[test]
+    fn test_check_round_trip() {
+        assert!(check_round_trip("17\n", "\n", &17).is_ok());
+        let e = check_round_trip("17\nx\n", "\nx\n", &17).unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<RoundTripError>(),
+            Some(RoundTripError::Unconsumed(_))
+        ));
+        let e = check_round_trip("17\n", "", &18).unwrap_err();
+        assert!(matches!(
+            e.downcast_ref::<RoundTripError>(),
+            Some(RoundTripError::Mismatch(_))
+        ));
+    }
+}